@@ -3,11 +3,15 @@
 use ash::vk;
 use glam::{vec2, vec3, Mat4, Quat, Vec2, Vec3};
 use std::mem::size_of;
-use std::time;
-use vk_engine::engine_core::write_struct_to_buffer;
-use vk_engine::{default_descriptor_set_layout_bindings, init_window, BaseApp};
-use winit::event::{Event, VirtualKeyCode, WindowEvent};
-use winit::event_loop::ControlFlow;
+use vk_engine::camera::{Camera, OrbitController};
+use vk_engine::engine_core::UniformBuffer;
+use vk_engine::input::{Binding, Input};
+use vk_engine::shaders::Shader;
+use vk_engine::{
+    default_descriptor_set_layout_bindings, init_window, App, AppSettings, BaseApp, Flow,
+    FrameContext, PipelineDescription, VertexInputDescriptors,
+};
+use winit::event::VirtualKeyCode;
 
 const APP_TITLE: &str = "KK Engine Test App";
 
@@ -36,44 +40,45 @@ fn main() {
     let verts = vec![
         Vertex {
             pos: vec3(-0.5, -0.5, -0.5),
-            tex: vec2(0.0, 0.0),
+            tex: vec2(0.0, 1.0),
         },
         Vertex {
             pos: vec3(0.5, -0.5, -0.5),
-            tex: vec2(1.0, 0.0),
+            tex: vec2(1.0, 1.0),
         },
         Vertex {
             pos: vec3(-0.5, 0.5, -0.5),
-            tex: vec2(0.0, 1.0),
+            tex: vec2(0.0, 0.0),
         },
         Vertex {
             pos: vec3(0.5, 0.5, -0.5),
-            tex: vec2(1.0, 1.0),
+            tex: vec2(1.0, 0.0),
         },
         Vertex {
             pos: vec3(-0.5, -0.5, 0.5),
-            tex: vec2(1.0, 0.0),
+            tex: vec2(1.0, 1.0),
         },
         Vertex {
             pos: vec3(0.5, -0.5, 0.5),
-            tex: vec2(0.0, 0.0),
+            tex: vec2(0.0, 1.0),
         },
         Vertex {
             pos: vec3(-0.5, 0.5, 0.5),
-            tex: vec2(1.0, 1.0),
+            tex: vec2(1.0, 0.0),
         },
         Vertex {
             pos: vec3(0.5, 0.5, 0.5),
-            tex: vec2(0.0, 1.0),
+            tex: vec2(0.0, 0.0),
         },
     ];
+    // Counter-clockwise when seen from outside the cube
     let indices: Vec<u16> = vec![
-        0, 1, 2, 1, 3, 2, //front
-        5, 4, 6, 5, 6, 7, //back
-        4, 0, 6, 0, 2, 6, //left
-        1, 5, 3, 5, 7, 3, //right
-        4, 5, 0, 5, 1, 0, //top
-        2, 3, 6, 3, 7, 6, //bottom
+        0, 2, 1, 1, 2, 3, //front
+        5, 6, 4, 5, 7, 6, //back
+        4, 6, 0, 0, 6, 2, //left
+        1, 3, 5, 5, 3, 7, //right
+        4, 0, 5, 5, 0, 1, //bottom
+        2, 6, 3, 3, 6, 7, //top
     ];
 
     let num_indices = indices.len() as u32;
@@ -104,7 +109,7 @@ fn main() {
 
     let ubo_bindings = default_descriptor_set_layout_bindings();
 
-    let mut vulkan_app = BaseApp::new::<Vertex, u16, vk_engine::MVP>(
+    let mut vulkan_app = BaseApp::new_with_settings::<Vertex, u16>(
         window,
         APP_TITLE,
         &shaders_loaded,
@@ -112,160 +117,106 @@ fn main() {
         indices,
         &vertex_input_descriptors,
         ubo_bindings.clone(),
+        AppSettings::default().frame_stats_in_title(true),
     );
-
-    //Tracks which frame the CPU is currently writing commands for
-    //*Not* a framecounter, this value is mod MAX_FRAMES_IN_FLIGHT
-    let mut current_frame = 0;
-
-    //For the animation
-    let mut timer = time::Instant::now();
-    let speed = 0.3;
-    let mut spinning = true;
-    let mut theta = 0.0;
+    let uniforms = vulkan_app.create_uniform_buffer();
+    vulkan_app.bind_uniform_buffer(0, &uniforms);
+
+    let model_center = Vec3::new(0.0, 0.0, 2.0);
+    let mut camera = Camera::perspective(f32::to_radians(90.0), 0.01, None);
+    camera.look_at(Vec3::new(0.0, 1.0, 0.0), model_center);
+    let orbit = OrbitController::new(model_center, &camera);
+
+    let cube = Cube {
+        shaders: shaders_loaded,
+        vertex_input_descriptors,
+        ubo_bindings,
+        uniforms,
+        num_indices,
+        camera,
+        orbit,
+        model_center,
+        speed: 0.3,
+        spinning: true,
+        theta: 0.0,
+    };
 
     //The event loop hijacks the main thread, so once it closes the entire program exits.
-    //All cleanup operations should be handled either before the main loop, inside the mainloop,
-    //or in the drop function of any data moved into the closure
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                }
-                WindowEvent::KeyboardInput { input, .. } => match input.virtual_keycode {
-                    Some(VirtualKeyCode::Space) => {
-                        if input.state == winit::event::ElementState::Pressed {
-                            spinning = !spinning;
-                        }
-                    }
-                    Some(VirtualKeyCode::Escape) => {
-                        *control_flow = ControlFlow::Exit;
-                    }
-                    _ => (),
-                },
-                // On some platforms (occurs on Windows 10 as of writing), the swapchain is not marked as suboptimal/out-of-date when
-                // the window is resized, so here it is polled explicitly via winit to ensure the swapchain remains correctly sized
-                WindowEvent::Resized(_) => {
-                    vulkan_app.recreate_swapchain(
-                        &shaders_loaded,
-                        &vertex_input_descriptors,
-                        ubo_bindings.clone(),
-                    );
-                }
-                _ => (),
-            },
-            Event::MainEventsCleared => {
-                // Main body
-
-                // Wait for this frame's command buffer to finish execution (image presented)
-                vulkan_app.wait_for_in_flight_fence(current_frame);
-
-                // Acquire index of image from the swapchain, signal semaphore once finished
-                let (image_index, _) = match vulkan_app.acquire_next_image(current_frame) {
-                    Ok(i) => i,
-                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                        //Swapchain is outdated, recreate it before continuing
-                        vulkan_app.recreate_swapchain(
-                            &shaders_loaded,
-                            &vertex_input_descriptors,
-                            ubo_bindings.clone(),
-                        );
-                        return; //Exits current event loop iteration
-                    }
-                    _ => panic!("Could not acquire image from swapchain!"),
-                };
-
-                // Reset fence. This is done now, since if the swapchain is outdated, it causes an early return to the event loop
-                vulkan_app.reset_in_flight_fence(current_frame);
-
-                // Change time constant if spinning is enabled
-                if spinning {
-                    let time_delta = timer.elapsed();
-                    theta = (theta + time_delta.as_secs_f32() * speed) % (2.0 * std::f32::consts::PI);
-                }
-
-                let eye = Vec3::new(0.0, -1.0, 0.0);
-                let model_center = Vec3::new(0.0, 0.0, 2.0);
-                let up_direction = Vec3::new(0.0, -1.0, 0.0);
-                let aspect_ratio = vulkan_app.swapchain_extent.width as f32
-                    / vulkan_app.swapchain_extent.height as f32;
-
-                let model =
-                    Mat4::from_rotation_translation(Quat::from_rotation_y(theta), model_center);
-                // This look-at function assumes +Y is up, so the up-direction should be inverted to get Vulkan-like coordinates
-                let view = Mat4::look_at_rh(eye, model_center, -up_direction);
-                let projection =
-                    Mat4::perspective_infinite_rh(f32::to_radians(90.0), aspect_ratio, 0.01);
-
-                let ubo = vk_engine::MVP {
-                    model,
-                    view,
-                    projection,
-                };
-                // Copy data to uniform buffer
-                unsafe {
-                    write_struct_to_buffer(
-                        vulkan_app.uniform_buffers[current_frame]
-                            .memory_ptr
-                            .expect("Uniform buffer memory has not been mapped!"),
-                        &ubo as *const vk_engine::MVP,
-                    )
-                };
+    //The cube is dropped before the Vulkan app when the loop ends
+    vk_engine::run(event_loop, vulkan_app, cube);
+}
 
-                // Record drawing commands into command buffer for current frame
-                unsafe {
-                    vulkan_app.record_command_buffer(current_frame, |app| {
-                        vk_engine::drawing_commands(
-                            app,
-                            current_frame,
-                            image_index,
-                            |app| {
-                                app.logical_device.cmd_draw_indexed(
-                                    app.command_buffers[current_frame],
-                                    num_indices,
-                                    1,
-                                    0,
-                                    0,
-                                    0,
-                                );
-                            },
-                            &[0.0],
-                            vk::IndexType::UINT16
-                        );
-                    })
-                };
+struct Cube {
+    shaders: Vec<Shader>,
+    vertex_input_descriptors: VertexInputDescriptors,
+    ubo_bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    uniforms: UniformBuffer<vk_engine::MVP>,
+    num_indices: u32,
+    camera: Camera,
+    orbit: OrbitController,
+    model_center: Vec3,
+    //For the animation
+    speed: f32,
+    spinning: bool,
+    theta: f32,
+}
 
-                // Submit commands to render image
-                vulkan_app.submit_drawing_command_buffer(current_frame);
+impl App for Cube {
+    fn pipeline_description(&self) -> PipelineDescription<'_> {
+        PipelineDescription {
+            shaders: &self.shaders,
+            vertex_input_descriptors: &self.vertex_input_descriptors,
+            descriptor_set_bindings: &self.ubo_bindings,
+        }
+    }
 
-                // Present rendered image to the swap chain such that it will show up on screen
-                match vulkan_app
-                    .present_image(image_index, vulkan_app.sync.render_finished[current_frame])
-                {
-                    Ok(_) => (),
-                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                        //Swapchain might be outdated again
-                        vulkan_app.recreate_swapchain(
-                            &shaders_loaded,
-                            &vertex_input_descriptors,
-                            ubo_bindings.clone(),
-                        );
-                    }
-                    _ => panic!("Could not present image!"),
-                };
+    fn init(&mut self, _app: &mut BaseApp, input: &mut Input) {
+        input.bind_action("pause", Binding::Key(VirtualKeyCode::Space));
+    }
 
-                timer = time::Instant::now(); //Reset timer after frame is presented
-                current_frame = (current_frame + 1) % vk_engine::engine_core::MAX_FRAMES_IN_FLIGHT;
-                //Advance to next frame
-            }
-            Event::RedrawRequested(_) => { //Conditionally redraw (OS might request this too)
-            }
-            Event::LoopDestroyed => {
-                println!("Exiting event loop, should drop application");
-            }
-            _ => (),
+    fn update(&mut self, _app: &mut BaseApp, input: &Input, delta_time: f32) -> Flow {
+        if input.key_pressed(VirtualKeyCode::Escape) {
+            return Flow::Exit;
+        }
+        if input.action_pressed("pause") {
+            self.spinning = !self.spinning;
+        }
+        self.orbit.update(&mut self.camera, input);
+        // Change time constant if spinning is enabled
+        if self.spinning {
+            self.theta = (self.theta + delta_time * self.speed) % (2.0 * std::f32::consts::PI);
+        }
+        Flow::Continue
+    }
+
+    fn render(&mut self, frame: &mut FrameContext) {
+        let app = &mut *frame.app;
+        let aspect_ratio = app.swapchain_extent.width as f32 / app.swapchain_extent.height as f32;
+
+        let model =
+            Mat4::from_rotation_translation(Quat::from_rotation_y(self.theta), self.model_center);
+        let ubo = self.camera.mvp(model, aspect_ratio);
+        self.uniforms.write(frame.frame_index, &ubo);
+
+        let (frame_index, num_indices) = (frame.frame_index, self.num_indices);
+        unsafe {
+            vk_engine::drawing_commands(
+                app,
+                frame_index,
+                frame.image_index,
+                |app| {
+                    app.logical_device.cmd_draw_indexed(
+                        app.command_buffers[frame_index],
+                        num_indices,
+                        1,
+                        0,
+                        0,
+                        0,
+                    );
+                },
+                &[0.0],
+                vk::IndexType::UINT16,
+            );
         }
-    });
+    }
 }
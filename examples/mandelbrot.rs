@@ -3,10 +3,14 @@
 use ash::vk;
 use glam::{vec2, Vec2};
 use std::mem::size_of;
-use std::time;
-use vk_engine::{default_descriptor_set_layout_bindings, init_window, BaseApp};
-use winit::event::{Event, VirtualKeyCode, WindowEvent};
-use winit::event_loop::ControlFlow;
+use vk_engine::engine_core::{Std140, UniformBuffer};
+use vk_engine::input::{Binding, Input};
+use vk_engine::shaders::Shader;
+use vk_engine::{
+    default_descriptor_set_layout_bindings, init_window, App, BaseApp, Flow, FrameContext,
+    PipelineDescription, VertexInputDescriptors,
+};
+use winit::event::VirtualKeyCode;
 
 const APP_TITLE: &str = "KK Engine Test App";
 
@@ -53,8 +57,7 @@ fn main() {
 
     let ubo_bindings = default_descriptor_set_layout_bindings();
 
-    let mut vulkan_app = BaseApp::new::<Vec2, u16, u32>(
-        //Using u32 as uniform buffer type to put *some* sized type in
+    let mut vulkan_app = BaseApp::new::<Vec2, u16>(
         window,
         APP_TITLE,
         &shaders_loaded,
@@ -63,124 +66,97 @@ fn main() {
         &vertex_input_descriptors,
         ubo_bindings.clone(),
     );
+    let uniforms = vulkan_app.create_uniform_buffer();
+    vulkan_app.bind_uniform_buffer(0, &uniforms);
+
+    let mandelbrot = Mandelbrot {
+        shaders: shaders_loaded,
+        vertex_input_descriptors,
+        ubo_bindings,
+        uniforms,
+        push_constants: [0.0],
+        speed: 0.1,
+        zooming: true,
+    };
 
-    //Tracks which frame the CPU is currently writing commands for
-    //*Not* a framecounter, this value is mod MAX_FRAMES_IN_FLIGHT
-    let mut current_frame = 0;
+    //The event loop hijacks the main thread, so once it closes the entire program exits.
+    vk_engine::run(event_loop, vulkan_app, mandelbrot);
+}
 
+/// View of the fractal, written to the uniform buffer every frame.
+/// The shaders currently only read the zoom, from the push constants
+#[repr(C)]
+#[derive(Clone, Copy, Std140)]
+struct MandelbrotUniforms {
+    center: Vec2,
+    zoom: f32,
+    max_iterations: u32,
+}
+
+struct Mandelbrot {
+    shaders: Vec<Shader>,
+    vertex_input_descriptors: VertexInputDescriptors,
+    ubo_bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    uniforms: UniformBuffer<MandelbrotUniforms>,
     //For the animation
-    let mut push_constants = [0.0];
-    let mut timer = time::Instant::now();
-    let speed = 0.1;
-    let mut zooming = true;
+    push_constants: [f32; 1],
+    speed: f32,
+    zooming: bool,
+}
 
-    //The event loop hijacks the main thread, so once it closes the entire program exits.
-    //All cleanup operations should be handled either before the main loop, inside the mainloop,
-    //or in the drop function of any data moved into the closure
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Poll;
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                }
-                WindowEvent::KeyboardInput { input, .. } => match input.virtual_keycode {
-                    Some(VirtualKeyCode::Space) => {
-                        if input.state == winit::event::ElementState::Pressed {
-                            zooming = !zooming;
-                        }
-                    }
-                    Some(VirtualKeyCode::Escape) => {
-                        *control_flow = ControlFlow::Exit;
-                    }
-                    _ => (),
+impl App for Mandelbrot {
+    fn pipeline_description(&self) -> PipelineDescription<'_> {
+        PipelineDescription {
+            shaders: &self.shaders,
+            vertex_input_descriptors: &self.vertex_input_descriptors,
+            descriptor_set_bindings: &self.ubo_bindings,
+        }
+    }
+
+    fn init(&mut self, _app: &mut BaseApp, input: &mut Input) {
+        input.bind_action("pause", Binding::Key(VirtualKeyCode::Space));
+    }
+
+    fn update(&mut self, _app: &mut BaseApp, input: &Input, delta_time: f32) -> Flow {
+        if input.key_pressed(VirtualKeyCode::Escape) {
+            return Flow::Exit;
+        }
+        if input.action_pressed("pause") {
+            self.zooming = !self.zooming;
+        }
+        // Change time constant if zooming is enabled
+        if self.zooming {
+            self.push_constants[0] = (self.push_constants[0] + delta_time * self.speed) % 2.0;
+        }
+        Flow::Continue
+    }
+
+    fn render(&mut self, frame: &mut FrameContext) {
+        let frame_index = frame.frame_index;
+        let uniforms = MandelbrotUniforms {
+            center: Vec2::ZERO,
+            zoom: self.push_constants[0],
+            max_iterations: 300,
+        };
+        self.uniforms.write(frame_index, &uniforms);
+        unsafe {
+            vk_engine::drawing_commands(
+                frame.app,
+                frame_index,
+                frame.image_index,
+                |app| {
+                    app.logical_device.cmd_draw_indexed(
+                        app.command_buffers[frame_index],
+                        6,
+                        1,
+                        0,
+                        0,
+                        0,
+                    );
                 },
-                _ => (),
-            },
-            Event::MainEventsCleared => {
-                // Main body
-
-                // Wait for this frame's command buffer to finish execution (image presented)
-                vulkan_app.wait_for_in_flight_fence(current_frame);
-
-                // Acquire index of image from the swapchain, signal semaphore once finished
-                let (image_index, _) = match vulkan_app.acquire_next_image(current_frame) {
-                    Ok(i) => i,
-                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) | Err(vk::Result::SUBOPTIMAL_KHR) => {
-                        //Swapchain is outdated, recreate it before continuing
-                        vulkan_app.recreate_swapchain(
-                            &shaders_loaded,
-                            &vertex_input_descriptors,
-                            ubo_bindings.clone(),
-                        );
-                        return; //Exits current event loop iteration
-                    }
-                    _ => panic!("Could not acquire image from swapchain!"),
-                };
-
-                // Reset fence. This is done now, since if the swapchain is outdated, it causes an early return to the event loop
-                vulkan_app.reset_in_flight_fence(current_frame);
-
-                // Change time constant if zooming is enabled
-                if zooming {
-                    let time_delta = timer.elapsed();
-                    push_constants[0] =
-                        (push_constants[0] + time_delta.as_secs_f32() * speed) % 2.0;
-                }
-
-                // Record drawing commands into command buffer for current frame
-                unsafe {
-                    vulkan_app.record_command_buffer(current_frame, |app| {
-                        vk_engine::drawing_commands(
-                            app,
-                            current_frame,
-                            image_index,
-                            |app| {
-                                app.logical_device.cmd_draw_indexed(
-                                    app.command_buffers[current_frame],
-                                    6,
-                                    1,
-                                    0,
-                                    0,
-                                    0,
-                                );
-                            },
-                            &push_constants,
-                            vk::IndexType::UINT16
-                        );
-                    })
-                };
-
-                // Submit commands to render image
-                vulkan_app.submit_drawing_command_buffer(current_frame);
-
-                // Present rendered image to the swap chain such that it will show up on screen
-                match vulkan_app
-                    .present_image(image_index, vulkan_app.sync.render_finished[current_frame])
-                {
-                    Ok(_) => (),
-                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) | Err(vk::Result::SUBOPTIMAL_KHR) => {
-                        //Swapchain might be outdated again
-                        vulkan_app.recreate_swapchain(
-                            &shaders_loaded,
-                            &vertex_input_descriptors,
-                            ubo_bindings.clone(),
-                        );
-                        return;
-                    }
-                    _ => panic!("Could not present image!"),
-                };
-
-                timer = time::Instant::now(); //Reset timer after frame is presented
-                current_frame = (current_frame + 1) % vk_engine::engine_core::MAX_FRAMES_IN_FLIGHT;
-                //Advance to next frame
-            }
-            Event::RedrawRequested(_) => { //Conditionally redraw (OS might request this too)
-            }
-            Event::LoopDestroyed => {
-                println!("Exiting event loop, should drop application");
-            }
-            _ => (),
+                &self.push_constants,
+                vk::IndexType::UINT16,
+            );
         }
-    });
+    }
 }
@@ -1,20 +1,124 @@
 use crate::engine_core::{self, ManagedImage, ValidIndexBufferType, VertexInputDescriptors};
-use crate::engine_core::{MAX_FRAMES_IN_FLIGHT, VALIDATION_ENABLED, VALIDATION_LAYERS};
+use crate::engine_core::{BindlessTextures, DescriptorAllocator, DescriptorSetBuilder};
+use crate::engine_core::{DebugNamer, FrameStats, GpuProfiler, ProfileScope, ValidationHandler};
+use crate::engine_core::{DeletionQueue, MeshRegistry, StagingRing, UploadManager};
+use crate::engine_core::{DeviceContext, LogicalDevice, ThreadCommandPool};
+use crate::engine_core::{DeviceRequirements, DeviceSelector, EnabledFeatures, PhysicalDeviceInfo};
+use crate::engine_core::{IndirectBuffer, IndirectDrawSupport, InstanceBuffer, MeshHandle};
+use crate::engine_core::{
+    Std140, UniformBuffer, ValidationSettings, MAX_FRAMES_IN_FLIGHT, VALIDATION_LAYERS,
+};
 use ash::{
     extensions::{
         ext::DebugUtils,
         khr::{Surface, Swapchain},
     },
-    vk, {Device, Entry, Instance},
+    vk, Entry,
 };
 use ash_window;
 use glam::*;
 use raw_window_handle::{HasRawDisplayHandle, HasRawWindowHandle};
 use std::ffi::CString;
 use std::mem::ManuallyDrop;
-use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use winit::window::Window;
 
+// How often the window title is refreshed when it shows frame statistics
+const TITLE_UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Optional configuration of a [`BaseApp`], used with [`BaseApp::new_with_settings`].
+/// The default settings give the same behaviour as [`BaseApp::new`].
+#[derive(Clone)]
+pub struct AppSettings {
+    /// Which physical device to render with. Overridden by the `VK_ENGINE_DEVICE` environment variable
+    pub device_selector: DeviceSelector,
+    /// Device extensions and features to enable. Devices are rejected and scored based on these
+    pub device_requirements: DeviceRequirements,
+    /// Highest Vulkan version to use. The version actually used is capped by the loader and the chosen device,
+    /// see [`BaseApp::api_version`]. Defaults to Vulkan 1.0
+    pub api_version: u32,
+    /// Total size in bytes of [`BaseApp::staging_ring`], split between the frames in flight
+    pub staging_ring_size: vk::DeviceSize,
+    /// Append [`BaseApp::frame_stats`] to the window title, refreshed twice a second
+    pub frame_stats_in_title: bool,
+    /// Validation layer configuration. Overridden by the `VK_ENGINE_VALIDATION` environment variable
+    pub validation: ValidationSettings,
+    /// Bindings of descriptor sets 1 and up of the graphics pipeline, set 0 being the bindings given to [`BaseApp::new`].
+    /// Sets are made with [`BaseApp::descriptor_set_builder`] and bound with [`BaseApp::cmd_bind_descriptor_set`]
+    pub descriptor_set_layouts: Vec<Vec<vk::DescriptorSetLayoutBinding>>,
+    /// Capacity of the global bindless texture array, see [`BaseApp::bindless_textures`].
+    /// The features it needs are requested from the device, and it is left out where they are missing
+    pub bindless_textures: Option<u32>,
+}
+impl Default for AppSettings {
+    fn default() -> Self {
+        AppSettings {
+            device_selector: DeviceSelector::default(),
+            device_requirements: DeviceRequirements::default(),
+            api_version: vk::API_VERSION_1_0,
+            staging_ring_size: engine_core::DEFAULT_STAGING_RING_SIZE,
+            frame_stats_in_title: false,
+            validation: ValidationSettings::default(),
+            descriptor_set_layouts: Vec::new(),
+            bindless_textures: None,
+        }
+    }
+}
+impl AppSettings {
+    pub fn api_version(mut self, api_version: u32) -> Self {
+        self.api_version = api_version;
+        self
+    }
+
+    pub fn device_selector(mut self, device_selector: DeviceSelector) -> Self {
+        self.device_selector = device_selector;
+        self
+    }
+
+    pub fn device_requirements(mut self, device_requirements: DeviceRequirements) -> Self {
+        self.device_requirements = device_requirements;
+        self
+    }
+
+    pub fn staging_ring_size(mut self, staging_ring_size: vk::DeviceSize) -> Self {
+        self.staging_ring_size = staging_ring_size;
+        self
+    }
+
+    pub fn frame_stats_in_title(mut self, frame_stats_in_title: bool) -> Self {
+        self.frame_stats_in_title = frame_stats_in_title;
+        self
+    }
+
+    pub fn validation(mut self, validation: ValidationSettings) -> Self {
+        self.validation = validation;
+        self
+    }
+
+    pub fn descriptor_set_layouts(
+        mut self,
+        descriptor_set_layouts: Vec<Vec<vk::DescriptorSetLayoutBinding>>,
+    ) -> Self {
+        self.descriptor_set_layouts = descriptor_set_layouts;
+        self
+    }
+
+    pub fn bindless_textures(mut self, capacity: u32) -> Self {
+        self.bindless_textures = Some(capacity);
+        self
+    }
+}
+
+// Copy recorded at the start of the next command buffer, see BaseApp::record_command_buffer
+struct PendingBufferCopy {
+    src_buffer: vk::Buffer,
+    dst_buffer: vk::Buffer,
+    region: vk::BufferCopy,
+    // Growing a buffer copies its old contents, which must finish before later writes to the same range
+    barrier_after: bool,
+}
+
 /** Large struct for eased initialization and use of Vulkan for drawing to the screen.
 The struct has a lot of fields to ease cleanup of the Vulkan objects (cleaned when the struct is dropped in Rust fashion),
 as well as because many of the fields are dependant on one another, so keeping them organized together is vital to not lose track.
@@ -24,12 +128,42 @@ pub struct BaseApp {
     // Changing the order will likely cause bad cleanup behaviour.
     pub sync: engine_core::SyncPrims,
     pub command_buffers: Vec<vk::CommandBuffer>,
-    descriptor_pool: vk::DescriptorPool,
+    /// Allocates the frame descriptor sets, and is used for sets of [`AppSettings::descriptor_set_layouts`]
+    pub descriptor_allocator: ManuallyDrop<DescriptorAllocator>,
+    descriptor_set_bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    additional_set_layouts: Vec<vk::DescriptorSetLayout>,
+    /** Global array of textures indexed by shaders, bound as the last descriptor set by [`crate::drawing_commands`].
+    The app's texture is at [`BaseApp::texture_bindless_index`]. Present if requested through [`AppSettings::bindless_textures`] and supported */
+    pub bindless_textures: ManuallyDrop<Option<BindlessTextures>>,
     pub index_buffer: ManuallyDrop<engine_core::ManagedBuffer>,
     pub vertex_buffer: ManuallyDrop<engine_core::ManagedBuffer>,
-    pub uniform_buffers: ManuallyDrop<Vec<engine_core::ManagedBuffer>>,
+    /// Number of indices last given to [`BaseApp::set_indices`] or extended by [`BaseApp::update_indices`]
+    pub index_count: u32,
+    pub index_type: vk::IndexType,
+    vertex_buffer_size: vk::DeviceSize,
+    index_buffer_size: vk::DeviceSize,
+    pending_buffer_copies: Vec<PendingBufferCopy>,
+    /// Meshes added with [`BaseApp::add_mesh`]
+    pub meshes: ManuallyDrop<MeshRegistry>,
+    // Resources that in-flight frames may still use, destroyed once their frame has finished
+    deletion_queue: DeletionQueue,
+    frame_number: u64,
+    completed_frame_number: u64,
+    // The frame last submitted with each in-flight fence
+    fence_frame_numbers: [u64; MAX_FRAMES_IN_FLIGHT],
+    /// Bound at binding 1 of the frame descriptor sets, see [`BaseApp::replace_texture`]
     pub texture: ManuallyDrop<engine_core::ManagedImage>,
     pub texture_sampler: vk::Sampler,
+    texture_bindless_index: Option<u32>,
+    // Frame descriptor sets still bound to a replaced texture, rebound when their frame is next recorded
+    stale_texture_sets: [bool; MAX_FRAMES_IN_FLIGHT],
+    // Buffers bound to the frame descriptor sets, written again if the sets are reallocated
+    bound_buffers: Vec<FrameBufferBinding>,
+    pub uploads: ManuallyDrop<UploadManager>,
+    /// Per-frame staging memory for streaming data, see [`StagingRing`]
+    pub staging_ring: ManuallyDrop<StagingRing>,
+    /// Timestamp queries, begun for each frame by [`BaseApp::record_command_buffer`]
+    pub gpu_profiler: ManuallyDrop<GpuProfiler>,
     command_pool: vk::CommandPool,
     pub framebuffers: Vec<vk::Framebuffer>,
     pub render_pass: vk::RenderPass,
@@ -38,20 +172,35 @@ pub struct BaseApp {
     pub graphics_pipeline_layout: vk::PipelineLayout,
     pub graphics_pipeline: vk::Pipeline,
     image_views: Vec<vk::ImageView>,
-    depth_image: ManuallyDrop<ManagedImage>,
+    pub swapchain_images: Vec<vk::Image>,
+    pub(crate) depth_image: ManuallyDrop<ManagedImage>,
     pub swapchain: vk::SwapchainKHR,
     pub swapchain_extent: vk::Extent2D,
     swapchain_loader: Swapchain,
     pub graphics_queue: vk::Queue,
     present_queue: vk::Queue,
-    pub logical_device: Rc<Device>,
+    pub logical_device: Arc<LogicalDevice>,
+    queue_family_indices: engine_core::QueueFamilyIndices,
+    pub physical_device: vk::PhysicalDevice,
+    physical_device_info: PhysicalDeviceInfo,
+    device_requirements: DeviceRequirements,
+    enabled_features: EnabledFeatures,
+    indirect_draw_support: IndirectDrawSupport,
+    instance_api_version: u32,
+    api_version: u32,
     window: Window,
+    window_title: String,
+    frame_stats: FrameStats,
+    frame_stats_in_title: bool,
+    last_title_update: Instant,
     surface: vk::SurfaceKHR,
     surface_loader: Surface,
+    debug_namer: DebugNamer,
     _messenger: vk::DebugUtilsMessengerEXT,
+    // Must outlive the messenger
+    validation_handler: Box<ValidationHandler>,
+    validation_settings: ValidationSettings,
     _debug_loader: DebugUtils,
-    instance: Box<Instance>,
-    _entry: Box<Entry>,
 }
 impl Drop for BaseApp {
     fn drop(&mut self) {
@@ -60,12 +209,12 @@ impl Drop for BaseApp {
 
             self.sync.destroy(&self.logical_device);
 
-            self.logical_device
-                .destroy_descriptor_pool(self.descriptor_pool, None);
+            ManuallyDrop::drop(&mut self.descriptor_allocator);
+            ManuallyDrop::drop(&mut self.bindless_textures);
 
-            // Destroying this manually causes an error, guessing ash does it automatically on drop,
-            // which it otherwise doesn't with other objects
-            //self.logical_device.destroy_descriptor_set_layout(self.descriptor_set_layout.unwrap(), None);
+            // Kept across swapchain recreation, see BaseApp::replace_frame_descriptor_sets
+            self.logical_device
+                .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
 
             self.logical_device
                 .destroy_sampler(self.texture_sampler, None);
@@ -73,78 +222,122 @@ impl Drop for BaseApp {
             //Explicitly dropping buffers to ensure that the logical device still exists when they do
             ManuallyDrop::drop(&mut self.vertex_buffer);
             ManuallyDrop::drop(&mut self.index_buffer);
-            ManuallyDrop::drop(&mut self.uniform_buffers);
+            ManuallyDrop::drop(&mut self.meshes);
+            self.retire_swapchain_and_dependants();
+            self.deletion_queue.flush();
             ManuallyDrop::drop(&mut self.depth_image);
             ManuallyDrop::drop(&mut self.texture);
+            ManuallyDrop::drop(&mut self.uploads);
+            ManuallyDrop::drop(&mut self.staging_ring);
+            ManuallyDrop::drop(&mut self.gpu_profiler);
 
             self.logical_device
                 .destroy_command_pool(self.command_pool, None);
 
-            self.clean_swapchain_and_dependants();
-
-            self.logical_device.destroy_device(None);
-
-            if VALIDATION_ENABLED {
+            if self.validation_settings.enabled {
                 self._debug_loader
                     .destroy_debug_utils_messenger(self._messenger, None)
             }
 
             self.surface_loader.destroy_surface(self.surface, None);
-            self.instance.destroy_instance(None);
+            // The device and instance are destroyed along with the last reference to the logical device,
+            // which may be held by resources that outlive the app
         }
         eprintln!("Engine stopped successfully");
     }
 }
 
 impl BaseApp {
-    pub fn new<VertexType: Sized, IndexType: ValidIndexBufferType, UBOType: Sized>(
+    /** Creates the app with set 0 of the graphics pipeline laid out by `descriptor_set_bindings`.
+    A `COMBINED_IMAGE_SAMPLER` at binding 1 is bound to [`BaseApp::texture`]. Uniform buffers are owned by the caller,
+    see [`BaseApp::create_uniform_buffer`]. */
+    pub fn new<VertexType: Sized, IndexType: ValidIndexBufferType>(
         window: winit::window::Window,
         app_name: &str,
-        shaders: &Vec<crate::shaders::Shader>,
+        shaders: &[crate::shaders::Shader],
         vertices: Vec<VertexType>,
         indices: Vec<IndexType>,
         vertex_input_descriptors: &VertexInputDescriptors,
         descriptor_set_bindings: Vec<vk::DescriptorSetLayoutBinding>,
     ) -> BaseApp {
-        let entry = Box::new(unsafe { Entry::load() }.unwrap());
-        if VALIDATION_ENABLED && !engine_core::check_validation_layer_support(&entry) {
-            panic!("Validation layer requested but not available!");
-        }
+        Self::new_with_settings::<VertexType, IndexType>(
+            window,
+            app_name,
+            shaders,
+            vertices,
+            indices,
+            vertex_input_descriptors,
+            descriptor_set_bindings,
+            AppSettings::default(),
+        )
+    }
+
+    /// Same as [`BaseApp::new`], with additional configuration given by `settings`
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_settings<VertexType: Sized, IndexType: ValidIndexBufferType>(
+        window: winit::window::Window,
+        app_name: &str,
+        shaders: &[crate::shaders::Shader],
+        vertices: Vec<VertexType>,
+        indices: Vec<IndexType>,
+        vertex_input_descriptors: &VertexInputDescriptors,
+        descriptor_set_bindings: Vec<vk::DescriptorSetLayoutBinding>,
+        settings: AppSettings,
+    ) -> BaseApp {
+        let entry = unsafe { Entry::load() }.unwrap();
+        let validation_settings = ValidationSettings::from_env()
+            .unwrap_or(settings.validation)
+            .supported(&entry);
+
+        let window_title = app_name.to_owned();
 
         //// Application info
         let app_name = CString::new(app_name).unwrap();
         let engine_name = CString::new("KK Engine").unwrap();
 
+        let instance_api_version =
+            engine_core::negotiate_instance_version(&entry, settings.api_version);
         let app_info = vk::ApplicationInfo::builder()
             .application_name(&app_name)
             .application_version(vk::make_api_version(0, 1, 0, 0))
             .engine_name(&engine_name)
-            .engine_version(vk::API_VERSION_1_0)
-            .api_version(vk::API_VERSION_1_0);
+            .engine_version(engine_core::engine_version())
+            .api_version(instance_api_version);
 
         let mut instance_extensions =
             ash_window::enumerate_required_extensions(window.raw_display_handle())
                 .unwrap()
                 .to_vec();
-        if VALIDATION_ENABLED {
+        if validation_settings.enabled {
             instance_extensions.push(DebugUtils::name().as_ptr());
         }
+        let enabled_validation_features = validation_settings.enabled_validation_features();
+        if !enabled_validation_features.is_empty() {
+            instance_extensions.push(vk::ExtValidationFeaturesFn::name().as_ptr());
+        }
 
         //// Instance & debug messenger
-        let mut messenger_info = engine_core::init_debug_messenger_info();
+        // Boxed so the messenger's pointer to it stays valid when the app is moved
+        let validation_handler = Box::new(ValidationHandler::new());
+        let mut messenger_info = engine_core::init_debug_messenger_info().user_data(
+            &*validation_handler as *const ValidationHandler as *mut std::os::raw::c_void,
+        );
         let mut instance_info = vk::InstanceCreateInfo::builder()
             .application_info(&app_info)
             .enabled_extension_names(&instance_extensions);
-        if VALIDATION_ENABLED {
+        let mut validation_features = vk::ValidationFeaturesEXT::builder()
+            .enabled_validation_features(&enabled_validation_features);
+        if validation_settings.enabled {
             instance_info = instance_info
                 .enabled_layer_names(&VALIDATION_LAYERS)
                 .push_next(&mut messenger_info);
         }
-        let instance = Box::new(
-            unsafe { entry.create_instance(&instance_info, None) }
-                .expect("Failed to create Vulkan instance!"),
-        );
-        let (_debug_loader, _messenger) = if VALIDATION_ENABLED {
+        if !enabled_validation_features.is_empty() {
+            instance_info = instance_info.push_next(&mut validation_features);
+        }
+        let instance = unsafe { entry.create_instance(&instance_info, None) }
+            .expect("Failed to create Vulkan instance!");
+        let (_debug_loader, _messenger) = if validation_settings.enabled {
             //Messenger attached
             let debug_loader = DebugUtils::new(&entry, &instance);
             let messenger =
@@ -172,12 +365,41 @@ impl BaseApp {
         .unwrap();
 
         //// Physical device and queues
-        let (physical_device, queue_family_indices) =
-            engine_core::find_physical_device(&instance, &surface_loader, &surface);
+        let device_requirements = match settings.bindless_textures {
+            Some(_) => settings
+                .device_requirements
+                .request_features(&BindlessTextures::required_features()),
+            None => settings.device_requirements,
+        };
+        let (physical_device_info, queue_family_indices) = engine_core::find_physical_device(
+            &instance,
+            &surface_loader,
+            &surface,
+            &device_requirements,
+            instance_api_version,
+            &settings.device_selector,
+        );
+        let physical_device = physical_device_info.handle;
+        let api_version = engine_core::api_version_without_patch(physical_device_info.api_version)
+            .min(instance_api_version);
 
         //// Logical device
-        let logical_device =
-            engine_core::create_logical_device(&instance, &physical_device, queue_family_indices);
+        let enabled_features = device_requirements
+            .negotiate(&instance, physical_device, api_version)
+            .unwrap(); //Checked in device_suitability, so will always succeed
+        let dynamic_rendering = enabled_features.features.vulkan13.dynamic_rendering == vk::TRUE;
+        let indirect_draw_support =
+            IndirectDrawSupport::new(&instance, &physical_device, &enabled_features);
+        let logical_device = Arc::new(LogicalDevice::new(
+            entry.clone(),
+            instance.clone(),
+            engine_core::create_logical_device(
+                &instance,
+                &physical_device,
+                queue_family_indices,
+                &enabled_features,
+            ),
+        ));
         let (graphics_queue, present_queue) =
             engine_core::get_queue_handles(&logical_device, queue_family_indices);
 
@@ -191,6 +413,7 @@ impl BaseApp {
                 &physical_device,
                 &swapchain_loader,
                 queue_family_indices,
+                vk::SwapchainKHR::null(),
             );
 
         //// Image views
@@ -203,17 +426,45 @@ impl BaseApp {
         //// Push constants
         let push_constants = [1.0];
 
+        //// Descriptor set layouts beyond set 0, with the bindless textures last
+        let mut descriptor_allocator = DescriptorAllocator::new(&logical_device);
+        let mut additional_set_layouts: Vec<vk::DescriptorSetLayout> = settings
+            .descriptor_set_layouts
+            .iter()
+            .map(|bindings| descriptor_allocator.create_layout(bindings))
+            .collect();
+        let mut bindless_textures = match settings.bindless_textures {
+            Some(capacity) if BindlessTextures::is_supported(&enabled_features) => Some(
+                BindlessTextures::new(&instance, &physical_device, &logical_device, capacity),
+            ),
+            Some(_) => {
+                log::warn!(
+                    "Bindless textures are not supported by the device, continuing without them"
+                );
+                None
+            }
+            None => None,
+        };
+        if let Some(bindless_textures) = &bindless_textures {
+            additional_set_layouts.push(bindless_textures.layout());
+        }
+
         //// Graphics pipeline
-        let (graphics_pipeline, graphics_pipeline_layout, descriptor_set_layout, render_pass) =
+        let descriptor_set_layout =
+            engine_core::create_descriptor_set_layout(&logical_device, &descriptor_set_bindings);
+        let (graphics_pipeline, graphics_pipeline_layout, render_pass) =
             engine_core::create_graphics_pipeline(
                 &logical_device,
                 swapchain_extent,
                 image_format,
-                &shaders,
+                shaders,
                 vertex_input_descriptors,
-                descriptor_set_bindings,
+                descriptor_set_layout,
+                &additional_set_layouts,
                 push_constants,
+                dynamic_rendering,
             );
+        descriptor_allocator.register_layout(descriptor_set_layout, &descriptor_set_bindings);
 
         //// Depth image
         // Could check for supported formats for depth, but for now just going with D32_SFLOAT
@@ -229,14 +480,18 @@ impl BaseApp {
             (swapchain_extent.width, swapchain_extent.height),
         );
 
-        //// Framebuffers
-        let framebuffers = engine_core::create_framebuffers(
-            &logical_device,
-            render_pass,
-            swapchain_extent,
-            &image_views,
-            depth_image.image_view,
-        );
+        //// Framebuffers, not used with dynamic rendering
+        let framebuffers = if dynamic_rendering {
+            Vec::new()
+        } else {
+            engine_core::create_framebuffers(
+                &logical_device,
+                render_pass,
+                swapchain_extent,
+                &image_views,
+                depth_image.image_view,
+            )
+        };
 
         //// Command pool and buffers
         let command_pool_info = vk::CommandPoolCreateInfo::builder()
@@ -245,74 +500,34 @@ impl BaseApp {
         let command_pool = unsafe { logical_device.create_command_pool(&command_pool_info, None) }
             .expect("Could not create command pool!");
 
-        let vertex_buffer = engine_core::create_vertex_buffer(
+        //// Uploads, through the dedicated transfer queue if there is one
+        let mut uploads = UploadManager::new(
             &instance,
             &physical_device,
             &logical_device,
-            (std::mem::size_of::<VertexType>() * vertices.len()) as u64,
+            queue_family_indices,
+            graphics_queue,
+            engine_core::get_transfer_queue(&logical_device, queue_family_indices),
+            enabled_features.features.vulkan12.timeline_semaphore == vk::TRUE,
         );
-        {
-            let vert_len = vertices.len();
-
-            let mut staging_buffer = engine_core::create_staging_buffer(
-                &instance,
-                &physical_device,
-                &logical_device,
-                (std::mem::size_of::<VertexType>() * vert_len) as u64,
-            );
-            staging_buffer.map_buffer_memory();
-
-            unsafe {
-                engine_core::write_vec_to_buffer(staging_buffer.memory_ptr.unwrap(), &vertices)
-            };
-            engine_core::copy_buffer(
-                &logical_device,
-                command_pool,
-                graphics_queue,
-                *staging_buffer,
-                *vertex_buffer,
-                (std::mem::size_of::<VertexType>() * vert_len) as u64,
-            );
-        }
 
-        let index_buffer = engine_core::create_index_buffer::<IndexType>(
+        let vertex_buffer_size = std::mem::size_of_val(vertices.as_slice()) as vk::DeviceSize;
+        let vertex_buffer = engine_core::create_vertex_buffer(
             &instance,
             &physical_device,
             &logical_device,
-            indices.len(),
+            vertex_buffer_size,
         );
-        {
-            let indices_len = indices.len();
-
-            let mut staging_buffer = engine_core::create_staging_buffer(
-                &instance,
-                &physical_device,
-                &logical_device,
-                (std::mem::size_of::<IndexType>() * indices_len) as u64,
-            );
-            staging_buffer.map_buffer_memory();
-
-            unsafe {
-                engine_core::write_vec_to_buffer(staging_buffer.memory_ptr.unwrap(), &indices)
-            };
-            engine_core::copy_buffer(
-                &logical_device,
-                command_pool,
-                graphics_queue,
-                *staging_buffer,
-                *index_buffer,
-                (std::mem::size_of::<IndexType>() * indices_len) as u64,
-            );
-        }
+        uploads.upload_buffer(&vertices, *vertex_buffer, 0);
 
-        //// Uniform buffers
-        let uniform_buffers = engine_core::create_uniform_buffers(
+        let index_buffer_size = std::mem::size_of_val(indices.as_slice()) as vk::DeviceSize;
+        let index_buffer = engine_core::create_index_buffer::<IndexType>(
             &instance,
             &physical_device,
             &logical_device,
-            std::mem::size_of::<UBOType>() as u64,
-            MAX_FRAMES_IN_FLIGHT,
+            indices.len(),
         );
+        uploads.upload_buffer(&indices, *index_buffer, 0);
 
         //// Command buffers
         let command_buffers = engine_core::allocate_command_buffers(
@@ -322,9 +537,27 @@ impl BaseApp {
         );
 
         //// Texture image
-        let texture = engine_core::load_image_immediate(&instance, &physical_device, &logical_device, command_pool, graphics_queue, "texture.jpg");
+        let texture = engine_core::load_image(
+            &instance,
+            &physical_device,
+            &logical_device,
+            &mut uploads,
+            "texture.jpg",
+        );
+        // All initial data goes in one batch, acquired by the graphics queue when the first frame is recorded
+        let initial_uploads = uploads.submit();
+        uploads.wait(initial_uploads);
+
+        let staging_ring = StagingRing::new(
+            &instance,
+            &physical_device,
+            &logical_device,
+            settings.staging_ring_size,
+            MAX_FRAMES_IN_FLIGHT,
+        );
 
         let texture_sampler = {
+            let anisotropy_enabled = enabled_features.features.core.sampler_anisotropy == vk::TRUE;
             let max_anisotropy =
                 unsafe { instance.get_physical_device_properties(physical_device) }
                     .limits
@@ -335,8 +568,13 @@ impl BaseApp {
                 .address_mode_u(vk::SamplerAddressMode::REPEAT)
                 .address_mode_v(vk::SamplerAddressMode::REPEAT)
                 .address_mode_w(vk::SamplerAddressMode::REPEAT)
-                .anisotropy_enable(true)
-                .max_anisotropy(max_anisotropy)
+                // Anisotropy is optional, so fall back to plain filtering where it isn't enabled
+                .anisotropy_enable(anisotropy_enabled)
+                .max_anisotropy(if anisotropy_enabled {
+                    max_anisotropy
+                } else {
+                    1.0
+                })
                 .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
                 .unnormalized_coordinates(false)
                 .compare_enable(false)
@@ -349,74 +587,63 @@ impl BaseApp {
                 .expect("Could not create texture sampler")
         };
 
-        //// Descriptor pool
-        let descriptor_pool = {
-            let pool_sizes = [
-                *vk::DescriptorPoolSize::builder()
-                    .ty(vk::DescriptorType::UNIFORM_BUFFER)
-                    .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32),
-                *vk::DescriptorPoolSize::builder()
-                    .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-                    .descriptor_count(MAX_FRAMES_IN_FLIGHT as u32),
-            ];
-            let pool_info = vk::DescriptorPoolCreateInfo::builder()
-                .pool_sizes(&pool_sizes)
-                .max_sets(MAX_FRAMES_IN_FLIGHT as u32);
-            unsafe { logical_device.create_descriptor_pool(&pool_info, None) }
-                .expect("Failed to create descriptor pool")
-        };
+        let texture_bindless_index = bindless_textures
+            .as_mut()
+            .map(|bindless_textures| bindless_textures.add(texture.image_view, texture_sampler));
 
         //// Descriptor sets
-        let descriptor_sets = {
-            let layouts = vec![descriptor_set_layout; MAX_FRAMES_IN_FLIGHT];
-            let alloc_info = vk::DescriptorSetAllocateInfo::builder()
-                .descriptor_pool(descriptor_pool)
-                .set_layouts(layouts.as_slice());
-            unsafe { logical_device.allocate_descriptor_sets(&alloc_info) }
-                .expect("Failed to allocate descriptor sets")
-        };
-        let descriptor_writes = {
-            let mut v = Vec::with_capacity(descriptor_sets.len());
-            for (i, set) in descriptor_sets.iter().enumerate() {
-                let descriptor_buffer_info = [*vk::DescriptorBufferInfo::builder()
-                    .buffer(*uniform_buffers[i])
-                    .offset(0)
-                    .range(std::mem::size_of::<UBOType>() as u64)];
-                let descriptor_image_info = [*vk::DescriptorImageInfo::builder()
-                    .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                    .image_view(texture.image_view)
-                    .sampler(texture_sampler)];
-                v.push(
-                    *vk::WriteDescriptorSet::builder()
-                        .dst_set(*set)
-                        .dst_binding(0)
-                        .dst_array_element(0)
-                        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-                        .buffer_info(&descriptor_buffer_info),
-                );
-                v.push(
-                    *vk::WriteDescriptorSet::builder()
-                        .dst_set(*set)
-                        .dst_binding(1)
-                        .dst_array_element(0)
-                        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-                        .image_info(&descriptor_image_info),
-                );
-            }
-            v
-        };
-        unsafe { logical_device.update_descriptor_sets(&descriptor_writes, &[]) }
+        // The texture is only bound if the layout has room for it
+        let descriptor_sets: Vec<vk::DescriptorSet> = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                let mut builder = DescriptorSetBuilder::new(&descriptor_set_bindings);
+                if builder.descriptor_type(1) == Some(vk::DescriptorType::COMBINED_IMAGE_SAMPLER) {
+                    builder = builder.image(
+                        1,
+                        texture.image_view,
+                        texture_sampler,
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    );
+                }
+                builder.build(&mut descriptor_allocator, descriptor_set_layout)
+            })
+            .collect();
+
+        let debug_namer = DebugNamer::new(
+            validation_settings.enabled.then(|| _debug_loader.clone()),
+            logical_device.handle(),
+        );
+
+        let gpu_profiler = GpuProfiler::new(
+            &instance,
+            &physical_device,
+            &logical_device,
+            queue_family_indices.graphics_queue,
+            engine_core::DEFAULT_MAX_PROFILE_SCOPES,
+        );
 
         //// Create semaphores for in-render-pass synchronization
         let sync = engine_core::create_sync_primitives(&logical_device);
 
-        BaseApp {
-            _entry: entry,
-            instance,
+        let app = BaseApp {
             logical_device,
+            queue_family_indices,
+            physical_device,
+            physical_device_info,
+            device_requirements,
+            enabled_features,
+            indirect_draw_support,
+            instance_api_version,
+            api_version,
+            debug_namer,
             _debug_loader,
             _messenger,
+            validation_handler,
+            validation_settings,
             window,
+            window_title,
+            frame_stats: FrameStats::default(),
+            frame_stats_in_title: settings.frame_stats_in_title,
+            last_title_update: Instant::now(),
             surface_loader,
             surface,
             graphics_queue,
@@ -425,6 +652,7 @@ impl BaseApp {
             swapchain,
             swapchain_extent,
             image_views,
+            swapchain_images,
             depth_image: ManuallyDrop::new(depth_image),
             graphics_pipeline,
             graphics_pipeline_layout,
@@ -435,15 +663,383 @@ impl BaseApp {
             command_pool,
             vertex_buffer: ManuallyDrop::new(vertex_buffer),
             index_buffer: ManuallyDrop::new(index_buffer),
-            uniform_buffers: ManuallyDrop::new(uniform_buffers),
+            index_count: indices.len() as u32,
+            index_type: IndexType::INDEX_TYPE,
+            vertex_buffer_size,
+            index_buffer_size,
+            pending_buffer_copies: Vec::new(),
+            meshes: ManuallyDrop::new(MeshRegistry::new()),
+            deletion_queue: DeletionQueue::new(),
+            frame_number: 0,
+            completed_frame_number: 0,
+            fence_frame_numbers: [0; MAX_FRAMES_IN_FLIGHT],
             texture: ManuallyDrop::new(texture),
             texture_sampler,
-            descriptor_pool,
+            texture_bindless_index,
+            stale_texture_sets: [false; MAX_FRAMES_IN_FLIGHT],
+            bound_buffers: Vec::new(),
+            uploads: ManuallyDrop::new(uploads),
+            staging_ring: ManuallyDrop::new(staging_ring),
+            gpu_profiler: ManuallyDrop::new(gpu_profiler),
+            descriptor_allocator: ManuallyDrop::new(descriptor_allocator),
+            descriptor_set_bindings,
+            additional_set_layouts,
+            bindless_textures: ManuallyDrop::new(bindless_textures),
             command_buffers,
             sync,
+        };
+        app.name_engine_objects();
+        app
+    }
+
+    /** Times the commands recorded into the command buffer at `buffer_index` until the returned guard is dropped,
+    see [`GpuProfiler::profile_scope`]. Results are read from [`BaseApp::gpu_profiler`].
+    # Safety
+    Only call inside [`BaseApp::record_command_buffer`], and drop the guard before the closure returns. */
+    pub unsafe fn profile_scope(&self, buffer_index: usize, name: &str) -> ProfileScope {
+        self.gpu_profiler
+            .profile_scope(self.command_buffers[buffer_index], name)
+    }
+
+    /** Where validation messages go. Set a callback or capture messages here, for example to check that a frame
+    produced no validation errors:
+    ```ignore
+    app.validation().start_capture();
+    // Render a frame and wait for it
+    assert!(app.validation().stop_capture().iter().all(|message| !message.is_error()));
+    ```
+    Receives nothing when validation is off. */
+    pub fn validation(&self) -> &ValidationHandler {
+        &self.validation_handler
+    }
+
+    /// The validation actually enabled, after applying `VK_ENGINE_VALIDATION` and dropping what the system lacks
+    pub fn validation_settings(&self) -> ValidationSettings {
+        self.validation_settings
+    }
+
+    /// Names objects and labels command buffers for debugging tools, does nothing when validation is off
+    pub fn debug_namer(&self) -> &DebugNamer {
+        &self.debug_namer
+    }
+
+    /** Points the `STORAGE_BUFFER` descriptor at `binding` of every frame's descriptor set to the whole of `buffer`.
+    Descriptor sets may not be updated while in use, so only call this before rendering starts, e.g. in [`App::init`](crate::App::init),
+    or after waiting for the device to be idle. */
+    pub fn bind_storage_buffer(&mut self, binding: u32, buffer: vk::Buffer) {
+        let info = *vk::DescriptorBufferInfo::builder()
+            .buffer(buffer)
+            .offset(0)
+            .range(vk::WHOLE_SIZE);
+        self.bind_frame_buffers(binding, [info; MAX_FRAMES_IN_FLIGHT]);
+    }
+
+    /// Layout of descriptor set `set` of the graphics pipeline. Set 0 holds the frame descriptor sets
+    pub fn set_layout(&self, set: u32) -> vk::DescriptorSetLayout {
+        match set {
+            0 => self.descriptor_set_layout,
+            _ => *self
+                .additional_set_layouts
+                .get(set as usize - 1)
+                .expect("No such descriptor set in the pipeline layout!"),
+        }
+    }
+
+    /** Builder for sets of layout `set`, to be built with [`BaseApp::descriptor_allocator`], e.g.
+    ```ignore
+    let material_set = app
+        .descriptor_set_builder(1)
+        .image(0, texture.image_view, sampler, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+        .build(&mut app.descriptor_allocator, app.set_layout(1));
+    ``` */
+    pub fn descriptor_set_builder(&self, set: u32) -> DescriptorSetBuilder {
+        let bindings = match set {
+            0 => self.descriptor_set_bindings.as_slice(),
+            _ => self
+                .descriptor_allocator
+                .layout_bindings(self.set_layout(set))
+                .expect("Sets of this layout are not made through the descriptor allocator!"),
+        };
+        DescriptorSetBuilder::new(bindings)
+    }
+
+    /** Binds `descriptor_set` as set `set` of the graphics pipeline.
+    # Safety
+    The command buffer of `buffer_index` must be recording, and `descriptor_set` must have the layout of `set`. */
+    pub unsafe fn cmd_bind_descriptor_set(
+        &self,
+        buffer_index: usize,
+        set: u32,
+        descriptor_set: vk::DescriptorSet,
+    ) {
+        self.logical_device.cmd_bind_descriptor_sets(
+            self.command_buffers[buffer_index],
+            vk::PipelineBindPoint::GRAPHICS,
+            self.graphics_pipeline_layout,
+            set,
+            &[descriptor_set],
+            &[],
+        );
+    }
+
+    /// Index of the bindless texture set in the graphics pipeline layout, if [`BaseApp::bindless_textures`] is present
+    pub fn bindless_set_index(&self) -> Option<u32> {
+        self.bindless_textures
+            .as_ref()
+            .map(|_| self.additional_set_layouts.len() as u32)
+    }
+
+    /// Adds a texture to [`BaseApp::bindless_textures`] with the app's sampler, returning its index.
+    /// Panics if bindless textures are not in use
+    pub fn add_bindless_texture(&mut self, image_view: vk::ImageView) -> u32 {
+        self.bindless_textures
+            .as_mut()
+            .expect("Bindless textures are not enabled!")
+            .add(image_view, self.texture_sampler)
+    }
+
+    /// Index of [`BaseApp::texture`] in [`BaseApp::bindless_textures`], if present. 0 until the texture is replaced
+    pub fn texture_bindless_index(&self) -> Option<u32> {
+        self.texture_bindless_index
+    }
+
+    /** Replaces [`BaseApp::texture`] without waiting for the frames in flight that still sample the old one.
+    Each frame's descriptor set is rebound when that frame is next recorded, and the old texture is destroyed
+    through the deletion queue once the frames using it have finished.
+    The bindless array cannot change an index that frames in flight read, so there the texture gets a new index,
+    see [`BaseApp::texture_bindless_index`], and the old index is freed with the old texture. */
+    pub fn replace_texture(&mut self, texture: ManagedImage) {
+        self.debug_namer.set_name(texture.image, "texture");
+        self.debug_namer
+            .set_name(texture.image_view, "texture view");
+        if let Some(bindless_textures) = self.bindless_textures.as_mut() {
+            let index = bindless_textures.add(texture.image_view, self.texture_sampler);
+            let old_index = self.texture_bindless_index.replace(index).unwrap();
+            let remove_old_index = bindless_textures.remove_later(old_index);
+            self.deletion_queue
+                .push_fn(self.frame_number + 1, remove_old_index);
+        }
+        self.stale_texture_sets.fill(true);
+
+        let old_texture = std::mem::replace(&mut *self.texture, texture);
+        // The current frame may have been submitted already, in which case it still samples the old texture
+        self.deletion_queue.push(self.frame_number + 1, old_texture);
+    }
+
+    /** Pushes texture indices for the following draws, see [`engine_core::cmd_push_texture_indices`].
+    # Safety
+    The command buffer of `buffer_index` must be recording. */
+    pub unsafe fn cmd_push_texture_indices(&self, buffer_index: usize, indices: &[u32]) {
+        engine_core::cmd_push_texture_indices(
+            &self.logical_device,
+            self.command_buffers[buffer_index],
+            self.graphics_pipeline_layout,
+            indices,
+        );
+    }
+
+    /// Number of the frame being prepared, counting up from 1 with every [`BaseApp::wait_for_in_flight_fence`]
+    pub fn frame_number(&self) -> u64 {
+        self.frame_number
+    }
+
+    /// Number of the last frame known to have finished on the GPU
+    pub fn completed_frame_number(&self) -> u64 {
+        self.completed_frame_number
+    }
+
+    /// Handles to share with loading and recording threads, see [`DeviceContext`]
+    pub fn device_context(&self) -> DeviceContext {
+        DeviceContext {
+            physical_device: self.physical_device,
+            logical_device: Arc::clone(&self.logical_device),
+            queue_family_indices: self.queue_family_indices,
+        }
+    }
+
+    /// Creates a command pool for the graphics queue family, to be moved to the thread that records with it
+    pub fn create_thread_command_pool(&self) -> ThreadCommandPool {
+        ThreadCommandPool::new(
+            &self.logical_device,
+            self.queue_family_indices.graphics_queue,
+        )
+    }
+
+    /** Submits primary command buffers recorded on other threads to the graphics queue, ahead of the next drawing submission.
+    Returns the frame number after which they have finished executing, see [`BaseApp::completed_frame_number`].
+    # Safety
+    The command buffers must be fully recorded from pools of the graphics queue family,
+    and their pools must not be used by other threads until this returns. */
+    pub unsafe fn submit_command_buffers(&mut self, command_buffers: &[vk::CommandBuffer]) -> u64 {
+        let submits = [*vk::SubmitInfo::builder().command_buffers(command_buffers)];
+        self.logical_device
+            .queue_submit(self.graphics_queue, &submits, vk::Fence::null())
+            .expect("Queue submission failed!");
+        // Finished along with the current frame, unless it has already been submitted
+        if self.fence_frame_numbers.contains(&self.frame_number) {
+            self.frame_number + 1
+        } else {
+            self.frame_number
         }
     }
 
+    /** Drops `resource` once every frame submitted so far has finished, instead of waiting for the device to be idle.
+    For replacing buffers, images and other resources that frames in flight may still use */
+    pub fn destroy_later<T: Send + 'static>(&mut self, resource: T) {
+        self.deletion_queue.push(self.frame_number, resource);
+    }
+
+    /// Runs `destroy` once every frame submitted so far has finished, for destroying raw Vulkan handles
+    pub fn destroy_later_with<F: FnOnce() + Send + 'static>(&mut self, destroy: F) {
+        self.deletion_queue.push_fn(self.frame_number, destroy);
+    }
+
+    /// Creates a uniform buffer holding a `T` per frame in flight, to be bound with [`BaseApp::bind_uniform_buffer`]
+    pub fn create_uniform_buffer<T: Std140>(&self) -> UniformBuffer<T> {
+        let uniform_buffer = UniformBuffer::new(
+            self.logical_device.instance(),
+            &self.physical_device,
+            &self.logical_device,
+        );
+        for frame in 0..MAX_FRAMES_IN_FLIGHT {
+            self.debug_namer
+                .set_name(uniform_buffer.buffer(frame), "uniform buffer");
+        }
+        uniform_buffer
+    }
+
+    /** Points the `UNIFORM_BUFFER` descriptor at `binding` of each frame's descriptor set to that frame's buffer of `uniform_buffer`.
+    Same restrictions as [`BaseApp::bind_storage_buffer`]: only call before rendering starts or while the device is idle. */
+    pub fn bind_uniform_buffer<T: Std140>(
+        &mut self,
+        binding: u32,
+        uniform_buffer: &UniformBuffer<T>,
+    ) {
+        let infos = std::array::from_fn(|frame| uniform_buffer.descriptor_buffer_info(frame));
+        self.bind_frame_buffers(binding, infos);
+    }
+
+    // Writes a buffer per frame to `binding` of the frame descriptor sets, remembered for BaseApp::replace_frame_descriptor_sets.
+    // Panics if `binding` is not a buffer descriptor of the layout
+    fn bind_frame_buffers(
+        &mut self,
+        binding: u32,
+        infos: [vk::DescriptorBufferInfo; MAX_FRAMES_IN_FLIGHT],
+    ) {
+        for (set, info) in self.descriptor_sets.iter().zip(&infos) {
+            let builder = DescriptorSetBuilder::new(&self.descriptor_set_bindings).buffer(
+                binding,
+                info.buffer,
+                info.offset,
+                info.range,
+            );
+            unsafe { builder.write(&self.logical_device, *set) };
+        }
+        let descriptor_type = DescriptorSetBuilder::new(&self.descriptor_set_bindings)
+            .descriptor_type(binding)
+            .unwrap();
+        self.bound_buffers.retain(|bound| bound.binding != binding);
+        self.bound_buffers.push(FrameBufferBinding {
+            binding,
+            descriptor_type,
+            infos,
+        });
+    }
+
+    /// Gives the object behind `handle` a name shown in validation messages and debugging tools, see [`DebugNamer`]
+    pub fn set_debug_name<H: vk::Handle>(&self, handle: H, name: &str) {
+        self.debug_namer.set_name(handle, name);
+    }
+
+    /** Opens a labelled region of the command buffer at `buffer_index`, closed by [`BaseApp::cmd_end_label`].
+    # Safety
+    Only call inside [`BaseApp::record_command_buffer`]. */
+    pub unsafe fn cmd_begin_label(&self, buffer_index: usize, name: &str) {
+        self.debug_namer
+            .cmd_begin_label(self.command_buffers[buffer_index], name, [0.0; 4]);
+    }
+
+    /** Closes the region last opened by [`BaseApp::cmd_begin_label`].
+    # Safety
+    Only call inside [`BaseApp::record_command_buffer`], after a matching [`BaseApp::cmd_begin_label`]. */
+    pub unsafe fn cmd_end_label(&self, buffer_index: usize) {
+        self.debug_namer
+            .cmd_end_label(self.command_buffers[buffer_index]);
+    }
+
+    pub fn window(&self) -> &Window {
+        &self.window
+    }
+
+    /** Timing of recent frames, where a frame starts at each [`BaseApp::wait_for_in_flight_fence`].
+    Wait time covers the fence wait and [`BaseApp::acquire_next_image`], record time covers [`BaseApp::record_command_buffer`]. */
+    pub fn frame_stats(&self) -> &FrameStats {
+        &self.frame_stats
+    }
+
+    pub fn frame_stats_mut(&mut self) -> &mut FrameStats {
+        &mut self.frame_stats
+    }
+
+    /// Whether [`BaseApp::frame_stats`] is appended to the window title, see [`AppSettings::frame_stats_in_title`]
+    pub fn set_frame_stats_in_title(&mut self, frame_stats_in_title: bool) {
+        self.frame_stats_in_title = frame_stats_in_title;
+        if !frame_stats_in_title {
+            self.window.set_title(&self.window_title);
+        }
+    }
+
+    fn update_title_frame_stats(&mut self) {
+        if self.frame_stats_in_title && self.last_title_update.elapsed() >= TITLE_UPDATE_INTERVAL {
+            self.window
+                .set_title(&format!("{} - {}", self.window_title, self.frame_stats));
+            self.last_title_update = Instant::now();
+        }
+    }
+
+    /// Properties of the physical device the app renders with, and why it was chosen
+    pub fn physical_device_info(&self) -> &PhysicalDeviceInfo {
+        &self.physical_device_info
+    }
+
+    /// Lists every physical device available to the app, with their suitability for rendering to its window.
+    /// Useful for letting users pick a device with [`DeviceSelector`]
+    pub fn available_physical_devices(&self) -> Vec<PhysicalDeviceInfo> {
+        engine_core::enumerate_physical_devices(
+            self.logical_device.instance(),
+            &self.surface_loader,
+            &self.surface,
+            &self.device_requirements,
+            self.instance_api_version,
+        )
+    }
+
+    /// Device extensions and features that were enabled, including which of the optional ones the device supported
+    pub fn enabled_features(&self) -> &EnabledFeatures {
+        &self.enabled_features
+    }
+
+    /// The Vulkan version in use, the lowest of the version requested in [`AppSettings`], the loader's and the device's
+    pub fn api_version(&self) -> u32 {
+        self.api_version
+    }
+
+    /// Whether drawing uses dynamic rendering (Vulkan 1.3) instead of a render pass and framebuffers.
+    /// If so, [`BaseApp::render_pass`] is null and [`BaseApp::framebuffers`] is empty
+    pub fn uses_dynamic_rendering(&self) -> bool {
+        self.enabled_features.features.vulkan13.dynamic_rendering == vk::TRUE
+    }
+
+    /// Whether barriers can be recorded with synchronization2 (Vulkan 1.3), see [`engine_core::cmd_image_barrier`]
+    pub fn uses_synchronization2(&self) -> bool {
+        self.enabled_features.features.vulkan13.synchronization2 == vk::TRUE
+    }
+
+    /// Which indirect drawing features the device has enabled
+    pub fn indirect_draw_support(&self) -> &IndirectDrawSupport {
+        &self.indirect_draw_support
+    }
+
     /** Acquire index of image from the swapchain, signal semaphore once finished.
     If the error is of type `ERROR_OUT_OF_DATE_KHR`, the swapchain needs to be recreated before rendering can resume.
     May also return error `SUBOPTIMAL_KHR`, in which case the swapchain *should* be recreated.
@@ -463,24 +1059,42 @@ impl BaseApp {
         &mut self,
         framebuffer_index: usize,
     ) -> Result<(u32, bool), vk::Result> {
-        unsafe {
+        let wait_start = Instant::now();
+        let result = unsafe {
             self.swapchain_loader.acquire_next_image(
                 self.swapchain,
                 u64::MAX,
                 self.sync.image_available[framebuffer_index],
                 vk::Fence::null(),
             )
-        }
+        };
+        self.frame_stats.add_wait_time(wait_start.elapsed());
+        result
     }
 
     /// Blocks host execution, waiting for the fence at `self.sync.in_flight[fence_index]` to be signaled. No timeout.
-    pub fn wait_for_in_flight_fence(&self, fence_index: usize) {
+    /// Also releases the staging memory of uploads that have completed, starts the frame in [`BaseApp::staging_ring`],
+    /// destroys resources whose frames have finished, advances [`BaseApp::frame_number`]
+    /// and begins timing a new frame in [`BaseApp::frame_stats`].
+    /// Call once per frame: until the frame is submitted, its staging memory is still referenced by queued copies
+    pub fn wait_for_in_flight_fence(&mut self, fence_index: usize) {
+        self.frame_stats.begin_frame();
+        self.update_title_frame_stats();
         let wait_fences = [self.sync.in_flight[fence_index]];
+        let wait_start = Instant::now();
         unsafe {
             self.logical_device
                 .wait_for_fences(&wait_fences, true, u64::MAX)
         }
         .unwrap();
+        self.frame_stats.add_wait_time(wait_start.elapsed());
+        self.uploads.collect_finished();
+        self.staging_ring.begin_frame(fence_index);
+        self.completed_frame_number = self
+            .completed_frame_number
+            .max(self.fence_frame_numbers[fence_index]);
+        self.deletion_queue.collect(self.completed_frame_number);
+        self.frame_number += 1;
     }
 
     /// Resets fence at `self.sync.in_flight[fence_index]`. No timeout.
@@ -489,8 +1103,377 @@ impl BaseApp {
         unsafe { self.logical_device.reset_fences(&wait_fences) }.unwrap();
     }
 
+    /** Replaces the contents of the vertex buffer with `vertices`, growing it if needed.
+    The copy is recorded at the start of the next [`BaseApp::record_command_buffer`], so the frame being recorded sees the new data
+    while frames already in flight keep using the old. Call between [`BaseApp::wait_for_in_flight_fence`] and recording. */
+    pub fn set_vertices<VertexType>(&mut self, vertices: &[VertexType]) {
+        let size = std::mem::size_of_val(vertices) as vk::DeviceSize;
+        self.reserve_vertex_buffer(size, false);
+        self.queue_buffer_write(vertices, self.vertex_buffer.buffer, 0);
+    }
+
+    /// Overwrites the vertices starting at `first_vertex`, growing the vertex buffer and keeping its contents if needed.
+    /// Synchronized like [`BaseApp::set_vertices`]
+    pub fn update_vertices<VertexType>(&mut self, first_vertex: usize, vertices: &[VertexType]) {
+        let offset = (first_vertex * std::mem::size_of::<VertexType>()) as vk::DeviceSize;
+        let size = std::mem::size_of_val(vertices) as vk::DeviceSize;
+        self.reserve_vertex_buffer(offset + size, true);
+        self.queue_buffer_write(vertices, self.vertex_buffer.buffer, offset);
+    }
+
+    /// Replaces the contents of the index buffer, growing it if needed, and sets [`BaseApp::index_count`] and [`BaseApp::index_type`].
+    /// Synchronized like [`BaseApp::set_vertices`]
+    pub fn set_indices<IndexType: ValidIndexBufferType>(&mut self, indices: &[IndexType]) {
+        let size = std::mem::size_of_val(indices) as vk::DeviceSize;
+        self.reserve_index_buffer(size, false);
+        self.queue_buffer_write(indices, self.index_buffer.buffer, 0);
+        self.index_count = indices.len() as u32;
+        self.index_type = IndexType::INDEX_TYPE;
+    }
+
+    /** Overwrites the indices starting at `first_index`, growing the index buffer and keeping its contents if needed.
+    [`BaseApp::index_count`] is extended to cover the written indices. Panics if the index type differs from the current one.
+    Synchronized like [`BaseApp::set_vertices`] */
+    pub fn update_indices<IndexType: ValidIndexBufferType>(
+        &mut self,
+        first_index: usize,
+        indices: &[IndexType],
+    ) {
+        assert_eq!(
+            IndexType::INDEX_TYPE,
+            self.index_type,
+            "Index type differs from the index buffer contents, use set_indices to change it"
+        );
+        let offset = (first_index * std::mem::size_of::<IndexType>()) as vk::DeviceSize;
+        let size = std::mem::size_of_val(indices) as vk::DeviceSize;
+        self.reserve_index_buffer(offset + size, true);
+        self.queue_buffer_write(indices, self.index_buffer.buffer, offset);
+        self.index_count = self.index_count.max((first_index + indices.len()) as u32);
+    }
+
+    fn reserve_vertex_buffer(&mut self, size: vk::DeviceSize, keep_contents: bool) {
+        if size <= self.vertex_buffer_size {
+            return;
+        }
+        let capacity = size.max(self.vertex_buffer_size * 2);
+        let vertex_buffer = engine_core::create_vertex_buffer(
+            self.logical_device.instance(),
+            &self.physical_device,
+            &self.logical_device,
+            capacity,
+        );
+        self.debug_namer.set_name(*vertex_buffer, "vertex buffer");
+        let new_buffer = *vertex_buffer;
+        let old_buffer = std::mem::replace(&mut *self.vertex_buffer, vertex_buffer);
+        let old_size = std::mem::replace(&mut self.vertex_buffer_size, capacity);
+        self.replace_geometry_buffer(old_buffer, old_size, new_buffer, keep_contents);
+    }
+
+    fn reserve_index_buffer(&mut self, size: vk::DeviceSize, keep_contents: bool) {
+        if size <= self.index_buffer_size {
+            return;
+        }
+        let capacity = size.max(self.index_buffer_size * 2);
+        let index_buffer = engine_core::create_index_buffer_with_size(
+            self.logical_device.instance(),
+            &self.physical_device,
+            &self.logical_device,
+            capacity,
+        );
+        self.debug_namer.set_name(*index_buffer, "index buffer");
+        let new_buffer = *index_buffer;
+        let old_buffer = std::mem::replace(&mut *self.index_buffer, index_buffer);
+        let old_size = std::mem::replace(&mut self.index_buffer_size, capacity);
+        self.replace_geometry_buffer(old_buffer, old_size, new_buffer, keep_contents);
+    }
+
+    /// Creates a sampled texture from tightly packed 4-byte pixels, blocking until the upload has finished.
+    /// See [`engine_core::load_image_immediate`]
+    pub fn load_image_immediate(
+        &self,
+        pixels: &[u8],
+        dimensions: (u32, u32),
+        format: vk::Format,
+    ) -> ManagedImage {
+        engine_core::load_image_immediate(
+            self.logical_device.instance(),
+            &self.physical_device,
+            &self.logical_device,
+            self.command_pool,
+            self.graphics_queue,
+            pixels,
+            dimensions,
+            format,
+        )
+    }
+
+    /** Records the upload of a mesh into its own vertex and index buffers, into the current batch of [`BaseApp::uploads`].
+    Add every mesh of a level before submitting them together with [`UploadManager::submit`],
+    and draw them with [`BaseApp::draw_mesh`] once [`UploadManager::is_complete`] returns `true` for the batch. */
+    pub fn add_mesh<VertexType, IndexType: ValidIndexBufferType>(
+        &mut self,
+        vertices: &[VertexType],
+        indices: &[IndexType],
+    ) -> MeshHandle {
+        let mesh = self.meshes.add(
+            self.logical_device.instance(),
+            &self.physical_device,
+            &self.logical_device,
+            &mut self.uploads,
+            vertices,
+            indices,
+        );
+        self.debug_namer
+            .set_name(mesh.vertex_buffer, "mesh vertex buffer");
+        self.debug_namer
+            .set_name(mesh.index_buffer, "mesh index buffer");
+        mesh
+    }
+
+    /// Same as [`BaseApp::add_mesh`], for vertices packed into bytes at `vertex_stride` apart, see [`MeshRegistry::add_raw`]
+    pub fn add_mesh_raw<IndexType: ValidIndexBufferType>(
+        &mut self,
+        vertex_data: &[u8],
+        vertex_stride: u32,
+        indices: &[IndexType],
+    ) -> MeshHandle {
+        let mesh = self.meshes.add_raw(
+            self.logical_device.instance(),
+            &self.physical_device,
+            &self.logical_device,
+            &mut self.uploads,
+            vertex_data,
+            vertex_stride,
+            indices,
+        );
+        self.debug_namer
+            .set_name(mesh.vertex_buffer, "mesh vertex buffer");
+        self.debug_namer
+            .set_name(mesh.index_buffer, "mesh index buffer");
+        mesh
+    }
+
+    /// Removes a mesh added with [`BaseApp::add_mesh`]. Its buffers are destroyed once the frames in flight have finished
+    pub fn remove_mesh(&mut self, mesh: MeshHandle) {
+        if let Some((vertex_buffer, index_buffer)) = self.meshes.remove(mesh) {
+            self.retire_buffer(vertex_buffer);
+            self.retire_buffer(index_buffer);
+        }
+    }
+
+    /** Binds the buffers of `mesh` and draws it. For use inside [`crate::drawing_commands`], after which the
+    default vertex and index buffers are no longer bound.
+    # Safety
+    The command buffer at `buffer_index` must be recording inside a render pass, and the mesh must not have been removed. */
+    pub unsafe fn draw_mesh(&self, buffer_index: usize, mesh: &MeshHandle) {
+        engine_core::cmd_draw_mesh(
+            &self.logical_device,
+            self.command_buffers[buffer_index],
+            mesh,
+            1,
+        );
+    }
+
+    /// Creates per-frame instance buffers for drawing many copies of `mesh`, see [`InstanceBuffer`]
+    pub fn create_instance_buffer<InstanceType>(
+        &self,
+        mesh: MeshHandle,
+        capacity: usize,
+    ) -> InstanceBuffer<InstanceType> {
+        InstanceBuffer::new(
+            self.logical_device.instance(),
+            &self.physical_device,
+            &self.logical_device,
+            mesh,
+            capacity,
+        )
+    }
+
+    /** Writes `instances` as this frame's instance data and draws all of them with a single draw call.
+    For use inside [`crate::drawing_commands`], with a pipeline whose vertex input includes the instance binding,
+    e.g. from [`VertexInputDescriptors::with_instance_transforms`].
+    # Safety
+    The command buffer at `buffer_index` must be recording inside a render pass, and the mesh must not have been removed. */
+    pub unsafe fn draw_instances<InstanceType>(
+        &self,
+        buffer_index: usize,
+        instance_buffer: &mut InstanceBuffer<InstanceType>,
+        instances: &[InstanceType],
+    ) {
+        instance_buffer.write(buffer_index, instances);
+        instance_buffer.cmd_draw(self.command_buffers[buffer_index], buffer_index);
+    }
+
+    /// Creates per-frame buffers for `max_draw_count` indirect draws, host visible for writing from the CPU
+    /// or device local for filling from compute shaders, see [`IndirectBuffer`]
+    pub fn create_indirect_buffer(
+        &self,
+        max_draw_count: u32,
+        host_visible: bool,
+    ) -> IndirectBuffer {
+        let indirect_buffer = IndirectBuffer::new(
+            self.logical_device.instance(),
+            &self.physical_device,
+            &self.logical_device,
+            max_draw_count,
+            host_visible,
+        );
+        for frame in 0..MAX_FRAMES_IN_FLIGHT {
+            self.debug_namer.set_name(
+                indirect_buffer.commands_buffer(frame),
+                &format!("indirect commands {}", frame),
+            );
+            self.debug_namer.set_name(
+                indirect_buffer.count_buffer(frame),
+                &format!("indirect count {}", frame),
+            );
+        }
+        indirect_buffer
+    }
+
+    /** Issues this frame's draws from `indirect_buffer` with the currently bound vertex and index buffers,
+    using the most efficient method the device supports. For use inside [`crate::drawing_commands`].
+    # Safety
+    The command buffer at `buffer_index` must be recording inside a render pass, and the draw commands must be written. */
+    pub unsafe fn draw_indirect(&self, buffer_index: usize, indirect_buffer: &IndirectBuffer) {
+        indirect_buffer.cmd_draw(
+            self.command_buffers[buffer_index],
+            buffer_index,
+            &self.indirect_draw_support,
+        );
+    }
+
+    // `old_buffer` has just been replaced by the bigger `new_buffer`
+    fn replace_geometry_buffer(
+        &mut self,
+        old_buffer: engine_core::ManagedBuffer,
+        old_size: vk::DeviceSize,
+        new_buffer: vk::Buffer,
+        keep_contents: bool,
+    ) {
+        // Queued writes into the old buffer go to the new one instead, after its old contents have been copied over
+        if keep_contents {
+            for copy in self.pending_buffer_copies.iter_mut() {
+                if copy.dst_buffer == *old_buffer {
+                    copy.dst_buffer = new_buffer;
+                }
+            }
+            if old_size > 0 {
+                self.pending_buffer_copies.insert(
+                    0,
+                    PendingBufferCopy {
+                        src_buffer: *old_buffer,
+                        dst_buffer: new_buffer,
+                        region: *vk::BufferCopy::builder().size(old_size),
+                        barrier_after: true,
+                    },
+                );
+            }
+        } else {
+            self.pending_buffer_copies
+                .retain(|copy| copy.dst_buffer != *old_buffer);
+        }
+        self.retire_buffer(old_buffer);
+    }
+
+    // Keeps `buffer` alive until every frame that may use it has finished. Pending copies involving it
+    // may only be recorded in the next frame, if this one has already been submitted
+    fn retire_buffer(&mut self, buffer: engine_core::ManagedBuffer) {
+        self.deletion_queue.push(self.frame_number + 1, buffer);
+    }
+
+    // Stages `data` in the staging ring, or in a temporary staging buffer if the ring is full
+    fn queue_buffer_write<T>(
+        &mut self,
+        data: &[T],
+        dst_buffer: vk::Buffer,
+        dst_offset: vk::DeviceSize,
+    ) {
+        let size = std::mem::size_of_val(data) as vk::DeviceSize;
+        if size == 0 {
+            return;
+        }
+        let (src_buffer, src_offset) = match self.staging_ring.write(data) {
+            Some(offset) => (self.staging_ring.buffer(), offset),
+            None => {
+                let mut staging_buffer = engine_core::create_staging_buffer(
+                    self.logical_device.instance(),
+                    &self.physical_device,
+                    &self.logical_device,
+                    size,
+                );
+                staging_buffer.map_buffer_memory();
+                staging_buffer.write(0, data);
+                let src_buffer = *staging_buffer;
+                self.retire_buffer(staging_buffer);
+                (src_buffer, 0)
+            }
+        };
+        self.pending_buffer_copies.push(PendingBufferCopy {
+            src_buffer,
+            dst_buffer,
+            region: *vk::BufferCopy::builder()
+                .src_offset(src_offset)
+                .dst_offset(dst_offset)
+                .size(size),
+            barrier_after: false,
+        });
+    }
+
+    unsafe fn record_pending_buffer_copies(&mut self, command_buffer: vk::CommandBuffer) {
+        if self.pending_buffer_copies.is_empty() {
+            return;
+        }
+        let transfer_to_transfer = [*vk::MemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ | vk::AccessFlags::TRANSFER_WRITE)];
+        // Earlier frames may still be reading the buffers, an execution dependency avoids overwriting them too early
+        self.logical_device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::VERTEX_INPUT,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[],
+        );
+        for copy in self.pending_buffer_copies.drain(..) {
+            self.logical_device.cmd_copy_buffer(
+                command_buffer,
+                copy.src_buffer,
+                copy.dst_buffer,
+                &[copy.region],
+            );
+            if copy.barrier_after {
+                self.logical_device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::DependencyFlags::empty(),
+                    &transfer_to_transfer,
+                    &[],
+                    &[],
+                );
+            }
+        }
+        let transfer_to_vertex_input = [*vk::MemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::VERTEX_ATTRIBUTE_READ | vk::AccessFlags::INDEX_READ)];
+        self.logical_device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::VERTEX_INPUT,
+            vk::DependencyFlags::empty(),
+            &transfer_to_vertex_input,
+            &[],
+            &[],
+        );
+    }
+
     /** Begins command buffer recording, runs the closure, then ends command buffer recording.
     Anything *could* be put in the closure, but the intent is Vulkan commands.
+    Finished uploads of [`BaseApp::uploads`] are acquired, and copies queued by the vertex and index update methods,
+    such as [`BaseApp::set_vertices`], are recorded before the closure runs.
     # Example:
     ```ignore
     unsafe {
@@ -505,8 +1488,9 @@ impl BaseApp {
     ``` */
     pub unsafe fn record_command_buffer<F>(&mut self, buffer_index: usize, commands: F)
     where
-        F: Fn(&mut BaseApp),
+        F: FnOnce(&mut BaseApp),
     {
+        let record_start = Instant::now();
         //Begin recording command buffer
         let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder();
         self.logical_device
@@ -516,11 +1500,36 @@ impl BaseApp {
             )
             .expect("Could not begin command buffer recording!");
 
+        self.gpu_profiler
+            .begin_frame(self.command_buffers[buffer_index], buffer_index);
+        if std::mem::take(&mut self.stale_texture_sets[buffer_index]) {
+            self.rebind_texture(buffer_index);
+        }
+        self.uploads
+            .cmd_acquire_finished(self.command_buffers[buffer_index]);
+        self.record_pending_buffer_copies(self.command_buffers[buffer_index]);
         commands(self);
 
         self.logical_device
             .end_command_buffer(self.command_buffers[buffer_index])
             .expect("Failed recording command buffer!");
+        self.frame_stats.add_record_time(record_start.elapsed());
+    }
+
+    // Points binding 1 of the frame descriptor set at the current texture, if the layout has one there.
+    // The frame at `buffer_index` must not be in flight
+    fn rebind_texture(&self, buffer_index: usize) {
+        let builder = DescriptorSetBuilder::new(&self.descriptor_set_bindings);
+        if builder.descriptor_type(1) != Some(vk::DescriptorType::COMBINED_IMAGE_SAMPLER) {
+            return;
+        }
+        let builder = builder.image(
+            1,
+            self.texture.image_view,
+            self.texture_sampler,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        );
+        unsafe { builder.write(&self.logical_device, self.descriptor_sets[buffer_index]) };
     }
 
     /*
@@ -536,10 +1545,123 @@ impl BaseApp {
     }
     */
 
+    /** Begins rendering to the swapchain image at `swapchain_image_index`, clearing color to black and depth to 1.0.
+    With a render pass, this begins the render pass on the matching framebuffer.
+    With dynamic rendering, the swapchain and depth images are first transitioned to attachment layouts.
+    # Safety
+    The command buffer at `buffer_index` must be recording, and must not already be rendering. */
+    pub unsafe fn begin_rendering(&self, buffer_index: usize, swapchain_image_index: u32) {
+        let command_buffer = self.command_buffers[buffer_index];
+        let render_area = vk::Rect2D::builder()
+            .offset(vk::Offset2D { x: 0, y: 0 })
+            .extent(self.swapchain_extent);
+        let mut clear_values = [vk::ClearValue::default(); 2];
+        clear_values[0].color.float32 = [0.0, 0.0, 0.0, 1.0];
+        clear_values[1].depth_stencil = vk::ClearDepthStencilValue {
+            depth: 1.0,
+            stencil: 0,
+        };
+
+        if !self.uses_dynamic_rendering() {
+            let renderpass_begin_info = vk::RenderPassBeginInfo::builder()
+                .render_pass(self.render_pass)
+                .framebuffer(self.framebuffers[swapchain_image_index as usize])
+                .render_area(*render_area)
+                .clear_values(&clear_values);
+            self.logical_device.cmd_begin_render_pass(
+                command_buffer,
+                &renderpass_begin_info,
+                vk::SubpassContents::INLINE,
+            );
+            return;
+        }
+
+        // Same dependencies as the subpass dependency of the default render pass
+        engine_core::cmd_image_barrier(
+            &self.logical_device,
+            command_buffer,
+            self.swapchain_images[swapchain_image_index as usize],
+            vk::ImageAspectFlags::COLOR,
+            &engine_core::ImageTransition {
+                old_layout: vk::ImageLayout::UNDEFINED,
+                new_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                src_stage: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access: vk::AccessFlags::empty(),
+                dst_stage: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                dst_access: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            },
+            self.uses_synchronization2(),
+        );
+        engine_core::cmd_image_barrier(
+            &self.logical_device,
+            command_buffer,
+            self.depth_image.image,
+            vk::ImageAspectFlags::DEPTH,
+            &engine_core::ImageTransition {
+                old_layout: vk::ImageLayout::UNDEFINED,
+                new_layout: vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                src_stage: vk::PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                src_access: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+                dst_stage: vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS,
+                dst_access: vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            },
+            self.uses_synchronization2(),
+        );
+
+        let color_attachments = [*vk::RenderingAttachmentInfo::builder()
+            .image_view(self.image_views[swapchain_image_index as usize])
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .clear_value(clear_values[0])];
+        let depth_attachment = vk::RenderingAttachmentInfo::builder()
+            .image_view(self.depth_image.image_view)
+            .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .clear_value(clear_values[1]);
+        let rendering_info = vk::RenderingInfo::builder()
+            .render_area(*render_area)
+            .layer_count(1)
+            .color_attachments(&color_attachments)
+            .depth_attachment(&depth_attachment);
+        self.logical_device
+            .cmd_begin_rendering(command_buffer, &rendering_info);
+    }
+
+    /** Ends rendering begun with [`BaseApp::begin_rendering`].
+    With dynamic rendering, the swapchain image is then transitioned to the present layout.
+    # Safety
+    The command buffer at `buffer_index` must be rendering to the swapchain image at `swapchain_image_index`. */
+    pub unsafe fn end_rendering(&self, buffer_index: usize, swapchain_image_index: u32) {
+        let command_buffer = self.command_buffers[buffer_index];
+        if !self.uses_dynamic_rendering() {
+            self.logical_device.cmd_end_render_pass(command_buffer);
+            return;
+        }
+
+        self.logical_device.cmd_end_rendering(command_buffer);
+        engine_core::cmd_image_barrier(
+            &self.logical_device,
+            command_buffer,
+            self.swapchain_images[swapchain_image_index as usize],
+            vk::ImageAspectFlags::COLOR,
+            &engine_core::ImageTransition {
+                old_layout: vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                new_layout: vk::ImageLayout::PRESENT_SRC_KHR,
+                src_stage: vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                src_access: vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+                dst_stage: vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                dst_access: vk::AccessFlags::empty(),
+            },
+            self.uses_synchronization2(),
+        );
+    }
+
     /** Submits the command buffer at `buffer_index` to the graphics queue, waiting for a swapchain image:`self.sync.image_available[buffer_index]`.
     Waits for the `COLOR_ATTACHMENT_OUTPUT` stage, then executes commands. Once the image has been drawn, `self.sync.render_finished[buffer_index]` is signaled,
     and the `self.sync.in_flight[buffer_index]` fence is signaled. */
-    pub fn submit_drawing_command_buffer(&self, buffer_index: usize) {
+    pub fn submit_drawing_command_buffer(&mut self, buffer_index: usize) {
         let wait_sems = [self.sync.image_available[buffer_index]];
         let wait_stages = [vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
         let signal_sems = [self.sync.render_finished[buffer_index]];
@@ -558,6 +1680,7 @@ impl BaseApp {
                 )
                 .expect("Queue submission failed!");
         }
+        self.fence_frame_numbers[buffer_index] = self.frame_number;
     }
 
     /** Queues up the image at `image_index` for presentation to the window surface.
@@ -595,49 +1718,51 @@ impl BaseApp {
 
     /** Recreates the swapchain and the dependants of the swapchain.
     Necessary if some condition changes that invalidates the swapchain, most commonly a window resize.
+    Does not wait for the device to be idle: the old swapchain and its dependants are destroyed through the deletion queue
+    once the frames in flight have finished with them, see [`BaseApp::destroy_later`].
     Excessive resizing of the window will cause rare Vulkan validation errors due to a data race in [`engine_core::create_swapchain`],
     where the extent of the window may change after it has been queried to set the swapchain extent, but before the swapchain is created.
     This error is non-fatal and largely unpreventable without a lot of runtime checks in that function, so for now it is ignored */
     pub fn recreate_swapchain(
         &mut self,
-        shaders: &Vec<crate::shaders::Shader>,
+        shaders: &[crate::shaders::Shader],
         vertex_input_descriptors: &VertexInputDescriptors,
         descriptor_set_bindings: Vec<vk::DescriptorSetLayoutBinding>,
     ) {
-        unsafe {
-            self.logical_device.device_wait_idle().unwrap();
-            self.clean_swapchain_and_dependants();
-        }
-
-        let (physical_device, queue_family_indices) =
-            engine_core::find_physical_device(&self.instance, &self.surface_loader, &self.surface);
         let (swapchain, image_format, swapchain_extent, swapchain_images) =
             engine_core::create_swapchain(
                 &self.window,
                 &self.surface_loader,
                 &self.surface,
-                &physical_device,
+                &self.physical_device,
                 &self.swapchain_loader,
-                queue_family_indices,
+                self.queue_family_indices,
+                self.swapchain,
             );
         let image_views = engine_core::create_swapchain_image_views(
             &self.logical_device,
             &swapchain_images,
             image_format,
         );
-        let (graphics_pipeline, graphics_pipeline_layout, descriptor_set_layout, render_pass) =
+        // The frame descriptor sets do not depend on the swapchain, and are only replaced if their bindings changed
+        if !same_bindings(&descriptor_set_bindings, &self.descriptor_set_bindings) {
+            self.replace_frame_descriptor_sets(descriptor_set_bindings);
+        }
+        let (graphics_pipeline, graphics_pipeline_layout, render_pass) =
             engine_core::create_graphics_pipeline(
                 &self.logical_device,
                 swapchain_extent,
                 image_format,
                 shaders,
                 vertex_input_descriptors,
-                descriptor_set_bindings,
+                self.descriptor_set_layout,
+                &self.additional_set_layouts,
                 [0.0],
+                self.uses_dynamic_rendering(),
             );
         let depth_image = engine_core::create_image(
-            &self.instance,
-            &physical_device,
+            self.logical_device.instance(),
+            &self.physical_device,
             &self.logical_device,
             vk::Format::D32_SFLOAT,
             vk::ImageTiling::OPTIMAL,
@@ -645,43 +1770,199 @@ impl BaseApp {
             vk::ImageAspectFlags::DEPTH,
             (swapchain_extent.width, swapchain_extent.height),
         );
-        let framebuffers = engine_core::create_framebuffers(
-            &self.logical_device,
-            render_pass,
-            swapchain_extent,
-            &image_views,
-            depth_image.image_view,
-        );
+        let framebuffers = if self.uses_dynamic_rendering() {
+            Vec::new()
+        } else {
+            engine_core::create_framebuffers(
+                &self.logical_device,
+                render_pass,
+                swapchain_extent,
+                &image_views,
+                depth_image.image_view,
+            )
+        };
 
-        unsafe { ManuallyDrop::drop(&mut self.depth_image) };
-        self.depth_image = ManuallyDrop::new(depth_image);
+        // Frames in flight keep rendering to the old swapchain, so it is retired rather than destroyed right away
+        let old_depth_image = std::mem::replace(&mut *self.depth_image, depth_image);
+        self.deletion_queue
+            .push(self.frame_number + 1, old_depth_image);
+        self.retire_swapchain_and_dependants();
 
         self.swapchain = swapchain;
         self.swapchain_extent = swapchain_extent;
         self.image_views = image_views;
+        self.swapchain_images = swapchain_images;
         self.render_pass = render_pass;
         self.graphics_pipeline = graphics_pipeline;
         self.graphics_pipeline_layout = graphics_pipeline_layout;
-        self.descriptor_set_layout = descriptor_set_layout;
         self.framebuffers = framebuffers;
+        self.name_swapchain_objects();
     }
 
-    unsafe fn clean_swapchain_and_dependants(&mut self) {
-        for buffer in self.framebuffers.drain(..) {
-            self.logical_device.destroy_framebuffer(buffer, None);
+    fn name_engine_objects(&self) {
+        let namer = &self.debug_namer;
+        if !namer.is_enabled() {
+            return;
         }
-        self.logical_device
-            .destroy_pipeline(self.graphics_pipeline, None);
-        self.logical_device
-            .destroy_pipeline_layout(self.graphics_pipeline_layout, None);
-        self.logical_device
-            .destroy_descriptor_set_layout(self.descriptor_set_layout, None);
-        self.logical_device
-            .destroy_render_pass(self.render_pass, None);
-        for view in self.image_views.drain(..) {
-            self.logical_device.destroy_image_view(view, None);
+        namer.set_name(self.logical_device.handle(), "logical device");
+        namer.set_name(self.graphics_queue, "graphics queue");
+        if self.present_queue != self.graphics_queue {
+            namer.set_name(self.present_queue, "present queue");
         }
-        self.swapchain_loader
-            .destroy_swapchain(self.swapchain, None);
+        namer.set_name(self.surface, "window surface");
+        namer.set_name(self.command_pool, "graphics command pool");
+        namer.set_names(&self.command_buffers, "frame command buffer");
+        namer.set_names(&self.sync.image_available, "image available semaphore");
+        namer.set_names(&self.sync.render_finished, "render finished semaphore");
+        namer.set_names(&self.sync.in_flight, "in flight fence");
+        namer.set_names(self.descriptor_allocator.pools(), "descriptor pool");
+        namer.set_names(&self.descriptor_sets, "frame descriptor set");
+        if let Some(bindless_textures) = self.bindless_textures.as_ref() {
+            namer.set_name(bindless_textures.set(), "bindless texture set");
+        }
+        namer.set_name(self.vertex_buffer.buffer, "vertex buffer");
+        namer.set_name(self.index_buffer.buffer, "index buffer");
+        namer.set_name(self.texture.image, "texture");
+        namer.set_name(self.texture.image_view, "texture view");
+        namer.set_name(self.texture_sampler, "texture sampler");
+        namer.set_name(self.staging_ring.buffer(), "staging ring");
+        if let Some(semaphore) = self.uploads.timeline_semaphore() {
+            namer.set_name(semaphore, "upload timeline semaphore");
+        }
+        self.name_swapchain_objects();
+    }
+
+    fn name_swapchain_objects(&self) {
+        let namer = &self.debug_namer;
+        if !namer.is_enabled() {
+            return;
+        }
+        namer.set_name(self.swapchain, "swapchain");
+        namer.set_names(&self.swapchain_images, "swapchain image");
+        namer.set_names(&self.image_views, "swapchain image view");
+        namer.set_name(self.depth_image.image, "depth image");
+        namer.set_name(self.depth_image.image_view, "depth image view");
+        namer.set_names(&self.framebuffers, "framebuffer");
+        namer.set_name(self.render_pass, "render pass");
+        namer.set_name(self.graphics_pipeline, "graphics pipeline");
+        namer.set_name(self.graphics_pipeline_layout, "graphics pipeline layout");
+        namer.set_name(self.descriptor_set_layout, "descriptor set layout");
+    }
+
+    /* Allocates new frame descriptor sets with a layout of `bindings`, and writes the texture and the bound buffers
+    whose bindings kept their descriptor type. Frames in flight keep using the old sets, which stay allocated
+    until the descriptor allocator is dropped, while their layout is destroyed once those frames have finished. */
+    fn replace_frame_descriptor_sets(&mut self, bindings: Vec<vk::DescriptorSetLayoutBinding>) {
+        let layout = engine_core::create_descriptor_set_layout(&self.logical_device, &bindings);
+        self.descriptor_allocator.register_layout(layout, &bindings);
+        let old_layout = std::mem::replace(&mut self.descriptor_set_layout, layout);
+        self.descriptor_allocator.forget_layout(old_layout);
+        let logical_device = Arc::clone(&self.logical_device);
+        self.deletion_queue
+            .push_fn(self.frame_number + 1, move || unsafe {
+                logical_device.destroy_descriptor_set_layout(old_layout, None)
+            });
+
+        self.descriptor_set_bindings = bindings;
+        self.descriptor_sets = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| self.descriptor_allocator.allocate(layout))
+            .collect();
+        self.debug_namer
+            .set_names(&self.descriptor_sets, "frame descriptor set");
+        // The new sets are not in use yet, so they are written right away
+        self.stale_texture_sets = [false; MAX_FRAMES_IN_FLIGHT];
+        for frame in 0..MAX_FRAMES_IN_FLIGHT {
+            self.rebind_texture(frame);
+        }
+        let builder = DescriptorSetBuilder::new(&self.descriptor_set_bindings);
+        let bound_buffers: Vec<FrameBufferBinding> = std::mem::take(&mut self.bound_buffers)
+            .into_iter()
+            .filter(|bound| builder.descriptor_type(bound.binding) == Some(bound.descriptor_type))
+            .collect();
+        for bound in bound_buffers {
+            self.bind_frame_buffers(bound.binding, bound.infos);
+        }
+    }
+
+    // Destroys the swapchain and the objects made for it once every frame submitted so far has finished.
+    // The current frame may have been submitted already, in which case it still renders to them
+    fn retire_swapchain_and_dependants(&mut self) {
+        let logical_device = Arc::clone(&self.logical_device);
+        let swapchain_loader = self.swapchain_loader.clone();
+        let framebuffers = std::mem::take(&mut self.framebuffers);
+        let image_views = std::mem::take(&mut self.image_views);
+        let graphics_pipeline = self.graphics_pipeline;
+        let graphics_pipeline_layout = self.graphics_pipeline_layout;
+        let render_pass = self.render_pass;
+        let swapchain = self.swapchain;
+        self.deletion_queue
+            .push_fn(self.frame_number + 1, move || unsafe {
+                for buffer in framebuffers {
+                    logical_device.destroy_framebuffer(buffer, None);
+                }
+                logical_device.destroy_pipeline(graphics_pipeline, None);
+                logical_device.destroy_pipeline_layout(graphics_pipeline_layout, None);
+                logical_device.destroy_render_pass(render_pass, None);
+                for view in image_views {
+                    logical_device.destroy_image_view(view, None);
+                }
+                swapchain_loader.destroy_swapchain(swapchain, None);
+            });
+    }
+}
+
+// A buffer per frame in flight bound to the frame descriptor sets, see BaseApp::bind_frame_buffers
+struct FrameBufferBinding {
+    binding: u32,
+    descriptor_type: vk::DescriptorType,
+    infos: [vk::DescriptorBufferInfo; MAX_FRAMES_IN_FLIGHT],
+}
+
+// Whether layouts made from `a` and `b` are identical, in which case their sets are interchangeable
+fn same_bindings(
+    a: &[vk::DescriptorSetLayoutBinding],
+    b: &[vk::DescriptorSetLayoutBinding],
+) -> bool {
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(a, b)| {
+            a.binding == b.binding
+                && a.descriptor_type == b.descriptor_type
+                && a.descriptor_count == b.descriptor_count
+                && a.stage_flags == b.stage_flags
+                && a.p_immutable_samplers == b.p_immutable_samplers
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::same_bindings;
+    use ash::vk;
+
+    fn binding(
+        binding: u32,
+        descriptor_type: vk::DescriptorType,
+    ) -> vk::DescriptorSetLayoutBinding {
+        *vk::DescriptorSetLayoutBinding::builder()
+            .binding(binding)
+            .descriptor_type(descriptor_type)
+            .descriptor_count(1)
+            .stage_flags(vk::ShaderStageFlags::VERTEX)
+    }
+
+    #[test]
+    fn compares_bindings_by_value() {
+        let bindings = [
+            binding(0, vk::DescriptorType::UNIFORM_BUFFER),
+            binding(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER),
+        ];
+        assert!(same_bindings(&bindings, &bindings.clone()));
+        assert!(!same_bindings(&bindings, &bindings[..1]));
+
+        let mut changed = bindings;
+        changed[1].descriptor_type = vk::DescriptorType::STORAGE_BUFFER;
+        assert!(!same_bindings(&bindings, &changed));
+        let mut changed = bindings;
+        changed[0].stage_flags = vk::ShaderStageFlags::FRAGMENT;
+        assert!(!same_bindings(&bindings, &changed));
     }
 }
@@ -0,0 +1,84 @@
+use crate::engine_core::{MeshHandle, ValidIndexBufferType};
+use crate::BaseApp;
+use ash::vk;
+
+/// Wavefront OBJ models and MTL material libraries
+pub mod obj;
+
+/// glTF 2.0 scenes
+#[cfg(feature = "gltf")]
+pub mod gltf;
+
+/// Index data of a mesh, `u16` if it has at most 65536 vertices and `u32` otherwise
+#[derive(Clone, Debug, PartialEq)]
+pub enum MeshIndices {
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+}
+
+impl MeshIndices {
+    pub(crate) fn from_u32(indices: Vec<u32>, vertex_count: usize) -> MeshIndices {
+        if vertex_count <= u16::MAX as usize + 1 {
+            MeshIndices::U16(indices.into_iter().map(|index| index as u16).collect())
+        } else {
+            MeshIndices::U32(indices)
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            MeshIndices::U16(indices) => indices.len(),
+            MeshIndices::U32(indices) => indices.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Uploads `vertices` with these indices, see [`BaseApp::add_mesh`]
+    pub fn add_mesh<VertexType>(&self, app: &mut BaseApp, vertices: &[VertexType]) -> MeshHandle {
+        match self {
+            MeshIndices::U16(indices) => app.add_mesh(vertices, indices),
+            MeshIndices::U32(indices) => app.add_mesh(vertices, indices),
+        }
+    }
+
+    /// Uploads vertices packed into bytes with these indices, see [`BaseApp::add_mesh_raw`]
+    pub fn add_mesh_raw(
+        &self,
+        app: &mut BaseApp,
+        vertex_data: &[u8],
+        vertex_stride: u32,
+    ) -> MeshHandle {
+        match self {
+            MeshIndices::U16(indices) => app.add_mesh_raw(vertex_data, vertex_stride, indices),
+            MeshIndices::U32(indices) => app.add_mesh_raw(vertex_data, vertex_stride, indices),
+        }
+    }
+
+    pub fn index_type(&self) -> vk::IndexType {
+        match self {
+            MeshIndices::U16(_) => u16::INDEX_TYPE,
+            MeshIndices::U32(_) => u32::INDEX_TYPE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn indices_fit_u16_up_to_65536_vertices() {
+        let indices = vec![0, 1, 65535];
+        assert_eq!(
+            MeshIndices::from_u32(indices.clone(), 65536),
+            MeshIndices::U16(vec![0, 1, 65535])
+        );
+        assert_eq!(
+            MeshIndices::from_u32(indices.clone(), 65537),
+            MeshIndices::U32(indices)
+        );
+    }
+}
@@ -0,0 +1,508 @@
+/*!
+Loader for glTF 2.0 scenes (`.gltf` with embedded or external buffers and images, and `.glb`), using the [gltf](https://crates.io/crates/gltf) crate.
+
+Vertex attributes are kept per primitive, and are packed into vertex data for any [`VertexInputDescriptors`] layout
+with [`GltfPrimitive::vertex_streams`], by telling which attribute each shader location reads.
+# Example:
+```ignore
+let scene = vk_engine::assets::gltf::load_gltf("assets/helmet.glb").unwrap();
+let layout = ObjVertex::input_descriptors();
+let semantics = [
+    (0, VertexSemantic::Position),
+    (1, VertexSemantic::Normal),
+    (2, VertexSemantic::TexCoord(0)),
+];
+let handles: Vec<MeshHandle> = scene.meshes[0]
+    .primitives
+    .iter()
+    .map(|primitive| primitive.add_to(&mut app, &layout, &semantics).unwrap())
+    .collect();
+let textures = scene.create_textures(&app);
+```
+*/
+
+use super::MeshIndices;
+use crate::engine_core::{ManagedImage, MeshHandle, VertexInputDescriptors};
+use crate::BaseApp;
+use ash::vk;
+use glam::Mat4;
+use std::path::Path;
+
+/// Vertex attribute of a glTF primitive
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VertexSemantic {
+    Position,
+    Normal,
+    Tangent,
+    TexCoord(u32),
+    Color(u32),
+}
+
+/// Reference from a material to a texture
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextureRef {
+    /// Index into [`GltfScene::images`]
+    pub image: usize,
+    /// Which `TexCoord` set to sample with
+    pub tex_coord: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AlphaMode {
+    Opaque,
+    /// Alpha below [`PbrMaterial::alpha_cutoff`] is fully transparent, the rest is opaque
+    Mask,
+    Blend,
+}
+
+/// PBR metallic-roughness material
+#[derive(Clone, Debug, PartialEq)]
+pub struct PbrMaterial {
+    pub name: Option<String>,
+    pub base_color_factor: [f32; 4],
+    pub base_color_texture: Option<TextureRef>,
+    pub metallic_factor: f32,
+    pub roughness_factor: f32,
+    /// Roughness in the green channel and metalness in the blue channel
+    pub metallic_roughness_texture: Option<TextureRef>,
+    pub normal_texture: Option<TextureRef>,
+    pub normal_scale: f32,
+    pub occlusion_texture: Option<TextureRef>,
+    pub occlusion_strength: f32,
+    pub emissive_factor: [f32; 3],
+    pub emissive_texture: Option<TextureRef>,
+    pub alpha_mode: AlphaMode,
+    pub alpha_cutoff: f32,
+    pub double_sided: bool,
+}
+
+/// Decoded image, converted to 8-bit RGBA
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfImage {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// Whether a material uses the image for color (base color or emission), in which case it is sRGB encoded
+    pub srgb: bool,
+}
+
+/// Geometry drawn with one material
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfPrimitive {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tangents: Vec<[f32; 4]>,
+    /// One list per `TEXCOORD_n` set
+    pub tex_coords: Vec<Vec<[f32; 2]>>,
+    /// One list per `COLOR_n` set
+    pub colors: Vec<Vec<[f32; 4]>>,
+    /// Indices, generated in order for non-indexed primitives
+    pub indices: MeshIndices,
+    /// Index into [`GltfScene::materials`], `None` for the default material
+    pub material: Option<usize>,
+    pub topology: vk::PrimitiveTopology,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfMesh {
+    pub name: Option<String>,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+/// Node of the scene hierarchy
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfNode {
+    pub name: Option<String>,
+    /// Transform relative to the parent node
+    pub transform: Mat4,
+    /// Index into [`GltfScene::meshes`]
+    pub mesh: Option<usize>,
+    /// Indices into [`GltfScene::nodes`]
+    pub children: Vec<usize>,
+}
+
+/// Everything loaded from a glTF file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<PbrMaterial>,
+    pub images: Vec<GltfImage>,
+    pub nodes: Vec<GltfNode>,
+    /// Root nodes of the default scene, or of the first scene if there is no default
+    pub root_nodes: Vec<usize>,
+}
+
+impl GltfScene {
+    /// World transform of every node, indexed like [`GltfScene::nodes`]. Nodes outside the scene keep the identity
+    pub fn world_transforms(&self) -> Vec<Mat4> {
+        let mut world_transforms = vec![Mat4::IDENTITY; self.nodes.len()];
+        let mut stack: Vec<(usize, Mat4)> = self
+            .root_nodes
+            .iter()
+            .map(|root| (*root, Mat4::IDENTITY))
+            .collect();
+        while let Some((node, parent_transform)) = stack.pop() {
+            let transform = parent_transform * self.nodes[node].transform;
+            world_transforms[node] = transform;
+            stack.extend(
+                self.nodes[node]
+                    .children
+                    .iter()
+                    .map(|child| (*child, transform)),
+            );
+        }
+        world_transforms
+    }
+
+    /// Uploads every image as a sampled texture, sRGB or linear as the materials use it, see [`BaseApp::load_image_immediate`].
+    /// Indexed like [`GltfScene::images`]
+    pub fn create_textures(&self, app: &BaseApp) -> Vec<ManagedImage> {
+        self.images
+            .iter()
+            .map(|image| {
+                let format = if image.srgb {
+                    vk::Format::R8G8B8A8_SRGB
+                } else {
+                    vk::Format::R8G8B8A8_UNORM
+                };
+                app.load_image_immediate(&image.pixels, (image.width, image.height), format)
+            })
+            .collect()
+    }
+}
+
+impl GltfPrimitive {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    // Components of an attribute for one vertex, `None` if the primitive lacks the attribute
+    fn attribute(&self, semantic: VertexSemantic, vertex: usize) -> Option<[f32; 4]> {
+        match semantic {
+            VertexSemantic::Position => self
+                .positions
+                .get(vertex)
+                .map(|[x, y, z]| [*x, *y, *z, 1.0]),
+            VertexSemantic::Normal => self.normals.get(vertex).map(|[x, y, z]| [*x, *y, *z, 0.0]),
+            VertexSemantic::Tangent => self.tangents.get(vertex).copied(),
+            VertexSemantic::TexCoord(set) => self
+                .tex_coords
+                .get(set as usize)?
+                .get(vertex)
+                .map(|[u, v]| [*u, *v, 0.0, 0.0]),
+            VertexSemantic::Color(set) => self.colors.get(set as usize)?.get(vertex).copied(),
+        }
+    }
+
+    /** Packs the attributes into one buffer per binding of `layout`, in the same order as `layout.bindings`.
+    `semantics` tells which attribute each location reads. Locations without one, and attributes the primitive lacks, are zero.
+    `INSTANCE`-rate bindings get empty buffers.
+    Supported formats are 1 to 4 component `SFLOAT` and `R8G8B8A8_UNORM`. */
+    pub fn vertex_streams(
+        &self,
+        layout: &VertexInputDescriptors,
+        semantics: &[(u32, VertexSemantic)],
+    ) -> Result<Vec<Vec<u8>>, String> {
+        let vertex_count = self.vertex_count();
+        let mut streams = Vec::with_capacity(layout.bindings.len());
+        for binding in &layout.bindings {
+            if binding.input_rate == vk::VertexInputRate::INSTANCE {
+                streams.push(Vec::new());
+                continue;
+            }
+            let stride = binding.stride as usize;
+            let mut stream = vec![0u8; stride * vertex_count];
+            for attribute in layout
+                .attributes
+                .iter()
+                .filter(|attribute| attribute.binding == binding.binding)
+            {
+                let semantic = match semantics
+                    .iter()
+                    .find(|(location, _)| *location == attribute.location)
+                {
+                    Some((_, semantic)) => *semantic,
+                    None => continue,
+                };
+                for vertex in 0..vertex_count {
+                    let value = match self.attribute(semantic, vertex) {
+                        Some(value) => value,
+                        None => break,
+                    };
+                    let start = vertex * stride + attribute.offset as usize;
+                    write_attribute(&mut stream[start..], attribute.format, value)?;
+                }
+            }
+            streams.push(stream);
+        }
+        Ok(streams)
+    }
+
+    /** Uploads the primitive into the app's mesh registry, with vertices packed for binding 0 of `layout`,
+    see [`GltfPrimitive::vertex_streams`] and [`BaseApp::add_mesh_raw`]. Only triangle lists are supported. */
+    pub fn add_to(
+        &self,
+        app: &mut BaseApp,
+        layout: &VertexInputDescriptors,
+        semantics: &[(u32, VertexSemantic)],
+    ) -> Result<MeshHandle, String> {
+        if self.topology != vk::PrimitiveTopology::TRIANGLE_LIST {
+            return Err(format!(
+                "Unsupported primitive topology {:?}",
+                self.topology
+            ));
+        }
+        let binding_index = layout
+            .bindings
+            .iter()
+            .position(|binding| binding.binding == 0)
+            .ok_or("Vertex layout has no binding 0")?;
+        let streams = self.vertex_streams(layout, semantics)?;
+        Ok(self.indices.add_mesh_raw(
+            app,
+            &streams[binding_index],
+            layout.bindings[binding_index].stride,
+        ))
+    }
+}
+
+fn write_attribute(
+    destination: &mut [u8],
+    format: vk::Format,
+    value: [f32; 4],
+) -> Result<(), String> {
+    let component_count = match format {
+        vk::Format::R32_SFLOAT => 1,
+        vk::Format::R32G32_SFLOAT => 2,
+        vk::Format::R32G32B32_SFLOAT => 3,
+        vk::Format::R32G32B32A32_SFLOAT => 4,
+        vk::Format::R8G8B8A8_UNORM => {
+            for (byte, component) in destination.iter_mut().zip(value) {
+                *byte = (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+            }
+            return Ok(());
+        }
+        _ => return Err(format!("Unsupported vertex attribute format {:?}", format)),
+    };
+    for (bytes, component) in destination
+        .chunks_exact_mut(4)
+        .zip(value)
+        .take(component_count)
+    {
+        bytes.copy_from_slice(&component.to_ne_bytes());
+    }
+    Ok(())
+}
+
+/// Loads a `.gltf` or `.glb` file, along with any external buffers and images it references
+pub fn load_gltf<P: AsRef<Path>>(path: P) -> Result<GltfScene, String> {
+    let path = path.as_ref();
+    let (document, buffers, images) =
+        ::gltf::import(path).map_err(|err| format!("'{}': {}", path.display(), err))?;
+
+    let texture_ref = |texture: ::gltf::Texture, tex_coord: u32| TextureRef {
+        image: texture.source().index(),
+        tex_coord,
+    };
+    let materials: Vec<PbrMaterial> = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            PbrMaterial {
+                name: material.name().map(str::to_owned),
+                base_color_factor: pbr.base_color_factor(),
+                base_color_texture: pbr
+                    .base_color_texture()
+                    .map(|info| texture_ref(info.texture(), info.tex_coord())),
+                metallic_factor: pbr.metallic_factor(),
+                roughness_factor: pbr.roughness_factor(),
+                metallic_roughness_texture: pbr
+                    .metallic_roughness_texture()
+                    .map(|info| texture_ref(info.texture(), info.tex_coord())),
+                normal_texture: material
+                    .normal_texture()
+                    .map(|info| texture_ref(info.texture(), info.tex_coord())),
+                normal_scale: material.normal_texture().map_or(1.0, |info| info.scale()),
+                occlusion_texture: material
+                    .occlusion_texture()
+                    .map(|info| texture_ref(info.texture(), info.tex_coord())),
+                occlusion_strength: material
+                    .occlusion_texture()
+                    .map_or(1.0, |info| info.strength()),
+                emissive_factor: material.emissive_factor(),
+                emissive_texture: material
+                    .emissive_texture()
+                    .map(|info| texture_ref(info.texture(), info.tex_coord())),
+                alpha_mode: match material.alpha_mode() {
+                    ::gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                    ::gltf::material::AlphaMode::Mask => AlphaMode::Mask,
+                    ::gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+                },
+                alpha_cutoff: material.alpha_cutoff().unwrap_or(0.5),
+                double_sided: material.double_sided(),
+            }
+        })
+        .collect();
+
+    let mut images: Vec<GltfImage> = images.into_iter().map(convert_image).collect();
+    for material in &materials {
+        for texture in [material.base_color_texture, material.emissive_texture]
+            .into_iter()
+            .flatten()
+        {
+            images[texture.image].srgb = true;
+        }
+    }
+
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .ok_or_else(|| format!("'{}': primitive without positions", path.display()))?
+                .collect();
+            let tex_coords = (0..)
+                .map_while(|set| reader.read_tex_coords(set))
+                .map(|tex_coords| tex_coords.into_f32().collect())
+                .collect();
+            let colors = (0..)
+                .map_while(|set| reader.read_colors(set))
+                .map(|colors| colors.into_rgba_f32().collect())
+                .collect();
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            primitives.push(GltfPrimitive {
+                normals: reader
+                    .read_normals()
+                    .map_or_else(Vec::new, |normals| normals.collect()),
+                tangents: reader
+                    .read_tangents()
+                    .map_or_else(Vec::new, |tangents| tangents.collect()),
+                tex_coords,
+                colors,
+                indices: MeshIndices::from_u32(indices, positions.len()),
+                positions,
+                material: primitive.material().index(),
+                topology: match primitive.mode() {
+                    ::gltf::mesh::Mode::Points => vk::PrimitiveTopology::POINT_LIST,
+                    ::gltf::mesh::Mode::Lines => vk::PrimitiveTopology::LINE_LIST,
+                    // Line loops have no Vulkan equivalent
+                    ::gltf::mesh::Mode::LineLoop | ::gltf::mesh::Mode::LineStrip => {
+                        vk::PrimitiveTopology::LINE_STRIP
+                    }
+                    ::gltf::mesh::Mode::Triangles => vk::PrimitiveTopology::TRIANGLE_LIST,
+                    ::gltf::mesh::Mode::TriangleStrip => vk::PrimitiveTopology::TRIANGLE_STRIP,
+                    ::gltf::mesh::Mode::TriangleFan => vk::PrimitiveTopology::TRIANGLE_FAN,
+                },
+            });
+        }
+        meshes.push(GltfMesh {
+            name: mesh.name().map(str::to_owned),
+            primitives,
+        });
+    }
+
+    let nodes = document
+        .nodes()
+        .map(|node| GltfNode {
+            name: node.name().map(str::to_owned),
+            transform: Mat4::from_cols_array_2d(&node.transform().matrix()),
+            mesh: node.mesh().map(|mesh| mesh.index()),
+            children: node.children().map(|child| child.index()).collect(),
+        })
+        .collect();
+    let root_nodes = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .map_or_else(Vec::new, |scene| {
+            scene.nodes().map(|node| node.index()).collect()
+        });
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        images,
+        nodes,
+        root_nodes,
+    })
+}
+
+// Single and two channel images are treated as grayscale (with alpha), like the image decoders produce them
+fn convert_image(image: ::gltf::image::Data) -> GltfImage {
+    use ::gltf::image::Format;
+    let to_u8_16 = |bytes: &[u8]| (u16::from_ne_bytes([bytes[0], bytes[1]]) >> 8) as u8;
+    let to_u8_32 = |bytes: &[u8]| {
+        (f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]).clamp(0.0, 1.0) * 255.0)
+            .round() as u8
+    };
+    let pixels: Vec<u8> = match image.format {
+        Format::R8G8B8A8 => image.pixels,
+        Format::R8 => image
+            .pixels
+            .iter()
+            .flat_map(|v| [*v, *v, *v, 255])
+            .collect(),
+        Format::R8G8 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        Format::R8G8B8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        Format::R16 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|p| {
+                let v = to_u8_16(p);
+                [v, v, v, 255]
+            })
+            .collect(),
+        Format::R16G16 => image
+            .pixels
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let v = to_u8_16(&p[0..2]);
+                [v, v, v, to_u8_16(&p[2..4])]
+            })
+            .collect(),
+        Format::R16G16B16 => image
+            .pixels
+            .chunks_exact(6)
+            .flat_map(|p| {
+                [
+                    to_u8_16(&p[0..2]),
+                    to_u8_16(&p[2..4]),
+                    to_u8_16(&p[4..6]),
+                    255,
+                ]
+            })
+            .collect(),
+        Format::R16G16B16A16 => image.pixels.chunks_exact(2).map(to_u8_16).collect(),
+        Format::R32G32B32FLOAT => image
+            .pixels
+            .chunks_exact(12)
+            .flat_map(|p| {
+                [
+                    to_u8_32(&p[0..4]),
+                    to_u8_32(&p[4..8]),
+                    to_u8_32(&p[8..12]),
+                    255,
+                ]
+            })
+            .collect(),
+        Format::R32G32B32A32FLOAT => image.pixels.chunks_exact(4).map(to_u8_32).collect(),
+    };
+    GltfImage {
+        pixels,
+        width: image.width,
+        height: image.height,
+        srgb: false,
+    }
+}
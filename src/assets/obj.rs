@@ -0,0 +1,609 @@
+/*!
+Loader for Wavefront OBJ models and their MTL material libraries.
+
+Faces are triangulated as fans, and every distinct position/UV/normal combination becomes one [`ObjVertex`].
+Each object or group in the file becomes an [`ObjMesh`], with indices stored as `u16` when its vertices allow it.
+# Example:
+```ignore
+let model = vk_engine::assets::obj::load_obj("assets/teapot.obj").unwrap();
+for mesh in &model.meshes {
+    let handle = mesh.add_to(&mut app);
+}
+```
+*/
+
+use super::MeshIndices;
+use crate::engine_core::{MeshHandle, VertexInputDescriptors};
+use crate::BaseApp;
+use ash::vk;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Vertex produced by the loader. Missing UVs and normals are zero
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ObjVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+    /// Texture coordinates with V flipped, so that (0, 0) is the top left of the texture as Vulkan samples it
+    pub uv: [f32; 2],
+}
+
+impl ObjVertex {
+    /// Vertex input for [`ObjVertex`] at binding 0: position at location 0, normal at location 1 and UV at location 2
+    pub fn input_descriptors() -> VertexInputDescriptors {
+        let bindings = vec![*vk::VertexInputBindingDescription::builder()
+            .binding(0)
+            .input_rate(vk::VertexInputRate::VERTEX)
+            .stride(std::mem::size_of::<ObjVertex>() as u32)];
+        let attributes = vec![
+            *vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(0)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(std::mem::offset_of!(ObjVertex, position) as u32),
+            *vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(1)
+                .format(vk::Format::R32G32B32_SFLOAT)
+                .offset(std::mem::offset_of!(ObjVertex, normal) as u32),
+            *vk::VertexInputAttributeDescription::builder()
+                .binding(0)
+                .location(2)
+                .format(vk::Format::R32G32_SFLOAT)
+                .offset(std::mem::offset_of!(ObjVertex, uv) as u32),
+        ];
+        VertexInputDescriptors {
+            bindings,
+            attributes,
+        }
+    }
+}
+
+/// Range of a mesh's indices drawn with one material, as set by `usemtl`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialRange {
+    /// Name of the material, look it up in [`ObjModel::materials`]. `None` before any `usemtl`
+    pub material: Option<String>,
+    pub first_index: u32,
+    pub index_count: u32,
+}
+
+/// An object or group of an OBJ file
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMesh {
+    pub name: String,
+    pub vertices: Vec<ObjVertex>,
+    pub indices: MeshIndices,
+    pub material_ranges: Vec<MaterialRange>,
+}
+
+impl ObjMesh {
+    /// Uploads the mesh into the app's mesh registry, see [`BaseApp::add_mesh`]
+    pub fn add_to(&self, app: &mut BaseApp) -> MeshHandle {
+        self.indices.add_mesh(app, &self.vertices)
+    }
+}
+
+/// Material from an MTL file. Texture paths are relative to the MTL file unless loaded through [`load_obj`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjMaterial {
+    pub name: String,
+    /// `Ka`
+    pub ambient: [f32; 3],
+    /// `Kd`
+    pub diffuse: [f32; 3],
+    /// `Ks`
+    pub specular: [f32; 3],
+    /// `Ke`
+    pub emissive: [f32; 3],
+    /// `Ns`
+    pub shininess: f32,
+    /// `d`, or one minus `Tr`
+    pub dissolve: f32,
+    /// `illum`
+    pub illumination_model: Option<u32>,
+    /// `map_Kd`
+    pub diffuse_texture: Option<PathBuf>,
+    /// `map_Ks`
+    pub specular_texture: Option<PathBuf>,
+    /// `map_Bump`, `bump` or `norm`
+    pub normal_texture: Option<PathBuf>,
+    /// `map_d`
+    pub alpha_texture: Option<PathBuf>,
+}
+
+impl ObjMaterial {
+    fn new(name: &str) -> ObjMaterial {
+        ObjMaterial {
+            name: name.to_owned(),
+            ambient: [0.0; 3],
+            diffuse: [1.0; 3],
+            specular: [0.0; 3],
+            emissive: [0.0; 3],
+            shininess: 0.0,
+            dissolve: 1.0,
+            illumination_model: None,
+            diffuse_texture: None,
+            specular_texture: None,
+            normal_texture: None,
+            alpha_texture: None,
+        }
+    }
+}
+
+/// Everything loaded from an OBJ file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ObjModel {
+    pub meshes: Vec<ObjMesh>,
+    /// Materials from every referenced material library. Empty if the model was only parsed with [`parse_obj`]
+    pub materials: Vec<ObjMaterial>,
+    /// Material libraries referenced by `mtllib`, as written in the file
+    pub material_libraries: Vec<String>,
+}
+
+impl ObjModel {
+    pub fn material(&self, name: &str) -> Option<&ObjMaterial> {
+        self.materials.iter().find(|material| material.name == name)
+    }
+}
+
+/// Loads an OBJ file along with the material libraries it references, which are looked up relative to the OBJ file
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<ObjModel, String> {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("Could not read '{}': {}", path.display(), err))?;
+    let mut model = parse_obj(&source).map_err(|err| format!("'{}': {}", path.display(), err))?;
+
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
+    for library in &model.material_libraries {
+        let library_path = directory.join(library);
+        let library_source = std::fs::read_to_string(&library_path)
+            .map_err(|err| format!("Could not read '{}': {}", library_path.display(), err))?;
+        let mut materials = parse_mtl(&library_source)
+            .map_err(|err| format!("'{}': {}", library_path.display(), err))?;
+        for material in materials.iter_mut() {
+            for texture in [
+                &mut material.diffuse_texture,
+                &mut material.specular_texture,
+                &mut material.normal_texture,
+                &mut material.alpha_texture,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                *texture = directory.join(&*texture);
+            }
+        }
+        model.materials.extend(materials);
+    }
+    Ok(model)
+}
+
+// Collects the faces of one object or group
+#[derive(Default)]
+struct MeshBuilder {
+    name: String,
+    vertices: Vec<ObjVertex>,
+    indices: Vec<u32>,
+    vertex_lookup: HashMap<(usize, Option<usize>, Option<usize>), u32>,
+    material_ranges: Vec<MaterialRange>,
+}
+
+impl MeshBuilder {
+    fn new(name: &str, material: Option<String>) -> MeshBuilder {
+        MeshBuilder {
+            name: name.to_owned(),
+            material_ranges: vec![MaterialRange {
+                material,
+                first_index: 0,
+                index_count: 0,
+            }],
+            ..MeshBuilder::default()
+        }
+    }
+
+    fn use_material(&mut self, material: Option<String>) {
+        let first_index = self.indices.len() as u32;
+        let current = self.material_ranges.last_mut().unwrap();
+        if current.index_count == 0 {
+            current.material = material;
+        } else {
+            self.material_ranges.push(MaterialRange {
+                material,
+                first_index,
+                index_count: 0,
+            });
+        }
+    }
+
+    fn build(mut self) -> Option<ObjMesh> {
+        if self.indices.is_empty() {
+            return None;
+        }
+        self.material_ranges.retain(|range| range.index_count > 0);
+        let vertex_count = self.vertices.len();
+        Some(ObjMesh {
+            name: self.name,
+            vertices: self.vertices,
+            indices: MeshIndices::from_u32(self.indices, vertex_count),
+            material_ranges: self.material_ranges,
+        })
+    }
+}
+
+/// Parses the contents of an OBJ file. Material libraries are listed in [`ObjModel::material_libraries`] but not loaded
+pub fn parse_obj(source: &str) -> Result<ObjModel, String> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut model = ObjModel::default();
+    let mut current_material: Option<String> = None;
+    let mut mesh = MeshBuilder::new("", None);
+
+    for (line_index, line) in source.lines().enumerate() {
+        let line_error = |message: &str| format!("line {}: {}", line_index + 1, message);
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+        match keyword {
+            "v" => positions.push(parse_floats(&arguments, 3).map_err(|err| line_error(&err))?),
+            "vn" => normals.push(parse_floats(&arguments, 3).map_err(|err| line_error(&err))?),
+            "vt" => {
+                let [u, v] = parse_floats(&arguments, 1).map_err(|err| line_error(&err))?;
+                uvs.push([u, 1.0 - v]);
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(line_error("Face with fewer than three vertices"));
+                }
+                let mut face = Vec::with_capacity(arguments.len());
+                for argument in &arguments {
+                    let key =
+                        parse_face_vertex(argument, positions.len(), uvs.len(), normals.len())
+                            .map_err(|err| line_error(&err))?;
+                    let index = match mesh.vertex_lookup.get(&key) {
+                        Some(index) => *index,
+                        None => {
+                            let (position, uv, normal) = key;
+                            let index = mesh.vertices.len() as u32;
+                            mesh.vertices.push(ObjVertex {
+                                position: positions[position],
+                                normal: normal.map_or([0.0; 3], |normal| normals[normal]),
+                                uv: uv.map_or([0.0; 2], |uv| uvs[uv]),
+                            });
+                            mesh.vertex_lookup.insert(key, index);
+                            index
+                        }
+                    };
+                    face.push(index);
+                }
+                for corner in 1..face.len() - 1 {
+                    mesh.indices
+                        .extend_from_slice(&[face[0], face[corner], face[corner + 1]]);
+                }
+                mesh.material_ranges.last_mut().unwrap().index_count += 3 * (face.len() as u32 - 2);
+            }
+            "o" | "g" => {
+                let name = arguments.join(" ");
+                let previous =
+                    std::mem::replace(&mut mesh, MeshBuilder::new(&name, current_material.clone()));
+                model.meshes.extend(previous.build());
+            }
+            "usemtl" => {
+                current_material = Some(arguments.join(" "));
+                mesh.use_material(current_material.clone());
+            }
+            "mtllib" => model
+                .material_libraries
+                .extend(arguments.iter().map(|library| library.to_string())),
+            // Smoothing groups, lines, points and free-form geometry are not supported
+            _ => (),
+        }
+    }
+    model.meshes.extend(mesh.build());
+    Ok(model)
+}
+
+/// Parses the contents of an MTL file
+pub fn parse_mtl(source: &str) -> Result<Vec<ObjMaterial>, String> {
+    let mut materials: Vec<ObjMaterial> = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let line_error = |message: &str| format!("line {}: {}", line_index + 1, message);
+        let line = line.split('#').next().unwrap().trim();
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let arguments: Vec<&str> = tokens.collect();
+        if keyword == "newmtl" {
+            materials.push(ObjMaterial::new(&arguments.join(" ")));
+            continue;
+        }
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err(line_error("Material property before any newmtl")),
+        };
+        let single = |arguments: &[&str]| -> Result<f32, String> {
+            let [value] = parse_floats(arguments, 1).map_err(|err| line_error(&err))?;
+            Ok(value)
+        };
+        // Texture maps may have options before the file name, which comes last
+        let texture = || arguments.last().map(PathBuf::from);
+        match keyword {
+            "Ka" => {
+                material.ambient = parse_floats(&arguments, 3).map_err(|err| line_error(&err))?
+            }
+            "Kd" => {
+                material.diffuse = parse_floats(&arguments, 3).map_err(|err| line_error(&err))?
+            }
+            "Ks" => {
+                material.specular = parse_floats(&arguments, 3).map_err(|err| line_error(&err))?
+            }
+            "Ke" => {
+                material.emissive = parse_floats(&arguments, 3).map_err(|err| line_error(&err))?
+            }
+            "Ns" => material.shininess = single(&arguments)?,
+            "d" => material.dissolve = single(&arguments)?,
+            "Tr" => material.dissolve = 1.0 - single(&arguments)?,
+            "illum" => {
+                material.illumination_model = Some(
+                    arguments
+                        .first()
+                        .and_then(|value| value.parse().ok())
+                        .ok_or_else(|| line_error("Expected an illumination model number"))?,
+                )
+            }
+            "map_Kd" => material.diffuse_texture = texture(),
+            "map_Ks" => material.specular_texture = texture(),
+            "map_Bump" | "map_bump" | "bump" | "norm" => material.normal_texture = texture(),
+            "map_d" => material.alpha_texture = texture(),
+            _ => (),
+        }
+    }
+    Ok(materials)
+}
+
+// Parses `N` floats from the start of `arguments`, requiring at least `required` of them. Missing ones are zero
+fn parse_floats<const N: usize>(arguments: &[&str], required: usize) -> Result<[f32; N], String> {
+    if arguments.len() < required {
+        return Err(format!(
+            "Expected {} numbers, found {}",
+            required,
+            arguments.len()
+        ));
+    }
+    let mut values = [0.0; N];
+    for (value, argument) in values.iter_mut().zip(arguments) {
+        *value = argument
+            .parse()
+            .map_err(|_| format!("'{}' is not a number", argument))?;
+    }
+    Ok(values)
+}
+
+// Parses a `v`, `v/vt`, `v//vn` or `v/vt/vn` face vertex into zero-based indices, resolving negative (relative) indices
+fn parse_face_vertex(
+    argument: &str,
+    position_count: usize,
+    uv_count: usize,
+    normal_count: usize,
+) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let resolve = |index: &str, count: usize, kind: &str| -> Result<usize, String> {
+        let index: i64 = index
+            .parse()
+            .map_err(|_| format!("'{}' is not a valid {} index", index, kind))?;
+        let resolved = if index < 0 {
+            count as i64 + index
+        } else {
+            index - 1
+        };
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(format!("{} index {} is out of range", kind, index));
+        }
+        Ok(resolved as usize)
+    };
+    let mut parts = argument.split('/');
+    let position = resolve(parts.next().unwrap(), position_count, "position")?;
+    let uv = match parts.next() {
+        Some("") | None => None,
+        Some(uv) => Some(resolve(uv, uv_count, "texture coordinate")?),
+    };
+    let normal = match parts.next() {
+        Some("") | None => None,
+        Some(normal) => Some(resolve(normal, normal_count, "normal")?),
+    };
+    Ok((position, uv, normal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUAD: &str = "
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+";
+
+    fn indices(mesh: &ObjMesh) -> Vec<u32> {
+        match &mesh.indices {
+            MeshIndices::U16(indices) => indices.iter().map(|index| *index as u32).collect(),
+            MeshIndices::U32(indices) => indices.clone(),
+        }
+    }
+
+    #[test]
+    fn face_with_positions_uvs_and_normals() {
+        let model = parse_obj(&format!("{}f 1/1/1 2/2/1 3/3/1", QUAD)).unwrap();
+        let mesh = &model.meshes[0];
+        assert_eq!(indices(mesh), [0, 1, 2]);
+        assert_eq!(
+            mesh.vertices[1],
+            ObjVertex {
+                position: [1.0, 0.0, 0.0],
+                normal: [0.0, 0.0, 1.0],
+                uv: [1.0, 1.0],
+            }
+        );
+    }
+
+    #[test]
+    fn face_with_positions_and_normals() {
+        let model = parse_obj(&format!("{}f 1//1 2//1 3//1", QUAD)).unwrap();
+        let vertex = model.meshes[0].vertices[2];
+        assert_eq!(vertex.position, [1.0, 1.0, 0.0]);
+        assert_eq!(vertex.normal, [0.0, 0.0, 1.0]);
+        assert_eq!(vertex.uv, [0.0, 0.0]);
+    }
+
+    #[test]
+    fn face_with_positions_only() {
+        let model = parse_obj(&format!("{}f 1 2 3", QUAD)).unwrap();
+        let vertex = model.meshes[0].vertices[0];
+        assert_eq!(vertex.position, [0.0, 0.0, 0.0]);
+        assert_eq!(vertex.normal, [0.0; 3]);
+        assert_eq!(vertex.uv, [0.0; 2]);
+    }
+
+    #[test]
+    fn negative_indices_are_relative_to_the_end() {
+        let relative = parse_obj(&format!("{}f -4/-4/-1 -3/-3/-1 -2/-2/-1", QUAD)).unwrap();
+        let absolute = parse_obj(&format!("{}f 1/1/1 2/2/1 3/3/1", QUAD)).unwrap();
+        assert_eq!(relative.meshes, absolute.meshes);
+    }
+
+    #[test]
+    fn out_of_range_indices_are_errors() {
+        assert!(parse_obj(&format!("{}f 1 2 5", QUAD)).is_err());
+        assert!(parse_obj(&format!("{}f 1 2 -5", QUAD)).is_err());
+        assert!(parse_obj(&format!("{}f 0 1 2", QUAD)).is_err());
+        assert!(parse_obj(&format!("{}f 1/1/2 2/2/1 3/3/1", QUAD)).is_err());
+    }
+
+    #[test]
+    fn polygons_are_triangulated_as_fans() {
+        let model = parse_obj(&format!("{}v 0.5 2 0\nf 1 2 3 5 4", QUAD)).unwrap();
+        assert_eq!(indices(&model.meshes[0]), [0, 1, 2, 0, 2, 3, 0, 3, 4]);
+        assert_eq!(model.meshes[0].material_ranges[0].index_count, 9);
+    }
+
+    #[test]
+    fn shared_vertices_are_deduplicated() {
+        let model =
+            parse_obj(&format!("{}f 1/1/1 2/2/1 3/3/1\nf 1/1/1 3/3/1 4/4/1", QUAD)).unwrap();
+        let mesh = &model.meshes[0];
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(indices(mesh), [0, 1, 2, 0, 2, 3]);
+
+        // Same position with another UV is a distinct vertex
+        let model = parse_obj(&format!("{}f 1/1 2/2 3/3\nf 1/2 3/3 4/4", QUAD)).unwrap();
+        assert_eq!(model.meshes[0].vertices.len(), 5);
+    }
+
+    #[test]
+    fn objects_and_groups_become_meshes() {
+        let model = parse_obj(&format!("{}o first\nf 1 2 3\ng second\nf 1 3 4", QUAD)).unwrap();
+        let names: Vec<&str> = model.meshes.iter().map(|mesh| mesh.name.as_str()).collect();
+        assert_eq!(names, ["first", "second"]);
+        assert_eq!(indices(&model.meshes[1]), [0, 1, 2]);
+    }
+
+    #[test]
+    fn material_ranges_follow_usemtl() {
+        let source = format!(
+            "{}mtllib scene.mtl\nf 1 2 3\nusemtl red\nusemtl blue\nf 1 3 4\nf 2 3 4\nusemtl red\nf 1 2 4",
+            QUAD
+        );
+        let model = parse_obj(&source).unwrap();
+        assert_eq!(model.material_libraries, ["scene.mtl"]);
+        let ranges = &model.meshes[0].material_ranges;
+        let expected = [(None, 0, 3), (Some("blue"), 3, 6), (Some("red"), 9, 3)];
+        assert_eq!(ranges.len(), expected.len());
+        for (range, (material, first_index, index_count)) in ranges.iter().zip(expected) {
+            assert_eq!(range.material.as_deref(), material);
+            assert_eq!(range.first_index, first_index);
+            assert_eq!(range.index_count, index_count);
+        }
+    }
+
+    #[test]
+    fn material_carries_over_to_new_objects() {
+        let model = parse_obj(&format!("{}usemtl red\nf 1 2 3\no next\nf 1 3 4", QUAD)).unwrap();
+        assert_eq!(
+            model.meshes[1].material_ranges[0].material.as_deref(),
+            Some("red")
+        );
+    }
+
+    #[test]
+    fn indices_are_u16_up_to_65536_vertices() {
+        let triangle_vertices = |count: usize| {
+            let mut source = String::new();
+            for i in 0..count {
+                source.push_str(&format!("v {} 0 0\n", i));
+            }
+            for i in (0..count).step_by(3) {
+                source.push_str(&format!("f {} {} {}\n", i + 1, i + 2, i + 3));
+            }
+            source
+        };
+        // 65535 vertices in triangles, plus one more triangle reusing two of them
+        let mut source = triangle_vertices(65535);
+        source.push_str("v -1 0 0\nf 1 2 65536\n");
+        let model = parse_obj(&source).unwrap();
+        assert_eq!(model.meshes[0].vertices.len(), 65536);
+        assert!(matches!(model.meshes[0].indices, MeshIndices::U16(_)));
+
+        let model = parse_obj(&triangle_vertices(65538)).unwrap();
+        assert!(matches!(model.meshes[0].indices, MeshIndices::U32(_)));
+    }
+
+    #[test]
+    fn mtl_properties() {
+        let source = "
+newmtl red
+Ka 0.1 0.2 0.3
+Kd 1 0 0
+Ks 0.5 0.5 0.5
+Ke 0 0 0.25
+Ns 32 # shininess
+Tr 0.25
+illum 2
+map_Kd -s 1 1 1 textures/red.png
+map_Bump normal.png
+
+newmtl plain
+";
+        let materials = parse_mtl(source).unwrap();
+        assert_eq!(materials.len(), 2);
+        let red = &materials[0];
+        assert_eq!(red.name, "red");
+        assert_eq!(red.ambient, [0.1, 0.2, 0.3]);
+        assert_eq!(red.diffuse, [1.0, 0.0, 0.0]);
+        assert_eq!(red.specular, [0.5; 3]);
+        assert_eq!(red.emissive, [0.0, 0.0, 0.25]);
+        assert_eq!(red.shininess, 32.0);
+        assert_eq!(red.dissolve, 0.75);
+        assert_eq!(red.illumination_model, Some(2));
+        assert_eq!(red.diffuse_texture, Some(PathBuf::from("textures/red.png")));
+        assert_eq!(red.normal_texture, Some(PathBuf::from("normal.png")));
+        assert_eq!(materials[1], ObjMaterial::new("plain"));
+    }
+
+    #[test]
+    fn mtl_property_before_newmtl_is_an_error() {
+        assert!(parse_mtl("Kd 1 1 1\nnewmtl late").is_err());
+    }
+}
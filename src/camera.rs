@@ -0,0 +1,326 @@
+/*!
+Cameras producing view and projection matrices for Vulkan, and controllers moving them from [`Input`].
+
+The world is right-handed with +Y up. Projections map it to Vulkan clip space, where +Y points down the screen
+and depth goes from 0 at the near plane to 1 at the far plane, or the other way around with reverse-Z.
+# Example:
+```ignore
+let mut camera = Camera::perspective(f32::to_radians(70.0), 0.01, None);
+camera.look_at(Vec3::new(0.0, 2.0, -4.0), Vec3::ZERO);
+let mut controller = OrbitController::new(Vec3::ZERO, &camera);
+
+// Each frame, with an `Input` fed the window's events
+controller.update(&mut camera, &input);
+let ubo = camera.mvp(model, aspect_ratio);
+```
+*/
+
+use crate::input::Input;
+use crate::MVP;
+use glam::{Mat4, Quat, Vec3};
+use winit::event::{MouseButton, VirtualKeyCode};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    Perspective {
+        /// Vertical field of view in radians
+        fov_y: f32,
+        near: f32,
+        /// `None` for an infinitely distant far plane
+        far: Option<f32>,
+    },
+    Orthographic {
+        /// Height of the view volume in world units. The width follows from the aspect ratio
+        height: f32,
+        near: f32,
+        far: f32,
+    },
+}
+
+/** Position, orientation and projection of a viewer.
+With `reverse_z`, depth is 1 at the near plane and 0 at the far plane, which spreads depth precision far better
+with floating point depth buffers. The pipeline must then use a `GREATER` depth compare op and clear depth to 0. */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position: Vec3,
+    /// Rotation from camera space, looking down -Z with +Y up, to world space
+    pub rotation: Quat,
+    pub projection: Projection,
+    pub reverse_z: bool,
+}
+
+impl Camera {
+    /// Perspective camera at the origin looking down -Z
+    pub fn perspective(fov_y: f32, near: f32, far: Option<f32>) -> Camera {
+        Camera {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            projection: Projection::Perspective { fov_y, near, far },
+            reverse_z: false,
+        }
+    }
+
+    /// Orthographic camera at the origin looking down -Z
+    pub fn orthographic(height: f32, near: f32, far: f32) -> Camera {
+        Camera {
+            position: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            projection: Projection::Orthographic { height, near, far },
+            reverse_z: false,
+        }
+    }
+
+    pub fn with_reverse_z(mut self, reverse_z: bool) -> Self {
+        self.reverse_z = reverse_z;
+        self
+    }
+
+    /// Moves the camera to `eye`, looking at `target` with +Y up
+    pub fn look_at(&mut self, eye: Vec3, target: Vec3) {
+        self.position = eye;
+        let view = Mat4::look_at_rh(eye, target, Vec3::Y);
+        self.rotation = Quat::from_mat4(&view.inverse());
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation * Vec3::NEG_Z
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.rotation * Vec3::X
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    /// World to camera space
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::from_rotation_translation(self.rotation, self.position).inverse()
+    }
+
+    /// Camera space to Vulkan clip space, for a viewport of the given width divided by height
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Mat4 {
+        let mut projection = match self.projection {
+            Projection::Perspective { fov_y, near, far } => match (far, self.reverse_z) {
+                (None, false) => Mat4::perspective_infinite_rh(fov_y, aspect_ratio, near),
+                (None, true) => Mat4::perspective_infinite_reverse_rh(fov_y, aspect_ratio, near),
+                (Some(far), false) => Mat4::perspective_rh(fov_y, aspect_ratio, near, far),
+                (Some(far), true) => Mat4::perspective_rh(fov_y, aspect_ratio, far, near),
+            },
+            Projection::Orthographic { height, near, far } => {
+                let (half_width, half_height) = (height * aspect_ratio / 2.0, height / 2.0);
+                let (near, far) = if self.reverse_z {
+                    (far, near)
+                } else {
+                    (near, far)
+                };
+                Mat4::orthographic_rh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    near,
+                    far,
+                )
+            }
+        };
+        // Vulkan's clip space has +Y pointing down
+        projection.y_axis.y = -projection.y_axis.y;
+        projection
+    }
+
+    /// Combined projection and view matrix
+    pub fn view_projection(&self, aspect_ratio: f32) -> Mat4 {
+        self.projection_matrix(aspect_ratio) * self.view_matrix()
+    }
+
+    /// Matrices for drawing a model with the given transform through this camera
+    pub fn mvp(&self, model: Mat4, aspect_ratio: f32) -> MVP {
+        MVP {
+            model,
+            view: self.view_matrix(),
+            projection: self.projection_matrix(aspect_ratio),
+        }
+    }
+}
+
+/** Orbits the camera around a target point. Dragging with the left mouse button rotates,
+dragging with the middle mouse button pans, and scrolling zooms. */
+pub struct OrbitController {
+    pub target: Vec3,
+    pub distance: f32,
+    /// Rotation around +Y in radians
+    pub yaw: f32,
+    /// Elevation above the target in radians, kept short of straight up or down
+    pub pitch: f32,
+    /// Radians per pixel of mouse motion
+    pub rotate_speed: f32,
+    /// Fraction of the distance zoomed per scroll line
+    pub zoom_speed: f32,
+    /// Fraction of the distance panned per pixel of mouse motion
+    pub pan_speed: f32,
+}
+
+impl OrbitController {
+    /// Orbits `target` from wherever `camera` currently is
+    pub fn new(target: Vec3, camera: &Camera) -> OrbitController {
+        let offset = camera.position - target;
+        let distance = offset.length().max(f32::EPSILON);
+        OrbitController {
+            target,
+            distance,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / distance).clamp(-1.0, 1.0).asin(),
+            rotate_speed: 0.005,
+            zoom_speed: 0.1,
+            pan_speed: 0.002,
+        }
+    }
+
+    /// Moves the camera by this frame's input
+    pub fn update(&mut self, camera: &mut Camera, input: &Input) {
+        let mouse_delta = input.mouse_motion();
+        if input.button_held(MouseButton::Left) {
+            self.yaw -= mouse_delta.x * self.rotate_speed;
+            self.pitch += mouse_delta.y * self.rotate_speed;
+            let limit = std::f32::consts::FRAC_PI_2 - 0.01;
+            self.pitch = self.pitch.clamp(-limit, limit);
+        }
+        if input.button_held(MouseButton::Middle) {
+            let pan = (-camera.right() * mouse_delta.x + camera.up() * mouse_delta.y)
+                * self.pan_speed
+                * self.distance;
+            self.target += pan;
+        }
+        self.distance *= (1.0 - self.zoom_speed).powf(input.scroll().y);
+
+        let offset = Vec3::new(
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.cos(),
+        ) * self.distance;
+        camera.look_at(self.target + offset, self.target);
+    }
+}
+
+/** First-person camera movement. W/A/S/D move, Space and Left Shift move up and down,
+and moving the mouse while holding the right mouse button looks around. */
+pub struct FlyController {
+    /// Units per second
+    pub move_speed: f32,
+    /// Radians per pixel of mouse motion
+    pub look_speed: f32,
+    /// Rotation around +Y in radians
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl FlyController {
+    /// Continues from the direction `camera` currently faces
+    pub fn new(camera: &Camera) -> FlyController {
+        let forward = camera.forward();
+        FlyController {
+            move_speed: 3.0,
+            look_speed: 0.003,
+            yaw: (-forward.x).atan2(-forward.z),
+            pitch: forward.y.clamp(-1.0, 1.0).asin(),
+        }
+    }
+
+    /// Moves the camera by this frame's input, as far as `delta_time` seconds allow
+    pub fn update(&mut self, camera: &mut Camera, input: &Input, delta_time: f32) {
+        let mouse_delta = input.mouse_motion();
+        if input.button_held(MouseButton::Right) {
+            self.yaw -= mouse_delta.x * self.look_speed;
+            self.pitch -= mouse_delta.y * self.look_speed;
+            let limit = std::f32::consts::FRAC_PI_2 - 0.01;
+            self.pitch = self.pitch.clamp(-limit, limit);
+        }
+        camera.rotation = Quat::from_rotation_y(self.yaw) * Quat::from_rotation_x(self.pitch);
+
+        let movement = camera.forward() * input.key_axis(VirtualKeyCode::W, VirtualKeyCode::S)
+            + camera.right() * input.key_axis(VirtualKeyCode::D, VirtualKeyCode::A)
+            + Vec3::Y * input.key_axis(VirtualKeyCode::Space, VirtualKeyCode::LShift);
+        camera.position += movement.normalize_or_zero() * self.move_speed * delta_time;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec4Swizzles;
+
+    /// Normalized device coordinates of a world space point
+    fn project(camera: &Camera, point: Vec3) -> Vec3 {
+        let clip = camera.view_projection(1.0) * point.extend(1.0);
+        clip.xyz() / clip.w
+    }
+
+    fn assert_near(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn clip_space_y_points_down() {
+        let cameras = [
+            Camera::perspective(f32::to_radians(90.0), 0.1, Some(100.0)),
+            Camera::perspective(f32::to_radians(90.0), 0.1, None),
+            Camera::orthographic(4.0, 0.1, 100.0),
+        ];
+        for camera in cameras {
+            let above = project(&camera, Vec3::new(0.0, 1.0, -2.0));
+            let right = project(&camera, Vec3::new(1.0, 0.0, -2.0));
+            assert!(above.y < 0.0, "{camera:?} maps up to {above}");
+            assert!(right.x > 0.0, "{camera:?} maps right to {right}");
+        }
+    }
+
+    #[test]
+    fn depth_goes_from_zero_at_near_to_one_at_far() {
+        let perspective = Camera::perspective(f32::to_radians(70.0), 0.5, Some(50.0));
+        assert_near(project(&perspective, Vec3::new(0.0, 0.0, -0.5)).z, 0.0);
+        assert_near(project(&perspective, Vec3::new(0.0, 0.0, -50.0)).z, 1.0);
+
+        let orthographic = Camera::orthographic(2.0, 0.5, 50.0);
+        assert_near(project(&orthographic, Vec3::new(0.0, 0.0, -0.5)).z, 0.0);
+        assert_near(project(&orthographic, Vec3::new(0.0, 0.0, -50.0)).z, 1.0);
+        assert_near(project(&orthographic, Vec3::new(0.0, 0.0, -25.25)).z, 0.5);
+
+        let infinite = Camera::perspective(f32::to_radians(70.0), 0.5, None);
+        assert_near(project(&infinite, Vec3::new(0.0, 0.0, -0.5)).z, 0.0);
+        let distant = project(&infinite, Vec3::new(0.0, 0.0, -1.0e6)).z;
+        assert!(distant < 1.0 && distant > 0.999, "distant depth {distant}");
+    }
+
+    #[test]
+    fn reverse_z_maps_near_to_one_and_far_to_zero() {
+        let perspective =
+            Camera::perspective(f32::to_radians(70.0), 0.5, Some(50.0)).with_reverse_z(true);
+        assert_near(project(&perspective, Vec3::new(0.0, 0.0, -0.5)).z, 1.0);
+        assert_near(project(&perspective, Vec3::new(0.0, 0.0, -50.0)).z, 0.0);
+
+        let orthographic = Camera::orthographic(2.0, 0.5, 50.0).with_reverse_z(true);
+        assert_near(project(&orthographic, Vec3::new(0.0, 0.0, -0.5)).z, 1.0);
+        assert_near(project(&orthographic, Vec3::new(0.0, 0.0, -50.0)).z, 0.0);
+
+        let infinite = Camera::perspective(f32::to_radians(70.0), 0.5, None).with_reverse_z(true);
+        assert_near(project(&infinite, Vec3::new(0.0, 0.0, -0.5)).z, 1.0);
+        let distant = project(&infinite, Vec3::new(0.0, 0.0, -1.0e6)).z;
+        assert!(distant > 0.0 && distant < 0.001, "distant depth {distant}");
+    }
+
+    #[test]
+    fn look_at_faces_the_target_upright() {
+        let mut camera = Camera::perspective(f32::to_radians(70.0), 0.1, None);
+        camera.look_at(Vec3::new(3.0, 2.0, 5.0), Vec3::new(1.0, 0.0, 1.0));
+        let centre = project(&camera, Vec3::new(1.0, 0.0, 1.0));
+        assert_near(centre.x, 0.0);
+        assert_near(centre.y, 0.0);
+        assert!(camera.up().y > 0.0);
+        assert_near(camera.right().y, 0.0);
+    }
+}
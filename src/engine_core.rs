@@ -3,65 +3,130 @@ use ash::extensions::khr::{Surface, Swapchain};
 use ash::{vk, Device, Entry, Instance};
 use cstr::cstr;
 use glam::*;
-use std::collections::HashSet;
 use std::ffi::CStr;
 use std::os::raw::{c_char, c_void};
-use std::rc::Rc;
+use std::sync::Arc;
 use winit::window::Window;
 
+mod bindless;
 mod buffer;
+mod debug_utils;
+mod deletion;
+mod descriptors;
+mod device;
+mod features;
+mod frame_stats;
+mod indirect;
+mod instancing;
+mod mesh;
 mod phys_device;
 mod pipeline;
+mod profiler;
+mod staging;
 mod swapchain;
+mod sync;
 mod textures;
-
+mod threading;
+mod uniform;
+mod upload;
+mod validation;
+
+pub use bindless::{
+    cmd_push_texture_indices, BindlessTextures, BINDLESS_TEXTURE_BINDING,
+    DEFAULT_BINDLESS_CAPACITY, MAX_PUSHED_TEXTURE_INDICES, TEXTURE_INDEX_PUSH_CONSTANT_OFFSET,
+};
 pub use buffer::ManagedBuffer;
+pub use debug_utils::DebugNamer;
+pub use deletion::DeletionQueue;
+pub use descriptors::{
+    descriptor_pool_sizes, DescriptorAllocator, DescriptorSetBuilder, DEFAULT_SETS_PER_POOL,
+    MAX_SETS_PER_POOL,
+};
+pub use device::LogicalDevice;
+pub use features::{DeviceFeatures, DeviceRequirements, EnabledFeatures};
+pub use frame_stats::{FrameStats, DEFAULT_FRAME_STATS_WINDOW};
+pub use indirect::{cmd_draw_indexed_indirect, IndirectBuffer, IndirectDrawSupport};
+pub use instancing::{InstanceBuffer, InstanceTransform, INSTANCE_BINDING};
+pub use mesh::{cmd_draw_mesh, MeshHandle, MeshRegistry};
+pub use phys_device::{
+    DeviceSelector, PhysicalDeviceInfo, QueueFamilyIndices, DEVICE_SELECTOR_ENV,
+};
 pub use pipeline::VertexInputDescriptors;
+pub use profiler::{
+    chrome_trace_json, GpuFrameTimings, GpuProfiler, GpuScope, ProfileScope,
+    DEFAULT_MAX_PROFILE_SCOPES, PROFILER_HISTORY_LENGTH,
+};
+pub use staging::StagingRing;
+pub use sync::{
+    cmd_image_barrier, create_timeline_semaphore, timeline_semaphore_value,
+    wait_timeline_semaphore, ImageTransition,
+};
 pub use textures::ManagedImage;
-
-pub trait ValidIndexBufferType {}
-impl ValidIndexBufferType for u16 {}
-impl ValidIndexBufferType for u32 {}
+pub use threading::{DeviceContext, ThreadCommandPool};
+pub use uniform::{align_offset, max_alignment, Std140, Std430, UniformBuffer};
+pub use upload::{UploadBatch, UploadManager};
+pub use validation::{
+    check_validation_features_support, init_debug_messenger_info, ValidationHandler,
+    ValidationMessage, ValidationObject, ValidationSettings, ValidationSeverity, VALIDATION_ENV,
+    VALIDATION_LOG_TARGET,
+};
+pub use vk_engine_derive::{Std140, Std430};
+
+pub trait ValidIndexBufferType {
+    /// Index type to bind index buffers of this type with
+    const INDEX_TYPE: vk::IndexType;
+}
+impl ValidIndexBufferType for u16 {
+    const INDEX_TYPE: vk::IndexType = vk::IndexType::UINT16;
+}
+impl ValidIndexBufferType for u32 {
+    const INDEX_TYPE: vk::IndexType = vk::IndexType::UINT32;
+}
 
 //["VK_LAYER_KHRONOS_validation\0" as *const str as *const [c_char] as *const c_char];
 pub const VALIDATION_LAYERS: [*const c_char; 1] = [cstr!("VK_LAYER_KHRONOS_validation").as_ptr()];
+/// Whether validation is on by default, see [`ValidationSettings`]
 #[cfg(debug_assertions)]
 pub const VALIDATION_ENABLED: bool = true;
 #[cfg(not(debug_assertions))]
 pub const VALIDATION_ENABLED: bool = false;
 
+/// Device extensions required by default, see [`DeviceRequirements`]
 pub const DEVICE_EXTS: [*const c_char; 1] = [Swapchain::name().as_ptr()];
 pub const GRAPHICS_Q_IDX: usize = 0;
 pub const PRESENT_Q_IDX: usize = 1;
+/// Default total size of the [`StagingRing`] owned by [`crate::BaseApp`]
+pub const DEFAULT_STAGING_RING_SIZE: vk::DeviceSize = 16 * 1024 * 1024;
 pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
 
-pub fn init_debug_messenger_info() -> vk::DebugUtilsMessengerCreateInfoEXTBuilder<'static> {
-    let messenger_info = vk::DebugUtilsMessengerCreateInfoEXT::builder()
-        .message_severity(
-            //vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE_EXT |
-            vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
-                | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
-        )
-        .message_type(
-            vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
-                | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
-                | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
-        )
-        .pfn_user_callback(Some(debug_callback));
-
-    messenger_info
-}
-unsafe extern "system" fn debug_callback(
-    _message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
-    _message_type: vk::DebugUtilsMessageTypeFlagsEXT,
-    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
-    _p_user_data: *mut c_void,
-) -> vk::Bool32 {
-    eprintln!(
-        "{}",
-        CStr::from_ptr((*p_callback_data).p_message).to_string_lossy()
-    );
-    vk::FALSE
+/// Strips the patch version, so versions reported by the loader and devices can be compared directly
+pub fn api_version_without_patch(api_version: u32) -> u32 {
+    vk::make_api_version(
+        vk::api_version_variant(api_version),
+        vk::api_version_major(api_version),
+        vk::api_version_minor(api_version),
+        0,
+    )
+}
+
+/// The highest Vulkan version supported by the loader, capped at `target`. Vulkan 1.0 loaders report nothing and yield 1.0
+pub fn negotiate_instance_version(entry: &Entry, target: u32) -> u32 {
+    let available = entry
+        .try_enumerate_instance_version()
+        .ok()
+        .flatten()
+        .unwrap_or(vk::API_VERSION_1_0);
+    api_version_without_patch(available).min(api_version_without_patch(target))
+}
+
+/// Version of the engine as given to Vulkan in [`vk::ApplicationInfo`]
+pub fn engine_version() -> u32 {
+    vk::make_api_version(
+        0,
+        env!("CARGO_PKG_VERSION_MAJOR").parse().unwrap(),
+        env!("CARGO_PKG_VERSION_MINOR").parse().unwrap(),
+        env!("CARGO_PKG_VERSION_PATCH").parse().unwrap(),
+    )
 }
 
 pub fn check_validation_layer_support(entry: &Entry) -> bool {
@@ -85,43 +150,78 @@ pub fn check_validation_layer_support(entry: &Entry) -> bool {
     true
 }
 
+/// Lists every physical device the instance can see, along with how suitable it is for rendering to `surface`
+/// with the given requirements. `instance_api_version` is the API version the instance was created with
+pub fn enumerate_physical_devices(
+    instance: &Instance,
+    surface_loader: &Surface,
+    surface: &vk::SurfaceKHR,
+    requirements: &DeviceRequirements,
+    instance_api_version: u32,
+) -> Vec<PhysicalDeviceInfo> {
+    unsafe { instance.enumerate_physical_devices() }
+        .unwrap()
+        .iter()
+        .enumerate()
+        .map(|(i, device)| {
+            phys_device::physical_device_info(
+                instance,
+                surface_loader,
+                surface,
+                device,
+                i,
+                requirements,
+                instance_api_version,
+            )
+        })
+        .collect()
+}
+
+/// Picks a physical device according to `selector`, or according to the `VK_ENGINE_DEVICE` environment variable if it is set.
+/// Panics if no device matches the selection.
 pub fn find_physical_device(
     instance: &Instance,
     surface_loader: &Surface,
     surface: &vk::SurfaceKHR,
-) -> (vk::PhysicalDevice, phys_device::QueueFamilyIndices) {
-    let devices = unsafe { instance.enumerate_physical_devices() }.unwrap();
+    requirements: &DeviceRequirements,
+    instance_api_version: u32,
+    selector: &DeviceSelector,
+) -> (PhysicalDeviceInfo, phys_device::QueueFamilyIndices) {
+    let devices = enumerate_physical_devices(
+        instance,
+        surface_loader,
+        surface,
+        requirements,
+        instance_api_version,
+    );
     if devices.is_empty() {
         panic!("No devices with Vulkan support!")
     }
 
-    let mut suitability = 0;
-    let physical_device = devices
-        .into_iter()
-        .max_by_key(|device| {
-            suitability =
-                phys_device::device_suitability(instance, surface_loader, surface, device);
-            suitability
-        })
-        .expect("No suitable GPU could be found!");
-    if suitability == 0 {
-        panic!("No suitable GPU could be found!")
-    }
+    let selector = DeviceSelector::from_env().unwrap_or_else(|| selector.clone());
+    let device_info = match selector.select(&devices) {
+        Ok(info) => info.clone(),
+        Err(reason) => {
+            let device_list: Vec<String> = devices.iter().map(|info| info.to_string()).collect();
+            panic!("{}\nAvailable devices:\n{}", reason, device_list.join("\n"))
+        }
+    };
 
     let queue_family_indices =
-        phys_device::find_queue_families(instance, surface_loader, surface, &physical_device)
+        phys_device::find_queue_families(instance, surface_loader, surface, &device_info.handle)
             .unwrap(); //Checked in device_suitabiliy, so will always succeed
-    (physical_device, queue_family_indices)
+    (device_info, queue_family_indices)
 }
 
+/// Creates the logical device with the extensions and features of `enabled`, usually negotiated by [`DeviceRequirements::negotiate`].
+/// Wrap it in a [`LogicalDevice`] to share it with the resources created from it
 pub fn create_logical_device(
     instance: &Instance,
     physical_device: &vk::PhysicalDevice,
     queue_family_indices: phys_device::QueueFamilyIndices,
-) -> Rc<Device> {
-    let unique_queue_family_indices: Vec<u32> = HashSet::from(queue_family_indices.array())
-        .drain()
-        .collect();
+    enabled: &EnabledFeatures,
+) -> Device {
+    let unique_queue_family_indices = queue_family_indices.unique();
     let device_queue_infos: &[vk::DeviceQueueCreateInfo] = &unique_queue_family_indices
         .into_iter()
         .map(|index| {
@@ -132,16 +232,28 @@ pub fn create_logical_device(
         .collect::<Vec<vk::DeviceQueueCreateInfo>>()
         .into_boxed_slice();
 
-    let device_features = vk::PhysicalDeviceFeatures::builder().sampler_anisotropy(true);
-    let device_create_info = vk::DeviceCreateInfo::builder()
+    let extension_names: Vec<*const c_char> =
+        enabled.extensions.iter().map(|ext| ext.as_ptr()).collect();
+    let mut device_features = enabled.features;
+    let mut descriptor_indexing = (enabled.api_version < vk::API_VERSION_1_2
+        && device_features.uses_descriptor_indexing())
+    .then(|| device_features.take_descriptor_indexing_ext());
+    let mut features_chain = unsafe { device_features.as_chain() };
+    let mut device_create_info = vk::DeviceCreateInfo::builder()
         .queue_create_infos(device_queue_infos)
-        .enabled_features(&device_features)
-        .enabled_extension_names(&DEVICE_EXTS);
+        .enabled_extension_names(&extension_names);
+    // Features beyond Vulkan 1.0 must be given through the chain, in which case enabled_features has to be null
+    if device_features.uses_chain() {
+        device_create_info = device_create_info.push_next(&mut features_chain);
+    } else {
+        device_create_info = device_create_info.enabled_features(&device_features.core);
+    }
+    if let Some(descriptor_indexing) = &mut descriptor_indexing {
+        device_create_info = device_create_info.push_next(descriptor_indexing);
+    }
 
-    Rc::new(
-        unsafe { instance.create_device(*physical_device, &device_create_info, None) }
-            .expect("Failed to create logical device!"),
-    )
+    unsafe { instance.create_device(*physical_device, &device_create_info, None) }
+        .expect("Failed to create logical device!")
 }
 
 pub fn get_queue_handles(
@@ -155,6 +267,18 @@ pub fn get_queue_handles(
     (graphics_queue, present_queue)
 }
 
+/// Handle to the dedicated transfer queue, if the device has a transfer family
+pub fn get_transfer_queue(
+    logical_device: &Device,
+    queue_family_indices: phys_device::QueueFamilyIndices,
+) -> Option<vk::Queue> {
+    queue_family_indices
+        .transfer_queue
+        .map(|family| unsafe { logical_device.get_device_queue(family, 0) })
+}
+
+/// Creates a swapchain for `surface`. Pass the swapchain being replaced as `old_swapchain`, or a null handle,
+/// so that the presentation engine may reuse its resources. The old swapchain still has to be destroyed
 pub fn create_swapchain(
     window: &Window,
     surface_loader: &Surface,
@@ -162,6 +286,7 @@ pub fn create_swapchain(
     physical_device: &vk::PhysicalDevice,
     swapchain_loader: &Swapchain,
     queue_family_indices: phys_device::QueueFamilyIndices,
+    old_swapchain: vk::SwapchainKHR,
 ) -> (vk::SwapchainKHR, vk::Format, vk::Extent2D, Vec<vk::Image>) {
     let (surface_capabilities, formats, present_modes) =
         phys_device::query_swap_chain_support(surface_loader, surface, physical_device);
@@ -191,6 +316,7 @@ pub fn create_swapchain(
         .pre_transform(surface_capabilities.current_transform)
         .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
         .clipped(true)
+        .old_swapchain(old_swapchain)
         //Might change depending on use case v v v
         .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT);
 
@@ -251,32 +377,55 @@ pub fn create_swapchain_image_views(
     image_views
 }
 
+/// Creates a descriptor set layout with `bindings`, to be destroyed by the caller
+pub fn create_descriptor_set_layout(
+    logical_device: &Device,
+    bindings: &[vk::DescriptorSetLayoutBinding],
+) -> vk::DescriptorSetLayout {
+    let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
+    unsafe { logical_device.create_descriptor_set_layout(&layout_info, None) }
+        .expect("Could not create descriptor set layout!")
+}
+
+/// Creates the default graphics pipeline along with its render pass.
+/// `descriptor_set_layout` is set 0, followed by `additional_set_layouts`, see [`create_descriptor_set_layout`].
+/// With `dynamic_rendering` (requires Vulkan 1.3) no render pass is created, and the returned render pass is null
+#[allow(clippy::too_many_arguments)]
 pub fn create_graphics_pipeline(
     logical_device: &Device,
     swapchain_extent: vk::Extent2D,
     image_format: vk::Format,
-    shaders: &Vec<shaders::Shader>,
+    shaders: &[shaders::Shader],
     vertex_input_descriptors: &VertexInputDescriptors,
-    descriptor_set_bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    additional_set_layouts: &[vk::DescriptorSetLayout],
     push_constants: [f32; 1],
-) -> (
-    vk::Pipeline,
-    vk::PipelineLayout,
-    vk::DescriptorSetLayout,
-    vk::RenderPass,
-) {
-    let render_pass = pipeline::default_render_pass(logical_device, image_format);
+    dynamic_rendering: bool,
+) -> (vk::Pipeline, vk::PipelineLayout, vk::RenderPass) {
+    let (render_pass, rendering_formats) = if dynamic_rendering {
+        (
+            vk::RenderPass::null(),
+            Some((image_format, vk::Format::D32_SFLOAT)),
+        )
+    } else {
+        (
+            pipeline::default_render_pass(logical_device, image_format),
+            None,
+        )
+    };
 
     let pipeline = pipeline::default_pipeline(
         logical_device,
         render_pass,
+        rendering_formats,
         swapchain_extent,
         shaders,
         vertex_input_descriptors,
-        descriptor_set_bindings,
+        descriptor_set_layout,
+        additional_set_layouts,
         push_constants,
     );
-    (pipeline.0, pipeline.1, pipeline.2, render_pass)
+    (pipeline.0, pipeline.1, render_pass)
 }
 
 pub fn create_framebuffers(
@@ -342,6 +491,8 @@ pub fn create_sync_primitives(logical_device: &Device) -> SyncPrims {
     }
 }
 impl SyncPrims {
+    /// # Safety
+    /// None of the primitives may be in use by the device.
     pub unsafe fn destroy(&self, logical_device: &Device) {
         for sem in &self.image_available {
             logical_device.destroy_semaphore(*sem, None);
@@ -371,7 +522,7 @@ pub fn allocate_command_buffers(
 pub fn create_staging_buffer(
     instance: &Instance,
     physical_device: &vk::PhysicalDevice,
-    logical_device: &Rc<Device>,
+    logical_device: &Arc<LogicalDevice>,
     memory_size: vk::DeviceSize,
 ) -> ManagedBuffer {
     //Host visible buffer; data is transferred to a device local buffer at transfer stage
@@ -388,26 +539,22 @@ pub fn create_staging_buffer(
         vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
     );
 
-    ManagedBuffer {
-        logical_device: Rc::clone(logical_device),
-        // memory_size,
-        buffer: staging_buffer,
-        buffer_memory: Some(staging_buffer_memory),
-        memory_ptr: None,
-    }
+    ManagedBuffer::new(logical_device, staging_buffer, Some(staging_buffer_memory))
 }
 
 pub fn create_vertex_buffer(
     instance: &Instance,
     physical_device: &vk::PhysicalDevice,
-    logical_device: &Rc<Device>,
+    logical_device: &Arc<LogicalDevice>,
     memory_size: u64,
 ) -> ManagedBuffer {
     //Device local buffer, or *true* vertex buffer, needs a staging buffer to transfer data to it
     let vertex_buffer = buffer::create_buffer(
         logical_device,
         memory_size,
-        vk::BufferUsageFlags::VERTEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+        vk::BufferUsageFlags::VERTEX_BUFFER
+            | vk::BufferUsageFlags::TRANSFER_DST
+            | vk::BufferUsageFlags::TRANSFER_SRC,
     );
     let vertex_buffer_memory = buffer::allocate_and_bind_buffer(
         instance,
@@ -417,27 +564,33 @@ pub fn create_vertex_buffer(
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
     );
 
-    ManagedBuffer {
-        logical_device: Rc::clone(logical_device),
-        // memory_size,
-        buffer: vertex_buffer,
-        buffer_memory: Some(vertex_buffer_memory),
-        memory_ptr: None,
-    }
+    ManagedBuffer::new(logical_device, vertex_buffer, Some(vertex_buffer_memory))
 }
 
 pub fn create_index_buffer<IndexType: ValidIndexBufferType>(
     instance: &Instance,
     physical_device: &vk::PhysicalDevice,
-    logical_device: &Rc<Device>,
+    logical_device: &Arc<LogicalDevice>,
     count: usize,
 ) -> ManagedBuffer {
     //Easy to get the memory size wrong, might fail invisibly
     let memory_size = (std::mem::size_of::<IndexType>() * count) as u64;
+    create_index_buffer_with_size(instance, physical_device, logical_device, memory_size)
+}
+
+/// Same as [`create_index_buffer`], sized in bytes, for buffers holding indices of any [`ValidIndexBufferType`]
+pub fn create_index_buffer_with_size(
+    instance: &Instance,
+    physical_device: &vk::PhysicalDevice,
+    logical_device: &Arc<LogicalDevice>,
+    memory_size: vk::DeviceSize,
+) -> ManagedBuffer {
     let index_buffer = buffer::create_buffer(
         logical_device,
         memory_size,
-        vk::BufferUsageFlags::INDEX_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+        vk::BufferUsageFlags::INDEX_BUFFER
+            | vk::BufferUsageFlags::TRANSFER_DST
+            | vk::BufferUsageFlags::TRANSFER_SRC,
     );
     let index_buffer_memory = buffer::allocate_and_bind_buffer(
         instance,
@@ -447,19 +600,13 @@ pub fn create_index_buffer<IndexType: ValidIndexBufferType>(
         vk::MemoryPropertyFlags::DEVICE_LOCAL,
     );
 
-    ManagedBuffer {
-        logical_device: Rc::clone(logical_device),
-        // memory_size,
-        buffer: index_buffer,
-        buffer_memory: Some(index_buffer_memory),
-        memory_ptr: None,
-    }
+    ManagedBuffer::new(logical_device, index_buffer, Some(index_buffer_memory))
 }
 
 pub fn create_uniform_buffers(
     instance: &Instance,
     physical_device: &vk::PhysicalDevice,
-    logical_device: &Rc<Device>,
+    logical_device: &Arc<LogicalDevice>,
     memory_size: u64,
     count: usize,
 ) -> Vec<ManagedBuffer> {
@@ -479,13 +626,8 @@ pub fn create_uniform_buffers(
             vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
         );
 
-        let mut managed_buffer = ManagedBuffer {
-            logical_device: Rc::clone(logical_device),
-            // memory_size,
-            buffer: uniform_buffer,
-            buffer_memory: Some(uniform_buffer_memory),
-            memory_ptr: None,
-        };
+        let mut managed_buffer =
+            ManagedBuffer::new(logical_device, uniform_buffer, Some(uniform_buffer_memory));
         managed_buffer.map_buffer_memory(); // Map immediately, as the uniform buffers are persistently mapped
 
         uniform_buffers.push(managed_buffer);
@@ -493,10 +635,73 @@ pub fn create_uniform_buffers(
     uniform_buffers
 }
 
+/** Creates a buffer for `STORAGE_BUFFER` descriptors, which shaders can both read and write.
+With `DEVICE_LOCAL` memory, fill it through [`UploadManager`] or from shaders.
+With `HOST_VISIBLE` memory it is mapped immediately, and written with [`ManagedBuffer::write`].
+Writing from vertex and fragment shaders requires the `vertex_pipeline_stores_and_atomics` and `fragment_stores_and_atomics` features,
+which [`DeviceRequirements::default`] requests, check [`EnabledFeatures`] for whether they were enabled. */
+pub fn create_storage_buffer(
+    instance: &Instance,
+    physical_device: &vk::PhysicalDevice,
+    logical_device: &Arc<LogicalDevice>,
+    memory_size: vk::DeviceSize,
+    memory_properties: vk::MemoryPropertyFlags,
+) -> ManagedBuffer {
+    let storage_buffer = buffer::create_buffer(
+        logical_device,
+        memory_size,
+        vk::BufferUsageFlags::STORAGE_BUFFER
+            | vk::BufferUsageFlags::TRANSFER_DST
+            | vk::BufferUsageFlags::TRANSFER_SRC,
+    );
+    let storage_buffer_memory = buffer::allocate_and_bind_buffer(
+        instance,
+        physical_device,
+        logical_device,
+        storage_buffer,
+        memory_properties,
+    );
+
+    let mut managed_buffer =
+        ManagedBuffer::new(logical_device, storage_buffer, Some(storage_buffer_memory));
+    if memory_properties.contains(vk::MemoryPropertyFlags::HOST_VISIBLE) {
+        managed_buffer.map_buffer_memory();
+    }
+    managed_buffer
+}
+
+/// Creates a buffer whose address can be fetched with [`ManagedBuffer::device_address`].
+/// Requires the Vulkan 1.2 `buffer_device_address` feature
+pub fn create_device_address_buffer(
+    instance: &Instance,
+    physical_device: &vk::PhysicalDevice,
+    logical_device: &Arc<LogicalDevice>,
+    memory_size: vk::DeviceSize,
+    usage: vk::BufferUsageFlags,
+    memory_properties: vk::MemoryPropertyFlags,
+) -> ManagedBuffer {
+    let buffer = buffer::create_buffer(
+        logical_device,
+        memory_size,
+        usage | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+    );
+    let buffer_memory = buffer::allocate_and_bind_buffer_with_flags(
+        instance,
+        physical_device,
+        logical_device,
+        buffer,
+        memory_properties,
+        vk::MemoryAllocateFlags::DEVICE_ADDRESS,
+    );
+
+    ManagedBuffer::new(logical_device, buffer, Some(buffer_memory))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn create_image(
     instance: &Instance,
     physical_device: &vk::PhysicalDevice,
-    logical_device: &Rc<Device>,
+    logical_device: &Arc<LogicalDevice>,
     format: vk::Format,
     tiling: vk::ImageTiling,
     usage: vk::ImageUsageFlags,
@@ -513,26 +718,32 @@ pub fn create_image(
     ));
     let texture_image_view =
         textures::create_image_view(logical_device, texture_image, format, aspect_flags);
-    let managed_image = ManagedImage {
-        logical_device: Rc::clone(logical_device),
-        image: texture_image,
-        image_view: texture_image_view,
+    ManagedImage::new(
+        logical_device,
+        texture_image,
+        texture_image_view,
         image_memory,
-        memory_ptr: None,
-    };
-    managed_image
+    )
 }
 
-
-pub fn load_image_immediate(instance: &Instance, physical_device: &vk::PhysicalDevice, logical_device: &Rc<Device>, command_pool: vk::CommandPool, queue: vk::Queue, filepath: &str) -> ManagedImage {
-    // Load image texture onto GPU
-    let (img_samples, (w, h)) = crate::load_image_as_rgba_samples(filepath);
-
+/** Creates a sampled texture from tightly packed pixels of the given 4-byte `format`, such as those of
+[`crate::load_image_as_rgba_samples`], blocking until the upload has finished. */
+#[allow(clippy::too_many_arguments)]
+pub fn load_image_immediate(
+    instance: &Instance,
+    physical_device: &vk::PhysicalDevice,
+    logical_device: &Arc<LogicalDevice>,
+    command_pool: vk::CommandPool,
+    queue: vk::Queue,
+    img_samples: &[u8],
+    (w, h): (u32, u32),
+    format: vk::Format,
+) -> ManagedImage {
     let texture_image = create_image(
-        &instance,
-        &physical_device,
-        &logical_device,
-        vk::Format::R8G8B8A8_SRGB,
+        instance,
+        physical_device,
+        logical_device,
+        format,
         vk::ImageTiling::OPTIMAL,
         vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
         vk::ImageAspectFlags::COLOR,
@@ -540,18 +751,13 @@ pub fn load_image_immediate(instance: &Instance, physical_device: &vk::PhysicalD
     );
 
     let mut tex_staging_buffer = create_staging_buffer(
-        &instance,
-        &physical_device,
-        &logical_device,
+        instance,
+        physical_device,
+        logical_device,
         vk::DeviceSize::from((w * h * 4) as u64),
     );
     tex_staging_buffer.map_buffer_memory();
-    unsafe {
-        write_vec_to_buffer(
-            tex_staging_buffer.memory_ptr.unwrap(),
-            &img_samples,
-        )
-    };
+    tex_staging_buffer.write(0, img_samples);
 
     fn transition_image_layout(
         logical_device: &Device,
@@ -601,22 +807,17 @@ pub fn load_image_immediate(instance: &Instance, physical_device: &vk::PhysicalD
         }
 
         unsafe {
-            immediate_commands(
-                &logical_device,
-                command_pool,
-                queue,
-                |cmd_buffer| {
-                    logical_device.cmd_pipeline_barrier(
-                        cmd_buffer,
-                        src_stage,
-                        dst_stage,
-                        vk::DependencyFlags::empty(),
-                        &[],
-                        &[],
-                        &[*barrier],
-                    );
-                },
-            );
+            immediate_commands(logical_device, command_pool, queue, |cmd_buffer| {
+                logical_device.cmd_pipeline_barrier(
+                    cmd_buffer,
+                    src_stage,
+                    dst_stage,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[],
+                    &[*barrier],
+                );
+            });
         }
     }
 
@@ -647,35 +848,30 @@ pub fn load_image_immediate(instance: &Instance, physical_device: &vk::PhysicalD
                 depth: 1,
             });
         unsafe {
-            immediate_commands(
-                logical_device,
-                command_pool,
-                queue,
-                |cmd_buffer| {
-                    logical_device.cmd_copy_buffer_to_image(
-                        cmd_buffer,
-                        buffer,
-                        image,
-                        vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-                        &[*region],
-                    );
-                },
-            );
+            immediate_commands(logical_device, command_pool, queue, |cmd_buffer| {
+                logical_device.cmd_copy_buffer_to_image(
+                    cmd_buffer,
+                    buffer,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[*region],
+                );
+            });
         }
     }
 
     transition_image_layout(
-        &logical_device,
+        logical_device,
         command_pool,
         queue,
         texture_image.image,
-        vk::Format::R8G8B8A8_SRGB,
+        format,
         vk::ImageLayout::UNDEFINED,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
     );
 
     copy_buffer_to_image(
-        &logical_device,
+        logical_device,
         command_pool,
         queue,
         tex_staging_buffer.buffer,
@@ -685,11 +881,11 @@ pub fn load_image_immediate(instance: &Instance, physical_device: &vk::PhysicalD
     );
 
     transition_image_layout(
-        &logical_device,
+        logical_device,
         command_pool,
         queue,
         texture_image.image,
-        vk::Format::R8G8B8A8_SRGB,
+        format,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
         vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
     );
@@ -697,22 +893,48 @@ pub fn load_image_immediate(instance: &Instance, physical_device: &vk::PhysicalD
     texture_image
 }
 
+/// Loads an image file into a sampled RGBA texture, recording the upload into `uploads`.
+/// The texture may be used once the batch has completed, see [`UploadManager::is_complete`]
+pub fn load_image(
+    instance: &Instance,
+    physical_device: &vk::PhysicalDevice,
+    logical_device: &Arc<LogicalDevice>,
+    uploads: &mut UploadManager,
+    filepath: &str,
+) -> ManagedImage {
+    let (img_samples, (w, h)) = crate::load_image_as_rgba_samples(filepath);
 
+    let texture_image = create_image(
+        instance,
+        physical_device,
+        logical_device,
+        vk::Format::R8G8B8A8_SRGB,
+        vk::ImageTiling::OPTIMAL,
+        vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED,
+        vk::ImageAspectFlags::COLOR,
+        (w, h),
+    );
+    uploads.upload_image(&img_samples, texture_image.image, (w, h));
 
-
+    texture_image
+}
 
 /// # Safety
 /// The memory pointed to by `buffer_pointer` must have at least as much space allocated as is required by `data`, and `buffer_pointer` must be valid.
-pub unsafe fn write_vec_to_buffer<T: Sized>(buffer_pointer: *mut c_void, data: &Vec<T>) {
+pub unsafe fn write_vec_to_buffer<T: Sized>(buffer_pointer: *mut c_void, data: &[T]) {
     std::ptr::copy_nonoverlapping(data.as_ptr(), buffer_pointer as *mut T, data.len());
 }
 
+/// # Safety
+/// The memory pointed to by `buffer_pointer` must have room for a `T`, and both pointers must be valid.
 pub unsafe fn write_struct_to_buffer<T: Sized>(buffer_pointer: *mut c_void, data: *const T) {
     std::ptr::copy_nonoverlapping(data, buffer_pointer as *mut T, 1);
 }
 
 /// Immediately submits the given commands to the given queue. Blocks until completion.
-pub unsafe fn immediate_commands<F: FnOnce(vk::CommandBuffer) -> ()>(
+/// # Safety
+/// `command_pool` must belong to the family of `queue`, and the commands recorded by `commands` must be valid.
+pub unsafe fn immediate_commands<F: FnOnce(vk::CommandBuffer)>(
     logical_device: &Device,
     command_pool: vk::CommandPool,
     queue: vk::Queue,
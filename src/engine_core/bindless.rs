@@ -0,0 +1,253 @@
+use super::{DeviceFeatures, EnabledFeatures, LogicalDevice};
+use ash::{vk, Device, Instance};
+use std::sync::{Arc, Mutex};
+
+/// Binding of the texture array in the set of [`BindlessTextures::layout`]
+pub const BINDLESS_TEXTURE_BINDING: u32 = 0;
+/// Textures [`BindlessTextures::new`] makes room for by default, capped by the device limits
+pub const DEFAULT_BINDLESS_CAPACITY: u32 = 4096;
+/// Offset of the texture indices in the push constants of the default pipeline, see [`cmd_push_texture_indices`]
+pub const TEXTURE_INDEX_PUSH_CONSTANT_OFFSET: u32 = 16;
+/// Number of `u32` texture indices that fit in the push constants of the default pipeline
+pub const MAX_PUSHED_TEXTURE_INDICES: usize = 4;
+
+/** One global, partially bound array of sampled textures that shaders index into, using `VK_EXT_descriptor_indexing`
+or Vulkan 1.2. Every texture gets a stable index when added, so materials only need to pass indices,
+usually through push constants, instead of binding a descriptor set per texture.
+```glsl
+#extension GL_EXT_nonuniform_qualifier : require
+layout(set = 1, binding = 0) uniform sampler2D textures[]; // Set index from BaseApp::bindless_set_index
+layout(push_constant) uniform Material { layout(offset = 16) uint texture_index; };
+// ...
+color = texture(textures[nonuniformEXT(texture_index)], uv);
+```
+The array is written with update-after-bind, so textures may be added while the set is bound or in use by frames in flight.
+Holds a reference to the device, so it may be dropped after the [`BaseApp`](crate::BaseApp) it was created from. */
+pub struct BindlessTextures {
+    logical_device: Arc<LogicalDevice>,
+    pool: vk::DescriptorPool,
+    layout: vk::DescriptorSetLayout,
+    set: vk::DescriptorSet,
+    capacity: u32,
+    next_index: u32,
+    // Shared with the closures of BindlessTextures::remove_later
+    free_indices: Arc<Mutex<Vec<u32>>>,
+}
+
+impl BindlessTextures {
+    /// Features to require or request in [`DeviceRequirements`](super::DeviceRequirements) to use bindless textures
+    pub fn required_features() -> DeviceFeatures {
+        let mut features = DeviceFeatures::default();
+        features
+            .vulkan12
+            .shader_sampled_image_array_non_uniform_indexing = vk::TRUE;
+        features
+            .vulkan12
+            .descriptor_binding_sampled_image_update_after_bind = vk::TRUE;
+        features
+            .vulkan12
+            .descriptor_binding_update_unused_while_pending = vk::TRUE;
+        features.vulkan12.descriptor_binding_partially_bound = vk::TRUE;
+        features.vulkan12.runtime_descriptor_array = vk::TRUE;
+        features
+    }
+
+    /// Whether every feature of [`BindlessTextures::required_features`] was enabled
+    pub fn is_supported(enabled_features: &EnabledFeatures) -> bool {
+        Self::required_features().is_subset_of(&enabled_features.features)
+    }
+
+    /// Creates the texture array with room for `capacity` textures, or fewer if the device limits are lower.
+    /// Requires the features of [`BindlessTextures::required_features`]
+    pub fn new(
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        capacity: u32,
+    ) -> BindlessTextures {
+        let capacity = capacity.min(max_update_after_bind_textures(instance, physical_device));
+
+        let bindings = [*vk::DescriptorSetLayoutBinding::builder()
+            .binding(BINDLESS_TEXTURE_BINDING)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(capacity)
+            .stage_flags(vk::ShaderStageFlags::ALL)];
+        let binding_flags = [vk::DescriptorBindingFlags::PARTIALLY_BOUND
+            | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+            | vk::DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING];
+        let mut binding_flags_info =
+            vk::DescriptorSetLayoutBindingFlagsCreateInfo::builder().binding_flags(&binding_flags);
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::builder()
+            .flags(vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+            .bindings(&bindings)
+            .push_next(&mut binding_flags_info);
+        let layout = unsafe { logical_device.create_descriptor_set_layout(&layout_info, None) }
+            .expect("Could not create bindless descriptor set layout!");
+
+        let pool_sizes = [*vk::DescriptorPoolSize::builder()
+            .ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(capacity)];
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .flags(vk::DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
+            .pool_sizes(&pool_sizes)
+            .max_sets(1);
+        let pool = unsafe { logical_device.create_descriptor_pool(&pool_info, None) }
+            .expect("Could not create bindless descriptor pool!");
+
+        let layouts = [layout];
+        let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(pool)
+            .set_layouts(&layouts);
+        let set = unsafe { logical_device.allocate_descriptor_sets(&alloc_info) }
+            .expect("Could not allocate bindless descriptor set!")[0];
+
+        BindlessTextures {
+            logical_device: Arc::clone(logical_device),
+            pool,
+            layout,
+            set,
+            capacity,
+            next_index: 0,
+            free_indices: Arc::default(),
+        }
+    }
+
+    /// Layout to include in pipeline layouts that read the textures
+    pub fn layout(&self) -> vk::DescriptorSetLayout {
+        self.layout
+    }
+
+    /// The one descriptor set holding every texture
+    pub fn set(&self) -> vk::DescriptorSet {
+        self.set
+    }
+
+    /// Most textures the array can hold
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Number of textures currently in the array
+    pub fn len(&self) -> u32 {
+        self.next_index - self.free_indices.lock().unwrap().len() as u32
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Adds a texture, sampled with `sampler` in `SHADER_READ_ONLY_OPTIMAL` layout. Returns the index shaders use to read it.
+    /// Panics if the array is full
+    pub fn add(&mut self, image_view: vk::ImageView, sampler: vk::Sampler) -> u32 {
+        let free_index = self.free_indices.lock().unwrap().pop();
+        let index = match free_index {
+            Some(index) => index,
+            None => {
+                assert!(
+                    self.next_index < self.capacity,
+                    "Bindless texture array is full!"
+                );
+                self.next_index += 1;
+                self.next_index - 1
+            }
+        };
+        self.replace(index, image_view, sampler);
+        index
+    }
+
+    /// Points `index` at another texture. Only call while no frame in flight reads `index`
+    pub fn replace(&self, index: u32, image_view: vk::ImageView, sampler: vk::Sampler) {
+        let image_info = [*vk::DescriptorImageInfo::builder()
+            .image_view(image_view)
+            .sampler(sampler)
+            .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)];
+        let write = *vk::WriteDescriptorSet::builder()
+            .dst_set(self.set)
+            .dst_binding(BINDLESS_TEXTURE_BINDING)
+            .dst_array_element(index)
+            .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .image_info(&image_info);
+        unsafe { self.logical_device.update_descriptor_sets(&[write], &[]) };
+    }
+
+    /** Frees `index` for reuse by a later [`BindlessTextures::add`].
+    Only remove a texture once no frame in flight reads it, as the index may be pointed at another texture right away. */
+    pub fn remove(&mut self, index: u32) {
+        self.remove_later(index)();
+    }
+
+    /** Returns a closure that frees `index` like [`BindlessTextures::remove`] when run,
+    for a [`DeletionQueue`](super::DeletionQueue) to run once no frame in flight reads it. */
+    pub fn remove_later(&self, index: u32) -> impl FnOnce() + Send + 'static {
+        let free_indices = Arc::clone(&self.free_indices);
+        let next_index = self.next_index;
+        move || {
+            let mut free_indices = free_indices.lock().unwrap();
+            assert!(
+                index < next_index && !free_indices.contains(&index),
+                "Bindless texture index {} is not in use!",
+                index
+            );
+            free_indices.push(index);
+        }
+    }
+}
+
+impl Drop for BindlessTextures {
+    fn drop(&mut self) {
+        unsafe {
+            self.logical_device.destroy_descriptor_pool(self.pool, None);
+            self.logical_device
+                .destroy_descriptor_set_layout(self.layout, None);
+        }
+    }
+}
+
+/** Pushes up to [`MAX_PUSHED_TEXTURE_INDICES`] texture indices at [`TEXTURE_INDEX_PUSH_CONSTANT_OFFSET`],
+visible to the vertex and fragment stages of the default pipeline.
+# Safety
+`command_buffer` must be recording, and `pipeline_layout` must have a push constant range covering the indices. */
+pub unsafe fn cmd_push_texture_indices(
+    logical_device: &Device,
+    command_buffer: vk::CommandBuffer,
+    pipeline_layout: vk::PipelineLayout,
+    indices: &[u32],
+) {
+    assert!(
+        indices.len() <= MAX_PUSHED_TEXTURE_INDICES,
+        "At most {} texture indices can be pushed!",
+        MAX_PUSHED_TEXTURE_INDICES
+    );
+    let bytes: Vec<u8> = indices
+        .iter()
+        .flat_map(|index| index.to_ne_bytes())
+        .collect();
+    logical_device.cmd_push_constants(
+        command_buffer,
+        pipeline_layout,
+        vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT,
+        TEXTURE_INDEX_PUSH_CONSTANT_OFFSET,
+        &bytes,
+    );
+}
+
+// The lowest of the limits on update-after-bind sampled images and samplers, per stage and per set
+fn max_update_after_bind_textures(
+    instance: &Instance,
+    physical_device: &vk::PhysicalDevice,
+) -> u32 {
+    let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingProperties::default();
+    let mut properties2 =
+        vk::PhysicalDeviceProperties2::builder().push_next(&mut descriptor_indexing);
+    unsafe { instance.get_physical_device_properties2(*physical_device, &mut properties2) };
+    [
+        descriptor_indexing.max_per_stage_descriptor_update_after_bind_sampled_images,
+        descriptor_indexing.max_per_stage_descriptor_update_after_bind_samplers,
+        descriptor_indexing.max_descriptor_set_update_after_bind_sampled_images,
+        descriptor_indexing.max_descriptor_set_update_after_bind_samplers,
+        descriptor_indexing.max_per_stage_update_after_bind_resources,
+    ]
+    .into_iter()
+    .min()
+    .unwrap()
+}
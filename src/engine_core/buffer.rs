@@ -1,17 +1,37 @@
+use super::LogicalDevice;
 use ash::{vk, Device, Instance};
 use std::ffi::c_void;
 use std::ops::Deref;
-use std::rc::Rc;
+use std::sync::Arc;
 
 pub struct ManagedBuffer {
-    pub logical_device: Rc<Device>,
+    pub logical_device: Arc<LogicalDevice>,
     // pub memory_size: vk::DeviceSize,
     pub buffer_memory: Option<vk::DeviceMemory>,
     pub buffer: vk::Buffer,
-    pub memory_ptr: Option<*mut c_void>,
+    // Pointer to the mapped memory and the number of bytes behind it, see ManagedBuffer::write
+    memory_ptr: Option<(*mut c_void, usize)>,
 }
+// The mapped pointer only refers to memory owned by the buffer, and Vulkan only requires external
+// synchronization for mapping, unmapping and destroying, which all take `&mut self` or ownership
+unsafe impl Send for ManagedBuffer {}
+unsafe impl Sync for ManagedBuffer {}
 impl ManagedBuffer {
-    /// Maps whole of the allocated buffer memory, returns pointer to the data.
+    /// Takes ownership of `buffer` and of `buffer_memory` bound to it, if any. The memory starts out unmapped
+    pub fn new(
+        logical_device: &Arc<LogicalDevice>,
+        buffer: vk::Buffer,
+        buffer_memory: Option<vk::DeviceMemory>,
+    ) -> ManagedBuffer {
+        ManagedBuffer {
+            logical_device: Arc::clone(logical_device),
+            buffer_memory,
+            buffer,
+            memory_ptr: None,
+        }
+    }
+
+    /// Maps whole of the allocated buffer memory, to be written with [`ManagedBuffer::write`].
     /// Invalid if memory is not visible to the host device (unsure what happens if not).
     /// Panics if there's no memory to map
     pub fn map_buffer_memory(&mut self) {
@@ -19,7 +39,13 @@ impl ManagedBuffer {
             if self.memory_ptr.is_some() {
                 panic!("Attempt to re-map buffer memory!")
             }
-            self.memory_ptr = Some(map_buffer_memory(&self.logical_device, memory))
+            // Memory is allocated with the size of the buffer's requirements, see allocate_and_bind_buffer
+            let size = unsafe {
+                self.logical_device
+                    .get_buffer_memory_requirements(self.buffer)
+            }
+            .size as usize;
+            self.memory_ptr = Some((map_buffer_memory(&self.logical_device, memory), size))
         } else {
             panic!("Attempt to map unallocated/unbound buffer memory!");
         }
@@ -28,7 +54,7 @@ impl ManagedBuffer {
     /// Unmaps buffer memory (unsure what happens if it isn't mapped. no-op?)
     /// Panics if there's no memory to unmap (does it matter?)
     pub fn unmap_buffer_memory(&mut self) {
-        if self.memory_ptr.is_some() {
+        if self.memory_ptr.take().is_some() {
             unsafe {
                 self.logical_device
                     .unmap_memory(self.buffer_memory.unwrap())
@@ -37,6 +63,46 @@ impl ManagedBuffer {
             panic!("Attempt to unmap unmapped buffer memory!");
         }
     }
+
+    pub fn is_mapped(&self) -> bool {
+        self.memory_ptr.is_some()
+    }
+
+    /// Copies `data` into the mapped memory, starting `offset` bytes in.
+    /// Panics if the memory is not mapped or `data` does not fit
+    pub fn write<T>(&mut self, offset: usize, data: &[T]) {
+        let ptr = self.mapped_ptr(offset, std::mem::size_of_val(data));
+        unsafe { super::write_vec_to_buffer(ptr, data) };
+    }
+
+    /// Copies `data` into the mapped memory, starting `offset` bytes in.
+    /// Panics if the memory is not mapped or `data` does not fit
+    pub fn write_struct<T>(&mut self, offset: usize, data: &T) {
+        self.write(offset, std::slice::from_ref(data));
+    }
+
+    // Pointer `offset` bytes into the mapped memory, checked to have `size` bytes behind it
+    pub(crate) fn mapped_ptr(&self, offset: usize, size: usize) -> *mut c_void {
+        let (ptr, mapped_size) = self.memory_ptr.expect("Buffer memory is not mapped!");
+        assert!(
+            offset
+                .checked_add(size)
+                .is_some_and(|end| end <= mapped_size),
+            "Writing {} bytes at offset {} overruns the {} mapped bytes of the buffer!",
+            size,
+            offset,
+            mapped_size
+        );
+        unsafe { (ptr as *mut u8).add(offset) as *mut c_void }
+    }
+}
+impl ManagedBuffer {
+    /// Address of the buffer for use in shaders. Requires the buffer to be created with `SHADER_DEVICE_ADDRESS` usage
+    /// and bound to memory allocated with the `DEVICE_ADDRESS` flag, see [`super::create_device_address_buffer`]
+    pub fn device_address(&self) -> vk::DeviceAddress {
+        let address_info = vk::BufferDeviceAddressInfo::builder().buffer(self.buffer);
+        unsafe { self.logical_device.get_buffer_device_address(&address_info) }
+    }
 }
 impl Deref for ManagedBuffer {
     type Target = vk::Buffer;
@@ -89,6 +155,25 @@ pub fn allocate_and_bind_buffer(
     logical_device: &Device,
     buffer: vk::Buffer,
     memory_properties: vk::MemoryPropertyFlags,
+) -> vk::DeviceMemory {
+    allocate_and_bind_buffer_with_flags(
+        instance,
+        physical_device,
+        logical_device,
+        buffer,
+        memory_properties,
+        vk::MemoryAllocateFlags::empty(),
+    )
+}
+
+/// Same as [`allocate_and_bind_buffer`], with additional allocation flags (e.g. `DEVICE_ADDRESS`)
+pub fn allocate_and_bind_buffer_with_flags(
+    instance: &Instance,
+    physical_device: &vk::PhysicalDevice,
+    logical_device: &Device,
+    buffer: vk::Buffer,
+    memory_properties: vk::MemoryPropertyFlags,
+    allocate_flags: vk::MemoryAllocateFlags,
 ) -> vk::DeviceMemory {
     let memory_requirements = unsafe { logical_device.get_buffer_memory_requirements(buffer) };
     fn find_memory_type(
@@ -107,7 +192,8 @@ pub fn allocate_and_bind_buffer(
         Err("No suitable memory type found!")
     }
 
-    let mem_alloc_info = vk::MemoryAllocateInfo::builder()
+    let mut flags_info = vk::MemoryAllocateFlagsInfo::builder().flags(allocate_flags);
+    let mut mem_alloc_info = vk::MemoryAllocateInfo::builder()
         .allocation_size(memory_requirements.size)
         .memory_type_index(
             find_memory_type(
@@ -119,6 +205,9 @@ pub fn allocate_and_bind_buffer(
             .unwrap()
             .0,
         );
+    if !allocate_flags.is_empty() {
+        mem_alloc_info = mem_alloc_info.push_next(&mut flags_info);
+    }
     // May hit allocation limit if too many separate allocations are performed; use some allocator to do many objects with few buffers
     let buffer_memory = unsafe { logical_device.allocate_memory(&mem_alloc_info, None) }.unwrap();
     unsafe { logical_device.bind_buffer_memory(buffer, buffer_memory, 0) }.unwrap();
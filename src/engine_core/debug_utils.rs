@@ -0,0 +1,100 @@
+use ash::{extensions::ext::DebugUtils, vk};
+use std::ffi::CString;
+
+/** Gives Vulkan objects readable names and marks regions of command buffers with labels, using `VK_EXT_debug_utils`.
+Validation messages and tools such as RenderDoc then show the names instead of raw handles.
+Every method does nothing when created without a loader, which is the case when validation is off. */
+#[derive(Clone)]
+pub struct DebugNamer {
+    loader: Option<DebugUtils>,
+    device: vk::Device,
+}
+
+impl DebugNamer {
+    /// `loader` should be `None` if the `VK_EXT_debug_utils` instance extension is not enabled
+    pub fn new(loader: Option<DebugUtils>, device: vk::Device) -> DebugNamer {
+        DebugNamer { loader, device }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.loader.is_some()
+    }
+
+    /// Names the object behind `handle`. Null handles are skipped
+    pub fn set_name<H: vk::Handle>(&self, handle: H, name: &str) {
+        let Some(loader) = &self.loader else {
+            return;
+        };
+        let raw_handle = handle.as_raw();
+        if raw_handle == 0 {
+            return;
+        }
+        let name = to_cstring(name);
+        let name_info = vk::DebugUtilsObjectNameInfoEXT::builder()
+            .object_type(H::TYPE)
+            .object_handle(raw_handle)
+            .object_name(&name);
+        unsafe { loader.set_debug_utils_object_name(self.device, &name_info) }
+            .expect("Could not set debug name!");
+    }
+
+    /// Names each handle `"{name} {index}"`, for per-frame or per-image objects
+    pub fn set_names<H: vk::Handle + Copy>(&self, handles: &[H], name: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+        for (i, handle) in handles.iter().enumerate() {
+            self.set_name(*handle, &format!("{} {}", name, i));
+        }
+    }
+
+    /** Opens a labelled region of `command_buffer`, closed by [`DebugNamer::cmd_end_label`]. Regions may be nested.
+    # Safety
+    `command_buffer` must be recording. */
+    pub unsafe fn cmd_begin_label(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        name: &str,
+        color: [f32; 4],
+    ) {
+        if let Some(loader) = &self.loader {
+            let name = to_cstring(name);
+            let label = vk::DebugUtilsLabelEXT::builder()
+                .label_name(&name)
+                .color(color);
+            loader.cmd_begin_debug_utils_label(command_buffer, &label);
+        }
+    }
+
+    /** Closes the region last opened by [`DebugNamer::cmd_begin_label`].
+    # Safety
+    `command_buffer` must be recording, with a region opened in it. */
+    pub unsafe fn cmd_end_label(&self, command_buffer: vk::CommandBuffer) {
+        if let Some(loader) = &self.loader {
+            loader.cmd_end_debug_utils_label(command_buffer);
+        }
+    }
+
+    /** Marks a single point in `command_buffer`.
+    # Safety
+    `command_buffer` must be recording. */
+    pub unsafe fn cmd_insert_label(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        name: &str,
+        color: [f32; 4],
+    ) {
+        if let Some(loader) = &self.loader {
+            let name = to_cstring(name);
+            let label = vk::DebugUtilsLabelEXT::builder()
+                .label_name(&name)
+                .color(color);
+            loader.cmd_insert_debug_utils_label(command_buffer, &label);
+        }
+    }
+}
+
+// Interior nul bytes would make the name invalid, so they are dropped
+fn to_cstring(name: &str) -> CString {
+    CString::new(name.replace('\0', "")).unwrap()
+}
@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+/** Resources waiting to be destroyed until the GPU is done with them, keyed by the frame number they were retired in.
+A resource retired in frame N may still be used by that frame or the frames in flight before it,
+so it is only dropped once frame N is known to have finished, see [`DeletionQueue::collect`].
+
+Anything whose `Drop` frees GPU objects can be queued, such as [`ManagedBuffer`](super::ManagedBuffer) and [`ManagedImage`](super::ManagedImage).
+Raw handles are queued as closures with [`DeletionQueue::push_fn`]. Everything queued must be `Send`,
+as resources are often retired from loading threads through the app that owns the queue.
+[`BaseApp`](crate::BaseApp) owns one, see [`BaseApp::destroy_later`](crate::BaseApp::destroy_later),
+and retires replaced swapchains, removed meshes and replaced textures through it. */
+#[derive(Default)]
+pub struct DeletionQueue {
+    entries: VecDeque<(u64, Box<dyn FnOnce() + Send>)>,
+}
+
+impl DeletionQueue {
+    pub fn new() -> DeletionQueue {
+        DeletionQueue::default()
+    }
+
+    /// Drops `resource` once frame `frame_number` has finished
+    pub fn push<T: Send + 'static>(&mut self, frame_number: u64, resource: T) {
+        self.push_fn(frame_number, move || drop(resource));
+    }
+
+    /// Runs `destroy` once frame `frame_number` has finished
+    pub fn push_fn<F: FnOnce() + Send + 'static>(&mut self, frame_number: u64, destroy: F) {
+        // Frame numbers only grow, so entries stay sorted unless pushed with an older frame
+        let position = self
+            .entries
+            .iter()
+            .rposition(|(frame, _)| *frame <= frame_number)
+            .map_or(0, |i| i + 1);
+        self.entries
+            .insert(position, (frame_number, Box::new(destroy)));
+    }
+
+    /// Destroys everything retired in `completed_frame_number` or earlier. Frames finish in submission order,
+    /// so every earlier frame must have finished too
+    pub fn collect(&mut self, completed_frame_number: u64) {
+        while let Some((frame, _)) = self.entries.front() {
+            if *frame > completed_frame_number {
+                break;
+            }
+            let (_, destroy) = self.entries.pop_front().unwrap();
+            destroy();
+        }
+    }
+
+    /// Destroys everything in the queue. Only call once the device is idle
+    pub fn flush(&mut self) {
+        for (_, destroy) in self.entries.drain(..) {
+            destroy();
+        }
+    }
+
+    /// Number of resources waiting to be destroyed
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Drop for DeletionQueue {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DeletionQueue;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn collects_finished_frames_in_order() {
+        let destroyed = Arc::new(Mutex::new(Vec::new()));
+        let mut queue = DeletionQueue::new();
+        for frame in [2, 1, 3, 1] {
+            let destroyed = Arc::clone(&destroyed);
+            queue.push_fn(frame, move || destroyed.lock().unwrap().push(frame));
+        }
+
+        queue.collect(0);
+        assert_eq!(queue.len(), 4);
+        queue.collect(2);
+        assert_eq!(*destroyed.lock().unwrap(), [1, 1, 2]);
+        drop(queue);
+        assert_eq!(*destroyed.lock().unwrap(), [1, 1, 2, 3]);
+    }
+}
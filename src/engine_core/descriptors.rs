@@ -0,0 +1,387 @@
+use super::LogicalDevice;
+use ash::{vk, Device};
+use std::sync::Arc;
+
+/// Descriptor sets of each layout the first pool created by a [`DescriptorAllocator`] has room for
+pub const DEFAULT_SETS_PER_POOL: u32 = 16;
+/// Pools stop doubling in size once they have room for this many sets of each layout
+pub const MAX_SETS_PER_POOL: u32 = 1024;
+
+/// Pool sizes with room for `set_count` descriptor sets with the given bindings
+pub fn descriptor_pool_sizes(
+    bindings: &[vk::DescriptorSetLayoutBinding],
+    set_count: u32,
+) -> Vec<vk::DescriptorPoolSize> {
+    let mut pool_sizes: Vec<vk::DescriptorPoolSize> = Vec::new();
+    // Bindings may be declared without descriptors, but pool sizes must not be empty
+    for binding in bindings
+        .iter()
+        .filter(|binding| binding.descriptor_count > 0)
+    {
+        let count = binding.descriptor_count * set_count;
+        match pool_sizes
+            .iter_mut()
+            .find(|size| size.ty == binding.descriptor_type)
+        {
+            Some(size) => size.descriptor_count += count,
+            None => pool_sizes.push(
+                *vk::DescriptorPoolSize::builder()
+                    .ty(binding.descriptor_type)
+                    .descriptor_count(count),
+            ),
+        }
+    }
+    pool_sizes
+}
+
+struct LayoutEntry {
+    layout: vk::DescriptorSetLayout,
+    bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    owned: bool,
+}
+
+/** Allocates descriptor sets from pools sized after the layouts registered with it.
+When a pool runs out, a new one with room for twice as many sets is created, up to [`MAX_SETS_PER_POOL`].
+Layouts made with [`DescriptorAllocator::create_layout`] are destroyed along with the pools when the allocator is dropped. */
+pub struct DescriptorAllocator {
+    logical_device: Arc<LogicalDevice>,
+    layouts: Vec<LayoutEntry>,
+    pools: Vec<vk::DescriptorPool>,
+    current_pool: usize,
+    sets_per_pool: u32,
+}
+
+impl DescriptorAllocator {
+    pub fn new(logical_device: &Arc<LogicalDevice>) -> DescriptorAllocator {
+        DescriptorAllocator {
+            logical_device: Arc::clone(logical_device),
+            layouts: Vec::new(),
+            pools: Vec::new(),
+            current_pool: 0,
+            sets_per_pool: DEFAULT_SETS_PER_POOL,
+        }
+    }
+
+    /// Creates a descriptor set layout with `bindings`, owned by the allocator
+    pub fn create_layout(
+        &mut self,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+    ) -> vk::DescriptorSetLayout {
+        let layout_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
+        let layout = unsafe {
+            self.logical_device
+                .create_descriptor_set_layout(&layout_info, None)
+        }
+        .expect("Could not create descriptor set layout!");
+        self.layouts.push(LayoutEntry {
+            layout,
+            bindings: bindings.to_vec(),
+            owned: true,
+        });
+        layout
+    }
+
+    /// Makes sets of a layout created elsewhere allocatable. The layout is not destroyed by the allocator
+    pub fn register_layout(
+        &mut self,
+        layout: vk::DescriptorSetLayout,
+        bindings: &[vk::DescriptorSetLayoutBinding],
+    ) {
+        self.forget_layout(layout);
+        self.layouts.push(LayoutEntry {
+            layout,
+            bindings: bindings.to_vec(),
+            owned: false,
+        });
+    }
+
+    /// Stops tracking a layout given to [`DescriptorAllocator::register_layout`], before it is destroyed
+    pub fn forget_layout(&mut self, layout: vk::DescriptorSetLayout) {
+        self.layouts
+            .retain(|entry| entry.owned || entry.layout != layout);
+    }
+
+    /// Bindings `layout` was created or registered with
+    pub fn layout_bindings(
+        &self,
+        layout: vk::DescriptorSetLayout,
+    ) -> Option<&[vk::DescriptorSetLayoutBinding]> {
+        self.layouts
+            .iter()
+            .find(|entry| entry.layout == layout)
+            .map(|entry| entry.bindings.as_slice())
+    }
+
+    /// Allocates a set of `layout`, which must have been created or registered with this allocator
+    pub fn allocate(&mut self, layout: vk::DescriptorSetLayout) -> vk::DescriptorSet {
+        assert!(
+            self.layout_bindings(layout).is_some(),
+            "Descriptor set layout is not known to the allocator!"
+        );
+        loop {
+            let new_pool = self.current_pool == self.pools.len();
+            if new_pool {
+                let pool = self.create_pool();
+                self.pools.push(pool);
+            }
+            let layouts = [layout];
+            let alloc_info = vk::DescriptorSetAllocateInfo::builder()
+                .descriptor_pool(self.pools[self.current_pool])
+                .set_layouts(&layouts);
+            match unsafe { self.logical_device.allocate_descriptor_sets(&alloc_info) } {
+                Ok(sets) => return sets[0],
+                // The pool was created before the layout was registered, or is full
+                Err(vk::Result::ERROR_OUT_OF_POOL_MEMORY)
+                | Err(vk::Result::ERROR_FRAGMENTED_POOL)
+                    if !new_pool =>
+                {
+                    self.current_pool += 1
+                }
+                Err(error) => panic!("Failed to allocate descriptor set: {}", error),
+            }
+        }
+    }
+
+    /** Returns every set allocated so far to the pools.
+    # Safety
+    None of the allocated sets may be in use by pending command buffers, or be used afterwards. */
+    pub unsafe fn reset(&mut self) {
+        for pool in &self.pools {
+            self.logical_device
+                .reset_descriptor_pool(*pool, vk::DescriptorPoolResetFlags::empty())
+                .expect("Could not reset descriptor pool!");
+        }
+        self.current_pool = 0;
+    }
+
+    /// Pools created so far
+    pub fn pools(&self) -> &[vk::DescriptorPool] {
+        &self.pools
+    }
+
+    fn create_pool(&mut self) -> vk::DescriptorPool {
+        let mut pool_sizes: Vec<vk::DescriptorPoolSize> = Vec::new();
+        for entry in &self.layouts {
+            for size in descriptor_pool_sizes(&entry.bindings, self.sets_per_pool) {
+                match pool_sizes.iter_mut().find(|total| total.ty == size.ty) {
+                    Some(total) => total.descriptor_count += size.descriptor_count,
+                    None => pool_sizes.push(size),
+                }
+            }
+        }
+        // Pools need at least one pool size, even for layouts without bindings
+        if pool_sizes.is_empty() {
+            pool_sizes.push(
+                *vk::DescriptorPoolSize::builder()
+                    .ty(vk::DescriptorType::UNIFORM_BUFFER)
+                    .descriptor_count(1),
+            );
+        }
+        let max_sets = self.sets_per_pool * self.layouts.len().max(1) as u32;
+        let pool_info = vk::DescriptorPoolCreateInfo::builder()
+            .pool_sizes(&pool_sizes)
+            .max_sets(max_sets);
+        let pool = unsafe { self.logical_device.create_descriptor_pool(&pool_info, None) }
+            .expect("Failed to create descriptor pool");
+        self.sets_per_pool = (self.sets_per_pool * 2).min(MAX_SETS_PER_POOL);
+        pool
+    }
+}
+
+impl Drop for DescriptorAllocator {
+    fn drop(&mut self) {
+        unsafe {
+            for pool in &self.pools {
+                self.logical_device.destroy_descriptor_pool(*pool, None);
+            }
+            for entry in self.layouts.iter().filter(|entry| entry.owned) {
+                self.logical_device
+                    .destroy_descriptor_set_layout(entry.layout, None);
+            }
+        }
+    }
+}
+
+enum DescriptorInfo {
+    Buffer(vk::DescriptorBufferInfo),
+    Image(vk::DescriptorImageInfo),
+}
+
+/** Collects buffers and images to write to a descriptor set by binding number.
+The descriptor type of each write is looked up in the layout's bindings.
+```ignore
+let set = DescriptorSetBuilder::new(&bindings)
+    .buffer(0, *uniform_buffer, 0, vk::WHOLE_SIZE)
+    .image(1, texture.image_view, sampler, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+    .build(&mut allocator, layout);
+``` */
+pub struct DescriptorSetBuilder {
+    bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    writes: Vec<(u32, vk::DescriptorType, DescriptorInfo)>,
+}
+
+impl DescriptorSetBuilder {
+    /// `bindings` are those of the layout the written sets have
+    pub fn new(bindings: &[vk::DescriptorSetLayoutBinding]) -> DescriptorSetBuilder {
+        DescriptorSetBuilder {
+            bindings: bindings.to_vec(),
+            writes: Vec::new(),
+        }
+    }
+
+    /// Type of the descriptor at `binding`, if the layout has it
+    pub fn descriptor_type(&self, binding: u32) -> Option<vk::DescriptorType> {
+        self.bindings
+            .iter()
+            .find(|layout_binding| layout_binding.binding == binding)
+            .map(|layout_binding| layout_binding.descriptor_type)
+    }
+
+    /// Binds `range` bytes of `buffer` from `offset`. Panics if `binding` is not a buffer descriptor in the layout
+    pub fn buffer(
+        mut self,
+        binding: u32,
+        buffer: vk::Buffer,
+        offset: vk::DeviceSize,
+        range: vk::DeviceSize,
+    ) -> Self {
+        let descriptor_type = self.expect_type(binding, &BUFFER_DESCRIPTOR_TYPES);
+        let info = *vk::DescriptorBufferInfo::builder()
+            .buffer(buffer)
+            .offset(offset)
+            .range(range);
+        self.writes
+            .push((binding, descriptor_type, DescriptorInfo::Buffer(info)));
+        self
+    }
+
+    /** Binds `image_view` in `image_layout`, along with `sampler` for sampler descriptors.
+    Panics if `binding` is not an image descriptor in the layout */
+    pub fn image(
+        mut self,
+        binding: u32,
+        image_view: vk::ImageView,
+        sampler: vk::Sampler,
+        image_layout: vk::ImageLayout,
+    ) -> Self {
+        let descriptor_type = self.expect_type(binding, &IMAGE_DESCRIPTOR_TYPES);
+        let info = *vk::DescriptorImageInfo::builder()
+            .image_view(image_view)
+            .sampler(sampler)
+            .image_layout(image_layout);
+        self.writes
+            .push((binding, descriptor_type, DescriptorInfo::Image(info)));
+        self
+    }
+
+    /** Writes the collected descriptors to `set`.
+    # Safety
+    `set` must have the layout given to [`DescriptorSetBuilder::new`], and not be in use by pending command buffers. */
+    pub unsafe fn write(&self, logical_device: &Device, set: vk::DescriptorSet) {
+        // The infos are referenced by pointer, so each write gets its own slice of one
+        let descriptor_writes: Vec<vk::WriteDescriptorSet> = self
+            .writes
+            .iter()
+            .map(|(binding, descriptor_type, info)| {
+                let write = vk::WriteDescriptorSet::builder()
+                    .dst_set(set)
+                    .dst_binding(*binding)
+                    .dst_array_element(0)
+                    .descriptor_type(*descriptor_type);
+                match info {
+                    DescriptorInfo::Buffer(info) => *write.buffer_info(std::slice::from_ref(info)),
+                    DescriptorInfo::Image(info) => *write.image_info(std::slice::from_ref(info)),
+                }
+            })
+            .collect();
+        logical_device.update_descriptor_sets(&descriptor_writes, &[]);
+    }
+
+    /// Allocates a set of `layout` from `allocator` and writes the collected descriptors to it
+    pub fn build(
+        &self,
+        allocator: &mut DescriptorAllocator,
+        layout: vk::DescriptorSetLayout,
+    ) -> vk::DescriptorSet {
+        let set = allocator.allocate(layout);
+        unsafe { self.write(&allocator.logical_device, set) };
+        set
+    }
+
+    fn expect_type(&self, binding: u32, allowed: &[vk::DescriptorType]) -> vk::DescriptorType {
+        match self.descriptor_type(binding) {
+            Some(descriptor_type) if allowed.contains(&descriptor_type) => descriptor_type,
+            Some(descriptor_type) => panic!(
+                "Binding {} is a {:?} descriptor, which can not be written with this method!",
+                binding, descriptor_type
+            ),
+            None => panic!("Descriptor set layout has no binding {}!", binding),
+        }
+    }
+}
+
+const BUFFER_DESCRIPTOR_TYPES: [vk::DescriptorType; 4] = [
+    vk::DescriptorType::UNIFORM_BUFFER,
+    vk::DescriptorType::STORAGE_BUFFER,
+    vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC,
+    vk::DescriptorType::STORAGE_BUFFER_DYNAMIC,
+];
+
+const IMAGE_DESCRIPTOR_TYPES: [vk::DescriptorType; 5] = [
+    vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+    vk::DescriptorType::SAMPLED_IMAGE,
+    vk::DescriptorType::STORAGE_IMAGE,
+    vk::DescriptorType::SAMPLER,
+    vk::DescriptorType::INPUT_ATTACHMENT,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(index: u32, ty: vk::DescriptorType, count: u32) -> vk::DescriptorSetLayoutBinding {
+        *vk::DescriptorSetLayoutBinding::builder()
+            .binding(index)
+            .descriptor_type(ty)
+            .descriptor_count(count)
+            .stage_flags(vk::ShaderStageFlags::ALL)
+    }
+
+    fn sizes(pool_sizes: &[vk::DescriptorPoolSize]) -> Vec<(vk::DescriptorType, u32)> {
+        pool_sizes
+            .iter()
+            .map(|size| (size.ty, size.descriptor_count))
+            .collect()
+    }
+
+    #[test]
+    fn pool_sizes_merge_bindings_of_the_same_type() {
+        let bindings = [
+            binding(0, vk::DescriptorType::UNIFORM_BUFFER, 1),
+            binding(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 4),
+            binding(2, vk::DescriptorType::UNIFORM_BUFFER, 2),
+            binding(3, vk::DescriptorType::STORAGE_BUFFER, 1),
+        ];
+        assert_eq!(
+            sizes(&descriptor_pool_sizes(&bindings, 16)),
+            [
+                (vk::DescriptorType::UNIFORM_BUFFER, 48),
+                (vk::DescriptorType::COMBINED_IMAGE_SAMPLER, 64),
+                (vk::DescriptorType::STORAGE_BUFFER, 16),
+            ]
+        );
+    }
+
+    #[test]
+    fn pool_sizes_skip_empty_bindings() {
+        let bindings = [
+            binding(0, vk::DescriptorType::SAMPLED_IMAGE, 0),
+            binding(1, vk::DescriptorType::UNIFORM_BUFFER, 1),
+        ];
+        assert_eq!(
+            sizes(&descriptor_pool_sizes(&bindings, 2)),
+            [(vk::DescriptorType::UNIFORM_BUFFER, 2)]
+        );
+        assert!(descriptor_pool_sizes(&[], 8).is_empty());
+    }
+}
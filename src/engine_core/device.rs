@@ -0,0 +1,46 @@
+use ash::{Device, Entry, Instance};
+use std::ops::Deref;
+
+/** The logical device, owning the instance it was created from and the loader of both.
+Buffers, images, pools and [`DeviceContext`](super::DeviceContext)s hold an `Arc` to it, so whichever of them
+is dropped last, possibly after the [`BaseApp`](crate::BaseApp), destroys the device and then the instance.
+Derefs to the [`Device`] for making Vulkan calls. */
+pub struct LogicalDevice {
+    device: Device,
+    instance: Instance,
+    _entry: Entry,
+}
+
+impl LogicalDevice {
+    /// Takes ownership of `device`, and of the `instance` and `entry` it was created from.
+    /// Objects created from the instance, such as surfaces and debug messengers, must be destroyed before this is dropped
+    pub fn new(entry: Entry, instance: Instance, device: Device) -> LogicalDevice {
+        LogicalDevice {
+            device,
+            instance,
+            _entry: entry,
+        }
+    }
+
+    /// The instance the device was created from
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+}
+
+impl Deref for LogicalDevice {
+    type Target = Device;
+    fn deref(&self) -> &Self::Target {
+        &self.device
+    }
+}
+
+impl Drop for LogicalDevice {
+    fn drop(&mut self) {
+        unsafe {
+            self.device.device_wait_idle().unwrap();
+            self.device.destroy_device(None);
+            self.instance.destroy_instance(None);
+        }
+    }
+}
@@ -0,0 +1,422 @@
+use ash::{vk, Instance};
+use std::ffi::{c_void, CStr, CString};
+use std::mem::{offset_of, size_of};
+
+/// Device features, spanning the core features and the Vulkan 1.1/1.2/1.3 feature structs.
+/// The chained structs can only be queried and enabled on devices and instances of Vulkan 1.2 or newer (1.3 for `vulkan13`).
+/// The `p_next` members are ignored, the engine builds the chain itself.
+#[derive(Clone, Copy, Default)]
+pub struct DeviceFeatures {
+    pub core: vk::PhysicalDeviceFeatures,
+    pub vulkan11: vk::PhysicalDeviceVulkan11Features,
+    pub vulkan12: vk::PhysicalDeviceVulkan12Features,
+    pub vulkan13: vk::PhysicalDeviceVulkan13Features,
+}
+
+// The feature structs are all plain sequences of Bool32 (after the sType/pNext header for the chained ones),
+// so they can be combined element-wise through slices spanning their first to last feature
+type FeatureRange = (usize, usize);
+const CORE_RANGE: FeatureRange = (
+    offset_of!(vk::PhysicalDeviceFeatures, robust_buffer_access),
+    offset_of!(vk::PhysicalDeviceFeatures, inherited_queries),
+);
+const VULKAN11_RANGE: FeatureRange = (
+    offset_of!(
+        vk::PhysicalDeviceVulkan11Features,
+        storage_buffer16_bit_access
+    ),
+    offset_of!(vk::PhysicalDeviceVulkan11Features, shader_draw_parameters),
+);
+const VULKAN12_RANGE: FeatureRange = (
+    offset_of!(
+        vk::PhysicalDeviceVulkan12Features,
+        sampler_mirror_clamp_to_edge
+    ),
+    offset_of!(
+        vk::PhysicalDeviceVulkan12Features,
+        subgroup_broadcast_dynamic_id
+    ),
+);
+// The descriptor indexing features, in the same order in the 1.2 struct and the VK_EXT_descriptor_indexing struct
+const DESCRIPTOR_INDEXING_RANGE: FeatureRange = (
+    offset_of!(
+        vk::PhysicalDeviceVulkan12Features,
+        shader_input_attachment_array_dynamic_indexing
+    ),
+    offset_of!(vk::PhysicalDeviceVulkan12Features, runtime_descriptor_array),
+);
+const DESCRIPTOR_INDEXING_EXT_RANGE: FeatureRange = (
+    offset_of!(
+        vk::PhysicalDeviceDescriptorIndexingFeatures,
+        shader_input_attachment_array_dynamic_indexing
+    ),
+    offset_of!(
+        vk::PhysicalDeviceDescriptorIndexingFeatures,
+        runtime_descriptor_array
+    ),
+);
+const VULKAN13_RANGE: FeatureRange = (
+    offset_of!(vk::PhysicalDeviceVulkan13Features, robust_image_access),
+    offset_of!(vk::PhysicalDeviceVulkan13Features, maintenance4),
+);
+
+fn bools<T>(features: &T, (first, last): FeatureRange) -> &[vk::Bool32] {
+    let count = (last - first) / size_of::<vk::Bool32>() + 1;
+    unsafe {
+        std::slice::from_raw_parts(
+            (features as *const T as *const u8).add(first) as *const vk::Bool32,
+            count,
+        )
+    }
+}
+fn bools_mut<T>(features: &mut T, (first, last): FeatureRange) -> &mut [vk::Bool32] {
+    let count = (last - first) / size_of::<vk::Bool32>() + 1;
+    unsafe {
+        std::slice::from_raw_parts_mut(
+            (features as *mut T as *mut u8).add(first) as *mut vk::Bool32,
+            count,
+        )
+    }
+}
+
+impl DeviceFeatures {
+    fn views(&self) -> [&[vk::Bool32]; 4] {
+        [
+            bools(&self.core, CORE_RANGE),
+            bools(&self.vulkan11, VULKAN11_RANGE),
+            bools(&self.vulkan12, VULKAN12_RANGE),
+            bools(&self.vulkan13, VULKAN13_RANGE),
+        ]
+    }
+
+    fn views_mut(&mut self) -> [&mut [vk::Bool32]; 4] {
+        [
+            bools_mut(&mut self.core, CORE_RANGE),
+            bools_mut(&mut self.vulkan11, VULKAN11_RANGE),
+            bools_mut(&mut self.vulkan12, VULKAN12_RANGE),
+            bools_mut(&mut self.vulkan13, VULKAN13_RANGE),
+        ]
+    }
+
+    fn combine(&self, other: &DeviceFeatures, op: fn(bool, bool) -> bool) -> DeviceFeatures {
+        let mut result = DeviceFeatures::default();
+        let other_views = other.views();
+        let self_views = self.views();
+        for (i, view) in result.views_mut().into_iter().enumerate() {
+            for (j, value) in view.iter_mut().enumerate() {
+                *value = op(
+                    self_views[i][j] != vk::FALSE,
+                    other_views[i][j] != vk::FALSE,
+                )
+                .into();
+            }
+        }
+        result
+    }
+
+    /// Features enabled in both `self` and `other`
+    pub fn intersection(&self, other: &DeviceFeatures) -> DeviceFeatures {
+        self.combine(other, |a, b| a && b)
+    }
+
+    /// Features enabled in either `self` or `other`
+    pub fn union(&self, other: &DeviceFeatures) -> DeviceFeatures {
+        self.combine(other, |a, b| a || b)
+    }
+
+    /// Features enabled in `self` but not in `other`
+    pub fn difference(&self, other: &DeviceFeatures) -> DeviceFeatures {
+        self.combine(other, |a, b| a && !b)
+    }
+
+    /// Whether every feature enabled in `self` is also enabled in `other`
+    pub fn is_subset_of(&self, other: &DeviceFeatures) -> bool {
+        self.difference(other).count() == 0
+    }
+
+    /// Number of enabled features
+    pub fn count(&self) -> usize {
+        self.views()
+            .iter()
+            .map(|view| view.iter().filter(|value| **value != vk::FALSE).count())
+            .sum()
+    }
+
+    fn chain_count(&self) -> [usize; 3] {
+        let views = self.views();
+        [1, 2, 3].map(|i| views[i].iter().filter(|value| **value != vk::FALSE).count())
+    }
+
+    /// Copy of the features with the chained structs that `api_version` can't express cleared.
+    /// On Vulkan 1.1 the descriptor indexing features of `vulkan12` are kept, as `VK_EXT_descriptor_indexing` provides them
+    pub fn limited_to_api_version(&self, api_version: u32) -> DeviceFeatures {
+        let mut limited = *self;
+        if api_version < vk::API_VERSION_1_2 {
+            limited.vulkan11 = Default::default();
+            limited.vulkan12 = Default::default();
+            if api_version >= vk::API_VERSION_1_1 {
+                bools_mut(&mut limited.vulkan12, DESCRIPTOR_INDEXING_RANGE)
+                    .copy_from_slice(bools(&self.vulkan12, DESCRIPTOR_INDEXING_RANGE));
+            }
+        }
+        if api_version < vk::API_VERSION_1_3 {
+            limited.vulkan13 = Default::default();
+        }
+        limited
+    }
+
+    /// Queries the features supported by `device`. The chained structs are only queried if `api_version` allows it,
+    /// which must not be higher than the API version of the instance or the device
+    pub fn supported_by(
+        instance: &Instance,
+        device: vk::PhysicalDevice,
+        api_version: u32,
+    ) -> DeviceFeatures {
+        let mut supported = DeviceFeatures {
+            core: unsafe { instance.get_physical_device_features(device) },
+            ..Default::default()
+        };
+        if api_version >= vk::API_VERSION_1_2 {
+            let mut features2 = vk::PhysicalDeviceFeatures2::builder()
+                .push_next(&mut supported.vulkan11)
+                .push_next(&mut supported.vulkan12);
+            if api_version >= vk::API_VERSION_1_3 {
+                features2 = features2.push_next(&mut supported.vulkan13);
+            }
+            unsafe { instance.get_physical_device_features2(device, &mut features2) };
+            supported.core = features2.features;
+        } else if api_version >= vk::API_VERSION_1_1
+            && has_device_extension(instance, device, vk::ExtDescriptorIndexingFn::name())
+        {
+            let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingFeatures::default();
+            let mut features2 =
+                vk::PhysicalDeviceFeatures2::builder().push_next(&mut descriptor_indexing);
+            unsafe { instance.get_physical_device_features2(device, &mut features2) };
+            bools_mut(&mut supported.vulkan12, DESCRIPTOR_INDEXING_RANGE)
+                .copy_from_slice(bools(&descriptor_indexing, DESCRIPTOR_INDEXING_EXT_RANGE));
+        }
+        supported.clear_chain_pointers();
+        supported
+    }
+
+    /// Whether any of the descriptor indexing features of `vulkan12` are enabled
+    pub fn uses_descriptor_indexing(&self) -> bool {
+        bools(&self.vulkan12, DESCRIPTOR_INDEXING_RANGE)
+            .iter()
+            .any(|value| *value != vk::FALSE)
+    }
+
+    /** Moves the descriptor indexing features of `vulkan12` into a `VK_EXT_descriptor_indexing` feature struct,
+    for devices older than Vulkan 1.2. The struct must be chained onto device creation on its own */
+    pub fn take_descriptor_indexing_ext(&mut self) -> vk::PhysicalDeviceDescriptorIndexingFeatures {
+        let mut descriptor_indexing = vk::PhysicalDeviceDescriptorIndexingFeatures::default();
+        bools_mut(&mut descriptor_indexing, DESCRIPTOR_INDEXING_EXT_RANGE)
+            .copy_from_slice(bools(&self.vulkan12, DESCRIPTOR_INDEXING_RANGE));
+        bools_mut(&mut self.vulkan12, DESCRIPTOR_INDEXING_RANGE).fill(vk::FALSE);
+        descriptor_indexing
+    }
+
+    fn clear_chain_pointers(&mut self) {
+        self.vulkan11.p_next = std::ptr::null_mut();
+        self.vulkan12.p_next = std::ptr::null_mut();
+        self.vulkan13.p_next = std::ptr::null_mut();
+    }
+
+    /** Builds the feature chain for device creation, to be pushed onto [`vk::DeviceCreateInfo`] in place of `enabled_features`.
+    Only the chained structs with any enabled features are linked, so the chain stays valid on Vulkan 1.0/1.1 devices
+    as long as the features were limited with [`DeviceFeatures::limited_to_api_version`].
+    # Safety
+    The returned struct points into `self`, which must outlive it and not be moved. */
+    pub unsafe fn as_chain(&mut self) -> vk::PhysicalDeviceFeatures2 {
+        self.clear_chain_pointers();
+        let [count11, count12, count13] = self.chain_count();
+        let mut next: *mut c_void = std::ptr::null_mut();
+        if count13 > 0 {
+            self.vulkan13.p_next = next;
+            next = &mut self.vulkan13 as *mut _ as *mut c_void;
+        }
+        if count12 > 0 {
+            self.vulkan12.p_next = next;
+            next = &mut self.vulkan12 as *mut _ as *mut c_void;
+        }
+        if count11 > 0 {
+            self.vulkan11.p_next = next;
+            next = &mut self.vulkan11 as *mut _ as *mut c_void;
+        }
+        vk::PhysicalDeviceFeatures2 {
+            p_next: next,
+            features: self.core,
+            ..Default::default()
+        }
+    }
+
+    /// Whether any of the chained (Vulkan 1.1+) features are enabled
+    pub fn uses_chain(&self) -> bool {
+        self.chain_count().iter().any(|count| *count > 0)
+    }
+}
+
+/** Device extensions and features the application needs or would like to have.
+Devices lacking anything required are rejected, while optional extensions and features are enabled where supported
+and improve the score of a device. Which of them were enabled can be queried afterwards through [`EnabledFeatures`].
+
+The default requires only the swapchain extension, and asks for sampler anisotropy, multi-draw indirect
+and storage buffer writes from vertex and fragment shaders. On Vulkan 1.2 it also asks for timeline semaphores
+and buffer device addresses, and on Vulkan 1.3 for synchronization2 and dynamic rendering. */
+#[derive(Clone)]
+pub struct DeviceRequirements {
+    pub required_extensions: Vec<CString>,
+    pub optional_extensions: Vec<CString>,
+    pub required_features: DeviceFeatures,
+    pub optional_features: DeviceFeatures,
+}
+impl Default for DeviceRequirements {
+    fn default() -> Self {
+        let mut optional_features = DeviceFeatures::default();
+        optional_features.core.sampler_anisotropy = vk::TRUE;
+        optional_features.core.multi_draw_indirect = vk::TRUE;
+        // Storage buffer writes from the graphics stages
+        optional_features.core.vertex_pipeline_stores_and_atomics = vk::TRUE;
+        optional_features.core.fragment_stores_and_atomics = vk::TRUE;
+        // Cleared on devices and instances older than the version that made them core
+        optional_features.vulkan12.timeline_semaphore = vk::TRUE;
+        optional_features.vulkan12.buffer_device_address = vk::TRUE;
+        optional_features.vulkan12.draw_indirect_count = vk::TRUE;
+        optional_features.vulkan13.synchronization2 = vk::TRUE;
+        optional_features.vulkan13.dynamic_rendering = vk::TRUE;
+        DeviceRequirements {
+            required_extensions: super::DEVICE_EXTS
+                .iter()
+                .map(|ext| unsafe { CStr::from_ptr(*ext) }.to_owned())
+                .collect(),
+            optional_extensions: Vec::new(),
+            required_features: DeviceFeatures::default(),
+            optional_features,
+        }
+    }
+}
+impl DeviceRequirements {
+    pub fn require_extension(mut self, name: &CStr) -> Self {
+        self.required_extensions.push(name.to_owned());
+        self
+    }
+
+    pub fn request_extension(mut self, name: &CStr) -> Self {
+        self.optional_extensions.push(name.to_owned());
+        self
+    }
+
+    /// Adds the enabled features of `features` to the required features
+    pub fn require_features(mut self, features: &DeviceFeatures) -> Self {
+        self.required_features = self.required_features.union(features);
+        self
+    }
+
+    /// Adds the enabled features of `features` to the optional features
+    pub fn request_features(mut self, features: &DeviceFeatures) -> Self {
+        self.optional_features = self.optional_features.union(features);
+        self
+    }
+
+    /** Checks the requirements against what `device` supports when used at `api_version`.
+    Returns the extensions and features to enable, or the reasons the device can't be used.
+    Features of chained structs that `api_version` doesn't cover count as unsupported */
+    pub fn negotiate(
+        &self,
+        instance: &Instance,
+        device: vk::PhysicalDevice,
+        api_version: u32,
+    ) -> Result<EnabledFeatures, Vec<String>> {
+        let available_extensions: Vec<CString> =
+            unsafe { instance.enumerate_device_extension_properties(device) }
+                .unwrap()
+                .iter()
+                .map(|ext| unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) }.to_owned())
+                .collect();
+        let supported = DeviceFeatures::supported_by(instance, device, api_version)
+            .limited_to_api_version(api_version);
+
+        let mut reasons = Vec::new();
+        for extension in &self.required_extensions {
+            if !available_extensions.contains(extension) {
+                reasons.push(format!("missing extension {}", extension.to_string_lossy()));
+            }
+        }
+        let missing = self.required_features.difference(&supported);
+        if missing.count() > 0 {
+            if self
+                .required_features
+                .limited_to_api_version(api_version)
+                .count()
+                < self.required_features.count()
+            {
+                reasons.push("required features need a newer Vulkan version".to_owned());
+            } else {
+                reasons.push(format!("missing {} required features", missing.count()));
+            }
+        }
+        if !reasons.is_empty() {
+            return Err(reasons);
+        }
+
+        let mut extensions = self.required_extensions.clone();
+        for extension in &self.optional_extensions {
+            if available_extensions.contains(extension) && !extensions.contains(extension) {
+                extensions.push(extension.clone());
+            }
+        }
+        let features = self
+            .required_features
+            .union(&self.optional_features.intersection(&supported));
+        // Before Vulkan 1.2, descriptor indexing is only supported through the extension
+        let descriptor_indexing_ext = vk::ExtDescriptorIndexingFn::name();
+        if api_version < vk::API_VERSION_1_2
+            && features.uses_descriptor_indexing()
+            && !extensions
+                .iter()
+                .any(|ext| ext.as_c_str() == descriptor_indexing_ext)
+        {
+            extensions.push(descriptor_indexing_ext.to_owned());
+        }
+        Ok(EnabledFeatures {
+            extensions,
+            features,
+            api_version,
+        })
+    }
+}
+
+/// The device extensions and features that were enabled on the logical device
+#[derive(Clone, Default)]
+pub struct EnabledFeatures {
+    pub extensions: Vec<CString>,
+    pub features: DeviceFeatures,
+    /// Vulkan version the features were negotiated for
+    pub api_version: u32,
+}
+impl EnabledFeatures {
+    pub fn has_extension(&self, name: &CStr) -> bool {
+        self.extensions.iter().any(|ext| ext.as_c_str() == name)
+    }
+
+    /// Number of optional extensions and features enabled, relative to `requirements`. Used to score devices
+    pub fn optional_count(&self, requirements: &DeviceRequirements) -> usize {
+        let extensions = requirements
+            .optional_extensions
+            .iter()
+            .filter(|ext| self.has_extension(ext))
+            .count();
+        extensions
+            + self
+                .features
+                .intersection(&requirements.optional_features)
+                .count()
+    }
+}
+
+fn has_device_extension(instance: &Instance, device: vk::PhysicalDevice, name: &CStr) -> bool {
+    unsafe { instance.enumerate_device_extension_properties(device) }
+        .unwrap_or_default()
+        .iter()
+        .any(|ext| unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) } == name)
+}
@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Number of frames the statistics of [`FrameStats`] are computed over by default
+pub const DEFAULT_FRAME_STATS_WINDOW: usize = 240;
+
+// Weight of the newest frame in the smoothed frame time
+const SMOOTHING_FACTOR: f64 = 0.1;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct FrameSample {
+    frame_time: Duration,
+    record_time: Duration,
+    wait_time: Duration,
+}
+
+/** Frame times over a rolling window of recent frames.
+A frame lasts from one [`FrameStats::begin_frame`] to the next. Within it, time spent waiting for the GPU
+and recording commands is added with [`FrameStats::add_wait_time`] and [`FrameStats::add_record_time`].
+[`BaseApp`](crate::BaseApp) does all of this itself, see [`BaseApp::frame_stats`](crate::BaseApp::frame_stats). */
+#[derive(Clone, Debug)]
+pub struct FrameStats {
+    samples: VecDeque<FrameSample>,
+    window_size: usize,
+    frame_start: Option<Instant>,
+    current_record_time: Duration,
+    current_wait_time: Duration,
+    smoothed_frame_time: f64,
+    frame_count: u64,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        FrameStats::new(DEFAULT_FRAME_STATS_WINDOW)
+    }
+}
+
+impl FrameStats {
+    /// Keeps statistics over the last `window_size` frames
+    pub fn new(window_size: usize) -> FrameStats {
+        let window_size = window_size.max(1);
+        FrameStats {
+            samples: VecDeque::with_capacity(window_size),
+            window_size,
+            frame_start: None,
+            current_record_time: Duration::ZERO,
+            current_wait_time: Duration::ZERO,
+            smoothed_frame_time: 0.0,
+            frame_count: 0,
+        }
+    }
+
+    /// Ends the current frame, if any, and starts timing the next
+    pub fn begin_frame(&mut self) {
+        let now = Instant::now();
+        if let Some(frame_start) = self.frame_start {
+            let frame_time = now - frame_start;
+            if self.samples.len() == self.window_size {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(FrameSample {
+                frame_time,
+                record_time: self.current_record_time,
+                wait_time: self.current_wait_time,
+            });
+            self.smoothed_frame_time = if self.frame_count == 0 {
+                frame_time.as_secs_f64()
+            } else {
+                self.smoothed_frame_time
+                    + (frame_time.as_secs_f64() - self.smoothed_frame_time) * SMOOTHING_FACTOR
+            };
+            self.frame_count += 1;
+        }
+        self.frame_start = Some(now);
+        self.current_record_time = Duration::ZERO;
+        self.current_wait_time = Duration::ZERO;
+    }
+
+    /// Counts `duration` as CPU time spent recording commands in the current frame
+    pub fn add_record_time(&mut self, duration: Duration) {
+        self.current_record_time += duration;
+    }
+
+    /// Counts `duration` as CPU time spent blocked on the GPU in the current frame
+    pub fn add_wait_time(&mut self, duration: Duration) {
+        self.current_wait_time += duration;
+    }
+
+    /// Forgets all frames, for example after the application has been paused
+    pub fn reset(&mut self) {
+        *self = FrameStats::new(self.window_size);
+    }
+
+    /// Number of completed frames since creation or the last reset
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    /// Number of frames the statistics are currently computed over
+    pub fn window_len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Duration of the last completed frame
+    pub fn delta(&self) -> Duration {
+        self.samples.back().map_or(Duration::ZERO, |s| s.frame_time)
+    }
+
+    /// Exponential moving average of the frame time, less jumpy than [`FrameStats::delta`] for display
+    pub fn smoothed(&self) -> Duration {
+        Duration::from_secs_f64(self.smoothed_frame_time)
+    }
+
+    /// Mean frame time over the window
+    pub fn average(&self) -> Duration {
+        self.mean(|s| s.frame_time)
+    }
+
+    pub fn min(&self) -> Duration {
+        self.samples
+            .iter()
+            .map(|s| s.frame_time)
+            .min()
+            .unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.samples
+            .iter()
+            .map(|s| s.frame_time)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Frame time that `percentile` percent of the frames in the window were at most, e.g. 99.0 for the 99th percentile
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        let mut frame_times: Vec<Duration> = self.samples.iter().map(|s| s.frame_time).collect();
+        frame_times.sort_unstable();
+        let rank =
+            (percentile.clamp(0.0, 100.0) / 100.0 * frame_times.len() as f64).ceil() as usize;
+        frame_times[rank.saturating_sub(1)]
+    }
+
+    /// Frames per second from the smoothed frame time
+    pub fn fps(&self) -> f64 {
+        if self.smoothed_frame_time > 0.0 {
+            1.0 / self.smoothed_frame_time
+        } else {
+            0.0
+        }
+    }
+
+    /// CPU time spent recording commands in the last completed frame
+    pub fn record_time(&self) -> Duration {
+        self.samples
+            .back()
+            .map_or(Duration::ZERO, |s| s.record_time)
+    }
+
+    /// CPU time spent waiting for the GPU in the last completed frame
+    pub fn wait_time(&self) -> Duration {
+        self.samples.back().map_or(Duration::ZERO, |s| s.wait_time)
+    }
+
+    /// Mean CPU time spent recording commands over the window
+    pub fn average_record_time(&self) -> Duration {
+        self.mean(|s| s.record_time)
+    }
+
+    /// Mean CPU time spent waiting for the GPU over the window
+    pub fn average_wait_time(&self) -> Duration {
+        self.mean(|s| s.wait_time)
+    }
+
+    fn mean(&self, field: impl Fn(&FrameSample) -> Duration) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().map(field).sum::<Duration>() / self.samples.len() as u32
+    }
+}
+
+/// One line summary, such as `60.0 fps, 16.67 ms (min 16.02, max 17.50, p99 17.31), record 0.12 ms, wait 15.80 ms`
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{:.1} fps, {:.2} ms (min {:.2}, max {:.2}, p99 {:.2}), record {:.2} ms, wait {:.2} ms",
+            self.fps(),
+            ms(self.smoothed()),
+            ms(self.min()),
+            ms(self.max()),
+            ms(self.percentile(99.0)),
+            ms(self.average_record_time()),
+            ms(self.average_wait_time()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_frame_times(
+        window_size: usize,
+        milliseconds: impl IntoIterator<Item = u64>,
+    ) -> FrameStats {
+        let mut stats = FrameStats::new(window_size);
+        for ms in milliseconds {
+            if stats.samples.len() == stats.window_size {
+                stats.samples.pop_front();
+            }
+            stats.samples.push_back(FrameSample {
+                frame_time: Duration::from_millis(ms),
+                ..FrameSample::default()
+            });
+        }
+        stats
+    }
+
+    #[test]
+    fn percentiles_use_nearest_rank() {
+        let stats = with_frame_times(100, 1..=100);
+        assert_eq!(stats.percentile(50.0), Duration::from_millis(50));
+        assert_eq!(stats.percentile(99.0), Duration::from_millis(99));
+        assert_eq!(stats.percentile(99.5), Duration::from_millis(100));
+        assert_eq!(stats.percentile(100.0), Duration::from_millis(100));
+        assert_eq!(stats.percentile(0.0), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn percentiles_ignore_sample_order_and_clamp() {
+        let stats = with_frame_times(10, [40, 10, 30, 20]);
+        assert_eq!(stats.percentile(25.0), Duration::from_millis(10));
+        assert_eq!(stats.percentile(75.0), Duration::from_millis(30));
+        assert_eq!(stats.percentile(150.0), Duration::from_millis(40));
+        assert_eq!(stats.percentile(-5.0), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn statistics_cover_only_the_window() {
+        let stats = with_frame_times(3, [100, 1, 2, 3]);
+        assert_eq!(stats.window_len(), 3);
+        assert_eq!(stats.max(), Duration::from_millis(3));
+        assert_eq!(stats.min(), Duration::from_millis(1));
+        assert_eq!(stats.average(), Duration::from_millis(2));
+        assert_eq!(stats.delta(), Duration::from_millis(3));
+    }
+
+    #[test]
+    fn empty_statistics_are_zero() {
+        let stats = FrameStats::default();
+        assert_eq!(stats.percentile(99.0), Duration::ZERO);
+        assert_eq!(stats.average(), Duration::ZERO);
+        assert_eq!(stats.fps(), 0.0);
+    }
+}
@@ -0,0 +1,214 @@
+use super::{buffer, EnabledFeatures, LogicalDevice, ManagedBuffer, MAX_FRAMES_IN_FLIGHT};
+use ash::{vk, Device, Instance};
+use std::sync::Arc;
+
+const COMMAND_STRIDE: u32 = std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32;
+
+/// How indirect draws can be issued on a device, see [`cmd_draw_indexed_indirect`]
+#[derive(Clone, Copy, Debug)]
+pub struct IndirectDrawSupport {
+    /// More than one draw per `vkCmdDrawIndexedIndirect`
+    pub multi_draw_indirect: bool,
+    /// `vkCmdDrawIndexedIndirectCount`, reading the number of draws from a buffer
+    pub draw_indirect_count: bool,
+    /// Most draws a single indirect command may issue
+    pub max_draw_indirect_count: u32,
+}
+
+impl IndirectDrawSupport {
+    pub fn new(
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        enabled_features: &EnabledFeatures,
+    ) -> IndirectDrawSupport {
+        let limits = unsafe { instance.get_physical_device_properties(*physical_device) }.limits;
+        let features = &enabled_features.features;
+        IndirectDrawSupport {
+            multi_draw_indirect: features.core.multi_draw_indirect == vk::TRUE,
+            draw_indirect_count: features.vulkan12.draw_indirect_count == vk::TRUE,
+            max_draw_indirect_count: limits.max_draw_indirect_count,
+        }
+    }
+}
+
+/** Issues the draws in `commands_buffer` from `offset`, using the currently bound vertex and index buffers.
+With `count_buffer`, the number of draws is read from the `u32` at the given offset, capped at `max_draw_count`.
+Uses `vkCmdDrawIndexedIndirectCount` when supported. Otherwise all `max_draw_count` draws are issued, so unused commands
+must have an `instance_count` of 0. Without `multiDrawIndirect`, each draw is issued separately.
+# Safety
+`command_buffer` must be recording inside a render pass with a pipeline, vertex buffers and index buffer bound. */
+pub unsafe fn cmd_draw_indexed_indirect(
+    logical_device: &Device,
+    command_buffer: vk::CommandBuffer,
+    commands_buffer: vk::Buffer,
+    offset: vk::DeviceSize,
+    count_buffer: Option<(vk::Buffer, vk::DeviceSize)>,
+    max_draw_count: u32,
+    support: &IndirectDrawSupport,
+) {
+    match count_buffer {
+        Some((count_buffer, count_offset)) if support.draw_indirect_count => {
+            logical_device.cmd_draw_indexed_indirect_count(
+                command_buffer,
+                commands_buffer,
+                offset,
+                count_buffer,
+                count_offset,
+                max_draw_count,
+                COMMAND_STRIDE,
+            );
+        }
+        _ if support.multi_draw_indirect => {
+            let batch_size = support.max_draw_indirect_count.max(1);
+            let mut first = 0;
+            while first < max_draw_count {
+                let draw_count = batch_size.min(max_draw_count - first);
+                logical_device.cmd_draw_indexed_indirect(
+                    command_buffer,
+                    commands_buffer,
+                    offset + first as vk::DeviceSize * COMMAND_STRIDE as vk::DeviceSize,
+                    draw_count,
+                    COMMAND_STRIDE,
+                );
+                first += draw_count;
+            }
+        }
+        _ => {
+            for draw in 0..max_draw_count {
+                logical_device.cmd_draw_indexed_indirect(
+                    command_buffer,
+                    commands_buffer,
+                    offset + draw as vk::DeviceSize * COMMAND_STRIDE as vk::DeviceSize,
+                    1,
+                    COMMAND_STRIDE,
+                );
+            }
+        }
+    }
+}
+
+/** `DrawIndexedIndirectCommand`s and a draw count, one set per frame in flight.
+Host visible buffers are written from the CPU with [`IndirectBuffer::write`]. Device local buffers are meant to be filled
+by compute shaders, and are also usable as storage buffers, laid out as a `u32` draw count in the count buffer
+and a tightly packed array of commands.
+Must not be dropped while frames using it are in flight, see [`BaseApp::destroy_later`](crate::BaseApp::destroy_later). */
+pub struct IndirectBuffer {
+    commands_buffers: Vec<ManagedBuffer>,
+    count_buffers: Vec<ManagedBuffer>,
+    max_draw_count: u32,
+    draw_counts: [u32; MAX_FRAMES_IN_FLIGHT],
+    logical_device: Arc<LogicalDevice>,
+}
+
+impl IndirectBuffer {
+    /// Creates buffers with room for `max_draw_count` commands per frame, host visible if `host_visible` and device local otherwise
+    pub fn new(
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        max_draw_count: u32,
+        host_visible: bool,
+    ) -> IndirectBuffer {
+        let memory_properties = if host_visible {
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT
+        } else {
+            vk::MemoryPropertyFlags::DEVICE_LOCAL
+        };
+        let create = |size: vk::DeviceSize| {
+            let indirect_buffer = buffer::create_buffer(
+                logical_device,
+                size,
+                vk::BufferUsageFlags::INDIRECT_BUFFER
+                    | vk::BufferUsageFlags::STORAGE_BUFFER
+                    | vk::BufferUsageFlags::TRANSFER_DST,
+            );
+            let indirect_buffer_memory = buffer::allocate_and_bind_buffer(
+                instance,
+                physical_device,
+                logical_device,
+                indirect_buffer,
+                memory_properties,
+            );
+            let mut managed_buffer = ManagedBuffer::new(
+                logical_device,
+                indirect_buffer,
+                Some(indirect_buffer_memory),
+            );
+            if host_visible {
+                managed_buffer.map_buffer_memory();
+            }
+            managed_buffer
+        };
+        let commands_size =
+            max_draw_count.max(1) as vk::DeviceSize * COMMAND_STRIDE as vk::DeviceSize;
+        IndirectBuffer {
+            commands_buffers: (0..MAX_FRAMES_IN_FLIGHT)
+                .map(|_| create(commands_size))
+                .collect(),
+            count_buffers: (0..MAX_FRAMES_IN_FLIGHT)
+                .map(|_| create(std::mem::size_of::<u32>() as vk::DeviceSize))
+                .collect(),
+            max_draw_count,
+            draw_counts: [max_draw_count; MAX_FRAMES_IN_FLIGHT],
+            logical_device: Arc::clone(logical_device),
+        }
+    }
+
+    pub fn max_draw_count(&self) -> u32 {
+        self.max_draw_count
+    }
+
+    /// Buffer of draw commands for `frame`
+    pub fn commands_buffer(&self, frame: usize) -> vk::Buffer {
+        *self.commands_buffers[frame]
+    }
+
+    /// Buffer holding the `u32` draw count for `frame`
+    pub fn count_buffer(&self, frame: usize) -> vk::Buffer {
+        *self.count_buffers[frame]
+    }
+
+    /** Replaces the draw commands of `frame` from the CPU. Panics if the buffers are not host visible or `commands` does not fit.
+    Only call once the frame's in-flight fence has been waited on. */
+    pub fn write(&mut self, frame: usize, commands: &[vk::DrawIndexedIndirectCommand]) {
+        assert!(
+            commands.len() <= self.max_draw_count as usize,
+            "{} draws do not fit in an indirect buffer of {}",
+            commands.len(),
+            self.max_draw_count
+        );
+        assert!(
+            self.commands_buffers[frame].is_mapped(),
+            "Indirect buffer is not host visible!"
+        );
+        self.commands_buffers[frame].write(0, commands);
+        self.count_buffers[frame].write(0, &[commands.len() as u32]);
+        self.draw_counts[frame] = commands.len() as u32;
+    }
+
+    /** Issues the draws of `frame` with [`cmd_draw_indexed_indirect`]. The draw count comes from the count buffer when the
+    device supports it, so it may be written by the GPU. Otherwise it is the number of commands last written from the CPU,
+    or [`IndirectBuffer::max_draw_count`] for buffers filled by the GPU.
+    # Safety
+    Same as [`cmd_draw_indexed_indirect`], and the commands must have been written and made visible to the draw indirect stage. */
+    pub unsafe fn cmd_draw(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        frame: usize,
+        support: &IndirectDrawSupport,
+    ) {
+        cmd_draw_indexed_indirect(
+            &self.logical_device,
+            command_buffer,
+            *self.commands_buffers[frame],
+            0,
+            Some((*self.count_buffers[frame], 0)),
+            if support.draw_indirect_count {
+                self.max_draw_count
+            } else {
+                self.draw_counts[frame]
+            },
+            support,
+        );
+    }
+}
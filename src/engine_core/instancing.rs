@@ -0,0 +1,177 @@
+use super::{
+    buffer, LogicalDevice, ManagedBuffer, MeshHandle, VertexInputDescriptors, MAX_FRAMES_IN_FLIGHT,
+};
+use ash::{vk, Instance};
+use glam::Mat4;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// Vertex binding that [`InstanceBuffer::cmd_draw`] binds instance data to. The mesh's vertices use binding 0
+pub const INSTANCE_BINDING: u32 = 1;
+
+/** Per-instance model matrix. In shaders, it is read as four `vec4` columns starting at the location given to
+[`VertexInputDescriptors::with_instance_transforms`], e.g.
+```glsl
+layout(location = 2) in mat4 instance_model;
+``` */
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct InstanceTransform {
+    pub model: Mat4,
+}
+
+impl VertexInputDescriptors {
+    /// Adds an `INSTANCE`-rate binding of `stride` bytes at `binding`
+    pub fn with_instance_binding(mut self, binding: u32, stride: u32) -> Self {
+        self.bindings.push(
+            *vk::VertexInputBindingDescription::builder()
+                .binding(binding)
+                .input_rate(vk::VertexInputRate::INSTANCE)
+                .stride(stride),
+        );
+        self
+    }
+
+    /// Adds an [`InstanceTransform`] binding at [`INSTANCE_BINDING`], taking the four locations from `first_location`
+    pub fn with_instance_transforms(self, first_location: u32) -> Self {
+        let mut descriptors = self.with_instance_binding(
+            INSTANCE_BINDING,
+            std::mem::size_of::<InstanceTransform>() as u32,
+        );
+        for column in 0..4 {
+            descriptors.attributes.push(
+                *vk::VertexInputAttributeDescription::builder()
+                    .binding(INSTANCE_BINDING)
+                    .location(first_location + column)
+                    .format(vk::Format::R32G32B32A32_SFLOAT)
+                    .offset(column * 16),
+            );
+        }
+        descriptors
+    }
+}
+
+/** Per-instance data for drawing many copies of one mesh in a single draw call.
+Holds a host visible buffer per frame in flight, so writing a frame's instances never touches data the GPU may still be reading.
+Must not be dropped while frames using it are in flight, see [`BaseApp::destroy_later`](crate::BaseApp::destroy_later). */
+pub struct InstanceBuffer<T> {
+    pub mesh: MeshHandle,
+    buffers: Vec<ManagedBuffer>,
+    capacity: usize,
+    buffer_capacities: [usize; MAX_FRAMES_IN_FLIGHT],
+    counts: [u32; MAX_FRAMES_IN_FLIGHT],
+    instance: Instance,
+    physical_device: vk::PhysicalDevice,
+    logical_device: Arc<LogicalDevice>,
+    _instance_type: PhantomData<T>,
+}
+
+impl<T> InstanceBuffer<T> {
+    /// Creates instance buffers for `mesh` with room for `capacity` instances. They grow as needed
+    pub fn new(
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        mesh: MeshHandle,
+        capacity: usize,
+    ) -> InstanceBuffer<T> {
+        let capacity = capacity.max(1);
+        let buffers = (0..MAX_FRAMES_IN_FLIGHT)
+            .map(|_| {
+                create_instance_buffer::<T>(instance, physical_device, logical_device, capacity)
+            })
+            .collect();
+        InstanceBuffer {
+            mesh,
+            buffers,
+            capacity,
+            buffer_capacities: [capacity; MAX_FRAMES_IN_FLIGHT],
+            counts: [0; MAX_FRAMES_IN_FLIGHT],
+            instance: instance.clone(),
+            physical_device: *physical_device,
+            logical_device: Arc::clone(logical_device),
+            _instance_type: PhantomData,
+        }
+    }
+
+    /// Number of instances that fit without growing
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Buffer holding the instances of `frame`
+    pub fn buffer(&self, frame: usize) -> vk::Buffer {
+        *self.buffers[frame]
+    }
+
+    /// Number of instances last written for `frame`
+    pub fn count(&self, frame: usize) -> u32 {
+        self.counts[frame]
+    }
+
+    /// Replaces the instances of `frame`. Only call once the frame's in-flight fence has been waited on
+    pub fn write(&mut self, frame: usize, instances: &[T]) {
+        if instances.len() > self.capacity {
+            self.capacity = instances.len().max(self.capacity * 2);
+        }
+        // Other frames' buffers grow the next time they are written, as they may still be in use
+        if instances.len() > self.buffer_capacities[frame] {
+            self.buffers[frame] = create_instance_buffer::<T>(
+                &self.instance,
+                &self.physical_device,
+                &self.logical_device,
+                self.capacity,
+            );
+            self.buffer_capacities[frame] = self.capacity;
+        }
+        self.buffers[frame].write(0, instances);
+        self.counts[frame] = instances.len() as u32;
+    }
+
+    /** Binds the mesh at binding 0 and the instances of `frame` at [`INSTANCE_BINDING`], then draws every instance in one call.
+    # Safety
+    `command_buffer` must be recording inside a render pass, with a pipeline bound whose vertex input has an instance binding
+    matching `T`, and the mesh must still be registered. */
+    pub unsafe fn cmd_draw(&self, command_buffer: vk::CommandBuffer, frame: usize) {
+        if self.counts[frame] == 0 {
+            return;
+        }
+        self.logical_device.cmd_bind_vertex_buffers(
+            command_buffer,
+            INSTANCE_BINDING,
+            &[*self.buffers[frame]],
+            &[0],
+        );
+        super::cmd_draw_mesh(
+            &self.logical_device,
+            command_buffer,
+            &self.mesh,
+            self.counts[frame],
+        );
+    }
+}
+
+fn create_instance_buffer<T>(
+    instance: &Instance,
+    physical_device: &vk::PhysicalDevice,
+    logical_device: &Arc<LogicalDevice>,
+    capacity: usize,
+) -> ManagedBuffer {
+    let size = (std::mem::size_of::<T>() * capacity).max(1) as vk::DeviceSize;
+    let instance_buffer =
+        buffer::create_buffer(logical_device, size, vk::BufferUsageFlags::VERTEX_BUFFER);
+    let instance_buffer_memory = buffer::allocate_and_bind_buffer(
+        instance,
+        physical_device,
+        logical_device,
+        instance_buffer,
+        vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+    );
+    let mut managed_buffer = ManagedBuffer::new(
+        logical_device,
+        instance_buffer,
+        Some(instance_buffer_memory),
+    );
+    managed_buffer.map_buffer_memory();
+    managed_buffer
+}
@@ -0,0 +1,207 @@
+use super::ValidIndexBufferType;
+use super::{
+    create_index_buffer, create_vertex_buffer, LogicalDevice, ManagedBuffer, UploadManager,
+};
+use ash::{vk, Device, Instance};
+use std::sync::Arc;
+
+/** Everything needed to draw a mesh registered in a [`MeshRegistry`].
+The buffers are owned by the registry, and stay valid until the mesh is removed from it.
+The offsets allow meshes to be ranges inside shared buffers. */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MeshHandle {
+    id: usize,
+    generation: u32,
+    pub vertex_buffer: vk::Buffer,
+    pub vertex_offset: vk::DeviceSize,
+    pub vertex_count: u32,
+    pub index_buffer: vk::Buffer,
+    pub index_offset: vk::DeviceSize,
+    pub index_count: u32,
+    pub index_type: vk::IndexType,
+}
+
+struct MeshBuffers {
+    vertex_buffer: ManagedBuffer,
+    index_buffer: ManagedBuffer,
+}
+
+// Ids are reused, so each slot counts how often it has been emptied to tell stale handles apart
+#[derive(Default)]
+struct MeshSlot {
+    generation: u32,
+    buffers: Option<MeshBuffers>,
+}
+
+/// Owns the vertex and index buffers of any number of meshes, handing out a [`MeshHandle`] for each
+#[derive(Default)]
+pub struct MeshRegistry {
+    meshes: Vec<MeshSlot>,
+    free_ids: Vec<usize>,
+}
+
+impl MeshRegistry {
+    pub fn new() -> MeshRegistry {
+        MeshRegistry::default()
+    }
+
+    /** Creates device local buffers for a mesh and records their uploads into `uploads`.
+    The mesh may be drawn once the upload batch has completed, see [`UploadManager::is_complete`]. */
+    pub fn add<VertexType, IndexType: ValidIndexBufferType>(
+        &mut self,
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        uploads: &mut UploadManager,
+        vertices: &[VertexType],
+        indices: &[IndexType],
+    ) -> MeshHandle {
+        self.add_vertex_data(
+            instance,
+            physical_device,
+            logical_device,
+            uploads,
+            vertices,
+            vertices.len(),
+            indices,
+        )
+    }
+
+    /// Same as [`MeshRegistry::add`], for vertices packed into bytes at `vertex_stride` apart, such as those of a runtime vertex layout.
+    /// Panics if the size of `vertex_data` is not a multiple of the stride
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_raw<IndexType: ValidIndexBufferType>(
+        &mut self,
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        uploads: &mut UploadManager,
+        vertex_data: &[u8],
+        vertex_stride: u32,
+        indices: &[IndexType],
+    ) -> MeshHandle {
+        assert!(
+            vertex_stride > 0 && vertex_data.len().is_multiple_of(vertex_stride as usize),
+            "Vertex data is not a whole number of {} byte vertices!",
+            vertex_stride
+        );
+        self.add_vertex_data(
+            instance,
+            physical_device,
+            logical_device,
+            uploads,
+            vertex_data,
+            vertex_data.len() / vertex_stride as usize,
+            indices,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_vertex_data<VertexData, IndexType: ValidIndexBufferType>(
+        &mut self,
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        uploads: &mut UploadManager,
+        vertices: &[VertexData],
+        vertex_count: usize,
+        indices: &[IndexType],
+    ) -> MeshHandle {
+        let vertex_buffer = create_vertex_buffer(
+            instance,
+            physical_device,
+            logical_device,
+            std::mem::size_of_val(vertices) as vk::DeviceSize,
+        );
+        let index_buffer = create_index_buffer::<IndexType>(
+            instance,
+            physical_device,
+            logical_device,
+            indices.len(),
+        );
+        uploads.upload_buffer(vertices, *vertex_buffer, 0);
+        uploads.upload_buffer(indices, *index_buffer, 0);
+
+        let handle = MeshHandle {
+            id: 0,
+            generation: 0,
+            vertex_buffer: *vertex_buffer,
+            vertex_offset: 0,
+            vertex_count: vertex_count as u32,
+            index_buffer: *index_buffer,
+            index_offset: 0,
+            index_count: indices.len() as u32,
+            index_type: IndexType::INDEX_TYPE,
+        };
+        let buffers = MeshBuffers {
+            vertex_buffer,
+            index_buffer,
+        };
+        let id = self.free_ids.pop().unwrap_or_else(|| {
+            self.meshes.push(MeshSlot::default());
+            self.meshes.len() - 1
+        });
+        let slot = &mut self.meshes[id];
+        slot.buffers = Some(buffers);
+        MeshHandle {
+            id,
+            generation: slot.generation,
+            ..handle
+        }
+    }
+
+    /// Removes a mesh, returning its vertex and index buffers.
+    /// These may still be in use by frames in flight, so destroying them must wait until those have finished
+    pub fn remove(&mut self, mesh: MeshHandle) -> Option<(ManagedBuffer, ManagedBuffer)> {
+        // Ids are reused, so a stale handle must not remove the mesh that took its place
+        if !self.contains(mesh) {
+            return None;
+        }
+        let slot = &mut self.meshes[mesh.id];
+        let buffers = slot.buffers.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free_ids.push(mesh.id);
+        Some((buffers.vertex_buffer, buffers.index_buffer))
+    }
+
+    /// Whether `mesh` is still registered
+    pub fn contains(&self, mesh: MeshHandle) -> bool {
+        matches!(
+            self.meshes.get(mesh.id),
+            Some(slot) if slot.buffers.is_some() && slot.generation == mesh.generation
+        )
+    }
+
+    /// Number of registered meshes
+    pub fn len(&self) -> usize {
+        self.meshes.len() - self.free_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/** Binds the vertex and index buffers of `mesh` and draws `instance_count` instances of it.
+# Safety
+`command_buffer` must be recording inside a render pass with a compatible pipeline bound, and the mesh must still be registered. */
+pub unsafe fn cmd_draw_mesh(
+    logical_device: &Device,
+    command_buffer: vk::CommandBuffer,
+    mesh: &MeshHandle,
+    instance_count: u32,
+) {
+    logical_device.cmd_bind_vertex_buffers(
+        command_buffer,
+        0,
+        &[mesh.vertex_buffer],
+        &[mesh.vertex_offset],
+    );
+    logical_device.cmd_bind_index_buffer(
+        command_buffer,
+        mesh.index_buffer,
+        mesh.index_offset,
+        mesh.index_type,
+    );
+    logical_device.cmd_draw_indexed(command_buffer, mesh.index_count, instance_count, 0, 0, 0);
+}
@@ -1,3 +1,4 @@
+use super::DeviceRequirements;
 use ash::{extensions::khr::Surface, vk, Instance};
 use std::ffi::CStr;
 
@@ -26,17 +27,33 @@ pub fn query_swap_chain_support(
 }
 
 /// Helper struct for queue family indices
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct QueueFamilyIndices {
     pub graphics_queue: u32,
     pub present_queue: u32,
+    /// Family dedicated to transfers (no graphics support), if the device has one
+    pub transfer_queue: Option<u32>,
 }
 impl QueueFamilyIndices {
-    /// Copies the queue indices into an array and returns it
+    /// Copies the graphics and present queue indices into an array and returns it
     /// **Do not** rely on the size or order of the array, they may change
     pub fn array(&self) -> [u32; 2] {
         [self.graphics_queue, self.present_queue]
     }
+
+    /// Every distinct queue family in use, including the transfer family
+    pub fn unique(&self) -> Vec<u32> {
+        let mut families = vec![self.graphics_queue];
+        for family in [Some(self.present_queue), self.transfer_queue]
+            .into_iter()
+            .flatten()
+        {
+            if !families.contains(&family) {
+                families.push(family);
+            }
+        }
+        families
+    }
 }
 
 //Find supported (command) queue families. We need certain ones for the engine to work
@@ -65,62 +82,229 @@ pub fn find_queue_families(
             return Some(QueueFamilyIndices {
                 graphics_queue: indices[0].unwrap(),
                 present_queue: indices[1].unwrap(),
+                transfer_queue: find_transfer_queue_family(&queue_family_properties),
             }); //Only reached if the above for loop does not break
         }
     }
     None
 }
-// How good is a given physical device? Uses heuristics to rank, picks best. Also invalidates devices that won't work
+
+// Prefer a transfer-only family (usually backed by a DMA engine), then any transfer family without graphics
+fn find_transfer_queue_family(
+    queue_family_properties: &[vk::QueueFamilyProperties],
+) -> Option<u32> {
+    let transfer_families = || {
+        queue_family_properties
+            .iter()
+            .enumerate()
+            .filter(|(_, family)| {
+                family.queue_count > 0
+                    && family.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                    && !family.queue_flags.contains(vk::QueueFlags::GRAPHICS)
+            })
+    };
+    transfer_families()
+        .find(|(_, family)| !family.queue_flags.contains(vk::QueueFlags::COMPUTE))
+        .or_else(|| transfer_families().next())
+        .map(|(i, _)| i as u32)
+}
+
+/// Summary of a physical device and how well it suits the engine, see [`super::enumerate_physical_devices`]
+#[derive(Clone, Debug)]
+pub struct PhysicalDeviceInfo {
+    pub handle: vk::PhysicalDevice,
+    /// Position of the device in the order reported by the Vulkan instance
+    pub index: usize,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub api_version: u32,
+    pub driver_version: u32,
+    pub memory_heaps: Vec<vk::MemoryHeap>,
+    /// Heuristic score, 0 means the device cannot be used
+    pub suitability: u32,
+    /// Why the device cannot be used, empty if it is suitable
+    pub unsuitable_reasons: Vec<String>,
+}
+impl PhysicalDeviceInfo {
+    pub fn is_suitable(&self) -> bool {
+        self.suitability > 0
+    }
+
+    /// Total size of the device local memory heaps in bytes
+    pub fn device_local_memory(&self) -> vk::DeviceSize {
+        self.memory_heaps
+            .iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .sum()
+    }
+}
+impl std::fmt::Display for PhysicalDeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[{}] {} ({:?}), Vulkan {}.{}.{}, driver {:#x}, {} MiB device local: ",
+            self.index,
+            self.name,
+            self.device_type,
+            vk::api_version_major(self.api_version),
+            vk::api_version_minor(self.api_version),
+            vk::api_version_patch(self.api_version),
+            self.driver_version,
+            self.device_local_memory() / (1024 * 1024),
+        )?;
+        if self.is_suitable() {
+            write!(f, "suitable (score {})", self.suitability)
+        } else {
+            write!(f, "unsuitable ({})", self.unsuitable_reasons.join(", "))
+        }
+    }
+}
+
+/// Name of the environment variable that overrides the [`DeviceSelector`] given to the engine.
+/// A number selects by index, anything else selects by name.
+pub const DEVICE_SELECTOR_ENV: &str = "VK_ENGINE_DEVICE";
+
+/// Which physical device to use, see [`super::find_physical_device`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// The suitable device with the highest score
+    #[default]
+    Best,
+    /// Device at the given position in the instance's device list
+    Index(usize),
+    /// Best suitable device whose name contains the given string, ignoring case
+    Name(String),
+}
+impl DeviceSelector {
+    /// Reads the selector from the `VK_ENGINE_DEVICE` environment variable, if it is set
+    pub fn from_env() -> Option<DeviceSelector> {
+        let value = std::env::var(DEVICE_SELECTOR_ENV).ok()?;
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+        Some(match value.parse::<usize>() {
+            Ok(index) => DeviceSelector::Index(index),
+            Err(_) => DeviceSelector::Name(value.to_owned()),
+        })
+    }
+
+    /// Picks a device from the list, returns `Err` with an explanation if the selection can't be satisfied
+    pub fn select<'a>(
+        &self,
+        devices: &'a [PhysicalDeviceInfo],
+    ) -> Result<&'a PhysicalDeviceInfo, String> {
+        let best = |candidates: Vec<&'a PhysicalDeviceInfo>| {
+            candidates
+                .into_iter()
+                .filter(|info| info.is_suitable())
+                .max_by_key(|info| info.suitability)
+        };
+        match self {
+            DeviceSelector::Best => best(devices.iter().collect())
+                .ok_or_else(|| "No suitable GPU could be found!".to_owned()),
+            DeviceSelector::Index(index) => {
+                let info = devices
+                    .get(*index)
+                    .ok_or_else(|| format!("No device with index {}", index))?;
+                if info.is_suitable() {
+                    Ok(info)
+                } else {
+                    Err(format!("Selected device is unsuitable: {}", info))
+                }
+            }
+            DeviceSelector::Name(name) => {
+                let lowercase_name = name.to_lowercase();
+                let matching: Vec<&PhysicalDeviceInfo> = devices
+                    .iter()
+                    .filter(|info| info.name.to_lowercase().contains(&lowercase_name))
+                    .collect();
+                if matching.is_empty() {
+                    return Err(format!("No device name contains '{}'", name));
+                }
+                let first = matching[0];
+                best(matching).ok_or_else(|| format!("Selected device is unsuitable: {}", first))
+            }
+        }
+    }
+}
+
+/// Gathers the properties of a device and rates it with [`device_suitability`]
+pub fn physical_device_info(
+    instance: &Instance,
+    surface_loader: &Surface,
+    surface: &vk::SurfaceKHR,
+    device: &vk::PhysicalDevice,
+    index: usize,
+    requirements: &DeviceRequirements,
+    instance_api_version: u32,
+) -> PhysicalDeviceInfo {
+    let properties = unsafe { instance.get_physical_device_properties(*device) };
+    let memory_properties = unsafe { instance.get_physical_device_memory_properties(*device) };
+    let (suitability, unsuitable_reasons) = device_suitability(
+        instance,
+        surface_loader,
+        surface,
+        device,
+        requirements,
+        super::api_version_without_patch(properties.api_version).min(instance_api_version),
+    );
+    PhysicalDeviceInfo {
+        handle: *device,
+        index,
+        name: unsafe { CStr::from_ptr(properties.device_name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned(),
+        device_type: properties.device_type,
+        api_version: properties.api_version,
+        driver_version: properties.driver_version,
+        memory_heaps: memory_properties.memory_heaps
+            [..memory_properties.memory_heap_count as usize]
+            .to_vec(),
+        suitability,
+        unsuitable_reasons,
+    }
+}
+
+// How good is a given physical device? Uses heuristics to rank, picks best. Also invalidates devices that won't work.
+// Along with the score, lists every reason a device was rejected
 pub fn device_suitability(
     instance: &Instance,
     surface_loader: &Surface,
     surface: &vk::SurfaceKHR,
     device: &vk::PhysicalDevice,
-) -> u32 {
+    requirements: &DeviceRequirements,
+    api_version: u32,
+) -> (u32, Vec<String>) {
     let device_properties = unsafe { instance.get_physical_device_properties(*device) };
-    let device_features = unsafe { instance.get_physical_device_features(*device) };
 
-    let mut score = 0; //Score of 0 => entirely unsuitable
-    if !check_device_extension_support(instance, device) {
-        return 0;
-    } //Must have extension to query swap chain
+    let (enabled, mut reasons) = match requirements.negotiate(instance, *device, api_version) {
+        Ok(enabled) => (Some(enabled), Vec::new()),
+        Err(reasons) => (None, reasons),
+    };
     let (_, formats, present_modes) = query_swap_chain_support(surface_loader, surface, device);
-    if device_features.geometry_shader == vk::FALSE
-        || device_features.sampler_anisotropy == vk::FALSE
-        || formats.is_empty()
-        || present_modes.is_empty()
-    {
-        return 0;
+    if formats.is_empty() {
+        reasons.push("no surface formats".to_owned());
+    }
+    if present_modes.is_empty() {
+        reasons.push("no present modes".to_owned());
     }
     if find_queue_families(instance, surface_loader, surface, device).is_none() {
-        return 0;
+        reasons.push("no graphics and present queue families".to_owned());
     }
+    let enabled = match enabled {
+        Some(enabled) if reasons.is_empty() => enabled,
+        _ => return (0, reasons), //Score of 0 => entirely unsuitable
+    };
 
+    let mut score = 0;
     if device_properties.device_type == vk::PhysicalDeviceType::DISCRETE_GPU {
         score += 1000
     }
     score += device_properties.limits.max_image_dimension2_d;
-    //println!("Device name: {}", unsafe {CStr::from_ptr(device_properties.device_name.as_ptr())}.to_string_lossy());
+    // Optional extensions and features only break ties between otherwise similar devices
+    score += 10 * enabled.optional_count(requirements) as u32;
 
-    score
-}
-// Physical device needs to support certain extensions
-fn check_device_extension_support(instance: &Instance, device: &vk::PhysicalDevice) -> bool {
-    let device_extension_properties =
-        unsafe { instance.enumerate_device_extension_properties(*device) }.unwrap();
-    let available_extension_names: Vec<&str> = device_extension_properties
-        .iter()
-        .map(|ext| {
-            unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) }
-                .to_str()
-                .unwrap()
-        })
-        .collect();
-    for extension in super::DEVICE_EXTS {
-        let ext_name = unsafe { CStr::from_ptr(extension) }.to_str().unwrap();
-        if !available_extension_names.contains(&ext_name) {
-            return false;
-        }
-    }
-    true
+    (score, reasons)
 }
@@ -8,15 +8,19 @@ use std::os::raw::c_char;
 
 const DEFAULT_ENTRY: *const c_char = cstr!("main").as_ptr();
 
+/// `render_pass` may be null when using dynamic rendering, in which case `rendering_formats` gives the (color, depth) attachment formats
+#[allow(clippy::too_many_arguments)]
 pub fn default_pipeline(
     logical_device: &Device,
     render_pass: vk::RenderPass,
+    rendering_formats: Option<(vk::Format, vk::Format)>,
     swapchain_extent: vk::Extent2D,
-    shaders: &Vec<Shader>,
+    shaders: &[Shader],
     vertex_input_descriptors: &VertexInputDescriptors,
-    descriptor_set_bindings: Vec<vk::DescriptorSetLayoutBinding>,
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    additional_set_layouts: &[vk::DescriptorSetLayout],
     push_constants: [f32; 1],
-) -> (vk::Pipeline, vk::PipelineLayout, vk::DescriptorSetLayout) {
+) -> (vk::Pipeline, vk::PipelineLayout) {
     // Vertex input settings
     let binding_descriptions = &vertex_input_descriptors.bindings;
     let attribute_descriptions = &vertex_input_descriptors.attributes;
@@ -68,26 +72,26 @@ pub fn default_pipeline(
         .logic_op_enable(false)
         .attachments(&pipeline_color_blend_attachment_states);
 
-    // Descriptor set layout
-    let descriptor_set_layout = {
-        let descriptor_set_layout_info =
-            vk::DescriptorSetLayoutCreateInfo::builder().bindings(&descriptor_set_bindings);
-
-        unsafe { logical_device.create_descriptor_set_layout(&descriptor_set_layout_info, None) }
-            .unwrap()
-    };
-
     // Pipeline layout
-    let push_constant_ranges = [*vk::PushConstantRange::builder()
-        .stage_flags(vk::ShaderStageFlags::VERTEX)
-        .offset(0)
-        .size((push_constants.len() * size_of::<f32>()) as u32)];
+    // The second range holds texture indices, see super::cmd_push_texture_indices
+    let push_constant_ranges = [
+        *vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::VERTEX)
+            .offset(0)
+            .size((push_constants.len() * size_of::<f32>()) as u32),
+        *vk::PushConstantRange::builder()
+            .stage_flags(vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT)
+            .offset(super::TEXTURE_INDEX_PUSH_CONSTANT_OFFSET)
+            .size((super::MAX_PUSHED_TEXTURE_INDICES * size_of::<u32>()) as u32),
+    ];
 
     let mut pipeline_layout_info =
         vk::PipelineLayoutCreateInfo::builder().push_constant_ranges(&push_constant_ranges);
     let pipeline_layout = {
-        let layout = [descriptor_set_layout];
-        pipeline_layout_info = pipeline_layout_info.set_layouts(&layout);
+        let layouts: Vec<vk::DescriptorSetLayout> = std::iter::once(descriptor_set_layout)
+            .chain(additional_set_layouts.iter().copied())
+            .collect();
+        pipeline_layout_info = pipeline_layout_info.set_layouts(&layouts);
         unsafe { logical_device.create_pipeline_layout(&pipeline_layout_info, None) }.unwrap()
     };
 
@@ -109,7 +113,15 @@ pub fn default_pipeline(
         .max_depth_bounds(1.0)
         .stencil_test_enable(false);
 
-    let graphics_pipeline_infos = [*vk::GraphicsPipelineCreateInfo::builder()
+    let color_formats = rendering_formats.map(|formats| [formats.0]);
+    let mut rendering_info = vk::PipelineRenderingCreateInfo::builder();
+    if let (Some(color_formats), Some((_, depth_format))) = (&color_formats, rendering_formats) {
+        rendering_info = rendering_info
+            .color_attachment_formats(color_formats)
+            .depth_attachment_format(depth_format);
+    }
+
+    let mut graphics_pipeline_info = vk::GraphicsPipelineCreateInfo::builder()
         .stages(&shader_stages)
         .vertex_input_state(&pipeline_vertex_input_state_info)
         .input_assembly_state(&pipeline_input_assembly_state_info)
@@ -120,7 +132,11 @@ pub fn default_pipeline(
         .depth_stencil_state(&depth_stencil_info)
         .layout(pipeline_layout)
         .render_pass(render_pass)
-        .subpass(0)];
+        .subpass(0);
+    if rendering_formats.is_some() {
+        graphics_pipeline_info = graphics_pipeline_info.push_next(&mut rendering_info);
+    }
+    let graphics_pipeline_infos = [*graphics_pipeline_info];
     let graphics_pipeline = unsafe {
         logical_device.create_graphics_pipelines(
             vk::PipelineCache::null(),
@@ -138,7 +154,7 @@ pub fn default_pipeline(
         }
     }
 
-    (graphics_pipeline, pipeline_layout, descriptor_set_layout)
+    (graphics_pipeline, pipeline_layout)
 }
 
 pub fn default_render_pass(logical_device: &Device, image_format: vk::Format) -> vk::RenderPass {
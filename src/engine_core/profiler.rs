@@ -0,0 +1,447 @@
+use super::{LogicalDevice, MAX_FRAMES_IN_FLIGHT};
+use ash::{vk, Instance};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::sync::Arc;
+
+/// Default number of scopes [`GpuProfiler`] can time per frame
+pub const DEFAULT_MAX_PROFILE_SCOPES: u32 = 256;
+/// Number of read back frames [`GpuProfiler`] keeps
+pub const PROFILER_HISTORY_LENGTH: usize = 120;
+
+/// GPU time of one profiled scope and the scopes nested inside it
+#[derive(Clone, Debug)]
+pub struct GpuScope {
+    pub name: String,
+    /// Nanoseconds since the first timestamp the profiler read back
+    pub start_ns: f64,
+    pub duration_ns: f64,
+    pub children: Vec<GpuScope>,
+}
+
+impl GpuScope {
+    pub fn duration_ms(&self) -> f64 {
+        self.duration_ns / 1_000_000.0
+    }
+}
+
+/// All top level scopes recorded during one frame
+#[derive(Clone, Debug)]
+pub struct GpuFrameTimings {
+    /// Counts the frames the profiler has begun, starting at 0
+    pub frame_number: u64,
+    pub scopes: Vec<GpuScope>,
+}
+
+impl GpuFrameTimings {
+    /// Sum of the top level scopes
+    pub fn total_ns(&self) -> f64 {
+        self.scopes.iter().map(|scope| scope.duration_ns).sum()
+    }
+}
+
+// A scope as recorded, before its timestamps are read back
+struct ScopeRecord {
+    name: String,
+    parent: Option<usize>,
+}
+
+struct FrameQueries {
+    query_pool: vk::QueryPool,
+    scopes: Vec<ScopeRecord>,
+    frame_number: u64,
+}
+
+struct ProfilerState {
+    logical_device: Arc<LogicalDevice>,
+    frames: Vec<FrameQueries>,
+    current_frame: Option<usize>,
+    open_scopes: Vec<usize>,
+    max_scopes: u32,
+    timestamp_period: f64,
+    timestamp_mask: u64,
+    epoch: Option<u64>,
+    frame_count: u64,
+    history: VecDeque<GpuFrameTimings>,
+}
+
+/** Times regions of command buffers on the GPU with timestamp queries, one query pool per frame in flight.
+Results are read back without blocking when the same frame in flight is begun again, after its fence has been waited on,
+so timings lag [`MAX_FRAMES_IN_FLIGHT`] frames behind.
+Does nothing if the queue family does not support timestamps.
+May outlive the [`BaseApp`](crate::BaseApp) it was created from, as it keeps the device alive.
+# Example:
+```ignore
+profiler.begin_frame(cmd, frame);
+{
+    let _frame = profiler.profile_scope(cmd, "frame");
+    let _opaque = profiler.profile_scope(cmd, "opaque pass");
+    // Draw
+}
+if let Some(timings) = profiler.latest_frame() { /* ... */ }
+``` */
+pub struct GpuProfiler {
+    state: Rc<RefCell<ProfilerState>>,
+    enabled: bool,
+}
+
+impl GpuProfiler {
+    /// Creates a profiler for command buffers submitted to queues of `queue_family_index`, timing up to `max_scopes` scopes per frame
+    pub fn new(
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        queue_family_index: u32,
+        max_scopes: u32,
+    ) -> GpuProfiler {
+        let limits = unsafe { instance.get_physical_device_properties(*physical_device) }.limits;
+        let timestamp_valid_bits =
+            unsafe { instance.get_physical_device_queue_family_properties(*physical_device) }
+                [queue_family_index as usize]
+                .timestamp_valid_bits;
+        let enabled = timestamp_valid_bits > 0 && max_scopes > 0;
+
+        let frames = if enabled {
+            (0..MAX_FRAMES_IN_FLIGHT)
+                .map(|_| {
+                    let pool_info = vk::QueryPoolCreateInfo::builder()
+                        .query_type(vk::QueryType::TIMESTAMP)
+                        .query_count(max_scopes * 2);
+                    FrameQueries {
+                        query_pool: unsafe { logical_device.create_query_pool(&pool_info, None) }
+                            .expect("Could not create timestamp query pool!"),
+                        scopes: Vec::new(),
+                        frame_number: 0,
+                    }
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        GpuProfiler {
+            state: Rc::new(RefCell::new(ProfilerState {
+                logical_device: Arc::clone(logical_device),
+                frames,
+                current_frame: None,
+                open_scopes: Vec::new(),
+                max_scopes,
+                timestamp_period: limits.timestamp_period as f64,
+                timestamp_mask: if timestamp_valid_bits >= 64 {
+                    u64::MAX
+                } else {
+                    (1 << timestamp_valid_bits) - 1
+                },
+                epoch: None,
+                frame_count: 0,
+                history: VecDeque::with_capacity(PROFILER_HISTORY_LENGTH),
+            })),
+            enabled,
+        }
+    }
+
+    /// Whether timestamps are supported, and so whether anything is recorded
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /** Reads back the timings last recorded for `frame`, then resets its queries for recording.
+    # Safety
+    `command_buffer` must be recording outside of a render pass, and all earlier submissions of `frame` must have completed. */
+    pub unsafe fn begin_frame(&self, command_buffer: vk::CommandBuffer, frame: usize) {
+        if !self.enabled {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        state.read_back(frame);
+        let device = Arc::clone(&state.logical_device);
+        let max_scopes = state.max_scopes;
+        let frame_count = state.frame_count;
+        let frame_queries = &mut state.frames[frame];
+        device.cmd_reset_query_pool(command_buffer, frame_queries.query_pool, 0, max_scopes * 2);
+        frame_queries.scopes.clear();
+        frame_queries.frame_number = frame_count;
+        state.frame_count += 1;
+        state.current_frame = Some(frame);
+        state.open_scopes.clear();
+    }
+
+    /** Starts a named scope, ended when the returned guard is dropped. Scopes begun while another is open are nested in it.
+    Scopes past the per-frame limit are not timed.
+    # Safety
+    `command_buffer` must be the one passed to [`GpuProfiler::begin_frame`], and must still be recording when the guard is dropped. */
+    pub unsafe fn profile_scope(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        name: &str,
+    ) -> ProfileScope {
+        let scope = if self.enabled {
+            self.state.borrow_mut().begin_scope(command_buffer, name)
+        } else {
+            None
+        };
+        ProfileScope {
+            state: Rc::clone(&self.state),
+            command_buffer,
+            scope,
+        }
+    }
+
+    /// Most recent frame read back, if any
+    pub fn latest_frame(&self) -> Option<GpuFrameTimings> {
+        self.state.borrow().history.back().cloned()
+    }
+
+    /// Up to [`PROFILER_HISTORY_LENGTH`] frames read back, oldest first
+    pub fn history(&self) -> Vec<GpuFrameTimings> {
+        self.state.borrow().history.iter().cloned().collect()
+    }
+
+    /// The read back history in the Chrome trace event format, see [`chrome_trace_json`]
+    pub fn chrome_trace(&self) -> String {
+        chrome_trace_json(self.state.borrow().history.iter())
+    }
+}
+
+impl Drop for GpuProfiler {
+    fn drop(&mut self) {
+        let state = self.state.borrow();
+        for frame in state.frames.iter() {
+            unsafe {
+                state
+                    .logical_device
+                    .destroy_query_pool(frame.query_pool, None)
+            };
+        }
+    }
+}
+
+impl ProfilerState {
+    unsafe fn begin_scope(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        name: &str,
+    ) -> Option<usize> {
+        let frame = self.current_frame?;
+        let parent = self.open_scopes.last().copied();
+        let frame_queries = &mut self.frames[frame];
+        let index = frame_queries.scopes.len();
+        if index as u32 >= self.max_scopes {
+            return None;
+        }
+        frame_queries.scopes.push(ScopeRecord {
+            name: name.to_owned(),
+            parent,
+        });
+        self.logical_device.cmd_write_timestamp(
+            command_buffer,
+            vk::PipelineStageFlags::TOP_OF_PIPE,
+            frame_queries.query_pool,
+            index as u32 * 2,
+        );
+        self.open_scopes.push(index);
+        Some(index)
+    }
+
+    unsafe fn end_scope(&mut self, command_buffer: vk::CommandBuffer, index: usize) {
+        let Some(frame) = self.current_frame else {
+            return;
+        };
+        self.logical_device.cmd_write_timestamp(
+            command_buffer,
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            self.frames[frame].query_pool,
+            index as u32 * 2 + 1,
+        );
+        if let Some(position) = self.open_scopes.iter().rposition(|&open| open == index) {
+            self.open_scopes.remove(position);
+        }
+    }
+
+    // Builds the scope tree of the frame's last recording. Skipped if its results are not available,
+    // which is also the case if a scope was left open
+    fn read_back(&mut self, frame: usize) {
+        let frame_queries = &self.frames[frame];
+        if frame_queries.scopes.is_empty() {
+            return;
+        }
+        let mut timestamps = vec![0u64; frame_queries.scopes.len() * 2];
+        let result = unsafe {
+            self.logical_device.get_query_pool_results(
+                frame_queries.query_pool,
+                0,
+                timestamps.len() as u32,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+        if result.is_err() {
+            return;
+        }
+
+        let mask = self.timestamp_mask;
+        let epoch = *self.epoch.get_or_insert(timestamps[0] & mask);
+        let to_ns = |ticks: u64| (ticks & mask).wrapping_sub(epoch) as f64 * self.timestamp_period;
+
+        // Children always come after their parent, so scopes are placed in reverse
+        let mut scopes: Vec<Option<GpuScope>> = frame_queries
+            .scopes
+            .iter()
+            .enumerate()
+            .map(|(i, record)| {
+                let start_ns = to_ns(timestamps[i * 2]);
+                let end_ns = to_ns(timestamps[i * 2 + 1]);
+                Some(GpuScope {
+                    name: record.name.clone(),
+                    start_ns,
+                    duration_ns: (end_ns - start_ns).max(0.0),
+                    children: Vec::new(),
+                })
+            })
+            .collect();
+        let mut roots = Vec::new();
+        for (i, record) in frame_queries.scopes.iter().enumerate().rev() {
+            let scope = scopes[i].take().unwrap();
+            match record.parent {
+                Some(parent) => scopes[parent].as_mut().unwrap().children.insert(0, scope),
+                None => roots.insert(0, scope),
+            }
+        }
+
+        if self.history.len() == PROFILER_HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(GpuFrameTimings {
+            frame_number: frame_queries.frame_number,
+            scopes: roots,
+        });
+        self.frames[frame].scopes.clear();
+    }
+}
+
+/// Ends its scope when dropped, see [`GpuProfiler::profile_scope`]
+pub struct ProfileScope {
+    state: Rc<RefCell<ProfilerState>>,
+    command_buffer: vk::CommandBuffer,
+    scope: Option<usize>,
+}
+
+impl Drop for ProfileScope {
+    fn drop(&mut self) {
+        if let Some(index) = self.scope {
+            unsafe {
+                self.state
+                    .borrow_mut()
+                    .end_scope(self.command_buffer, index)
+            };
+        }
+    }
+}
+
+/** Formats frame timings as Chrome trace event JSON, viewable in `chrome://tracing` or Perfetto.
+Each scope becomes a complete event, with nesting shown by overlapping time ranges. */
+pub fn chrome_trace_json<'a>(frames: impl IntoIterator<Item = &'a GpuFrameTimings>) -> String {
+    fn write_scope(json: &mut String, scope: &GpuScope, frame_number: u64) {
+        if !json.ends_with('[') {
+            json.push(',');
+        }
+        write!(
+            json,
+            r#"{{"name":"{}","cat":"gpu","ph":"X","ts":{:.3},"dur":{:.3},"pid":0,"tid":0,"args":{{"frame":{}}}}}"#,
+            escape_json(&scope.name),
+            scope.start_ns / 1000.0,
+            scope.duration_ns / 1000.0,
+            frame_number
+        )
+        .unwrap();
+        for child in scope.children.iter() {
+            write_scope(json, child, frame_number);
+        }
+    }
+
+    let mut json = String::from(r#"{"displayTimeUnit":"ms","traceEvents":["#);
+    for frame in frames {
+        for scope in frame.scopes.iter() {
+            write_scope(&mut json, scope, frame.frame_number);
+        }
+    }
+    json.push_str("]}");
+    json
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(name: &str, start_ns: f64, duration_ns: f64, children: Vec<GpuScope>) -> GpuScope {
+        GpuScope {
+            name: name.to_owned(),
+            start_ns,
+            duration_ns,
+            children,
+        }
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(escape_json("plain"), "plain");
+        assert_eq!(escape_json(r#"say "hi""#), r#"say \"hi\""#);
+        assert_eq!(escape_json(r"C:\path"), r"C:\\path");
+        assert_eq!(escape_json("a\nb\tc\r"), r"a\nb\tc\r");
+        assert_eq!(escape_json("\u{1}bell\u{7}"), r"\u0001bell\u0007");
+        assert_eq!(escape_json("ünï"), "ünï");
+    }
+
+    #[test]
+    fn empty_trace() {
+        assert_eq!(
+            chrome_trace_json([]),
+            r#"{"displayTimeUnit":"ms","traceEvents":[]}"#
+        );
+    }
+
+    #[test]
+    fn trace_lists_nested_scopes_in_microseconds() {
+        let frames = [
+            GpuFrameTimings {
+                frame_number: 3,
+                scopes: vec![scope(
+                    "frame",
+                    0.0,
+                    2000.0,
+                    vec![scope("shadow \"pass\"", 500.0, 1000.0, Vec::new())],
+                )],
+            },
+            GpuFrameTimings {
+                frame_number: 4,
+                scopes: vec![scope("frame", 16_000_000.0, 1500.0, Vec::new())],
+            },
+        ];
+        let expected = concat!(
+            r#"{"displayTimeUnit":"ms","traceEvents":["#,
+            r#"{"name":"frame","cat":"gpu","ph":"X","ts":0.000,"dur":2.000,"pid":0,"tid":0,"args":{"frame":3}},"#,
+            r#"{"name":"shadow \"pass\"","cat":"gpu","ph":"X","ts":0.500,"dur":1.000,"pid":0,"tid":0,"args":{"frame":3}},"#,
+            r#"{"name":"frame","cat":"gpu","ph":"X","ts":16000.000,"dur":1.500,"pid":0,"tid":0,"args":{"frame":4}}"#,
+            "]}"
+        );
+        assert_eq!(chrome_trace_json(&frames), expected);
+    }
+}
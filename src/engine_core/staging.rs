@@ -0,0 +1,234 @@
+use super::{
+    cmd_image_barrier, create_staging_buffer, ImageTransition, LogicalDevice, ManagedBuffer,
+};
+use ash::{vk, Instance};
+use std::ffi::c_void;
+use std::sync::Arc;
+
+// Partitions start on this alignment, which satisfies any buffer copy offset requirement
+const PARTITION_ALIGNMENT: vk::DeviceSize = 256;
+
+/** Persistently mapped staging buffer, partitioned into one region per frame in flight.
+Sub-allocations are valid until the same frame index comes around again, so copies from them must be recorded
+into the command buffer of the frame they were allocated in.
+
+[`BaseApp`](crate::BaseApp) owns one, and starts its frames in [`BaseApp::wait_for_in_flight_fence`](crate::BaseApp::wait_for_in_flight_fence).
+# Example:
+```ignore
+// Inside the closure given to record_command_buffer, before drawing_commands
+let command_buffer = app.command_buffers[current_frame];
+app.staging_ring.cmd_copy_to_buffer(command_buffer, &particles, *particle_buffer, 0);
+``` */
+pub struct StagingRing {
+    buffer: ManagedBuffer,
+    partition_size: vk::DeviceSize,
+    frame_index: usize,
+    head: vk::DeviceSize,
+}
+
+impl StagingRing {
+    /// `size` is the total size of the ring in bytes, split evenly between `frames_in_flight` partitions
+    pub fn new(
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        size: vk::DeviceSize,
+        frames_in_flight: usize,
+    ) -> StagingRing {
+        let partition_size =
+            (size / frames_in_flight as vk::DeviceSize) / PARTITION_ALIGNMENT * PARTITION_ALIGNMENT;
+        assert!(
+            partition_size > 0,
+            "Staging ring of {} bytes is too small for {} frames in flight",
+            size,
+            frames_in_flight
+        );
+        let mut buffer = create_staging_buffer(
+            instance,
+            physical_device,
+            logical_device,
+            partition_size * frames_in_flight as vk::DeviceSize,
+        );
+        buffer.map_buffer_memory();
+        StagingRing {
+            buffer,
+            partition_size,
+            frame_index: 0,
+            head: 0,
+        }
+    }
+
+    /// Staging buffer that sub-allocation offsets refer to
+    pub fn buffer(&self) -> vk::Buffer {
+        *self.buffer
+    }
+
+    /// Bytes available to each frame
+    pub fn partition_size(&self) -> vk::DeviceSize {
+        self.partition_size
+    }
+
+    /// Bytes still free in the current frame's partition
+    pub fn remaining(&self) -> vk::DeviceSize {
+        self.partition_size - self.head
+    }
+
+    /// Makes `frame_index` the current frame and frees its partition.
+    /// Only call once the GPU has finished the commands previously recorded for that frame
+    pub fn begin_frame(&mut self, frame_index: usize) {
+        self.frame_index = frame_index;
+        self.head = 0;
+    }
+
+    /** Sub-allocates `size` bytes from the current frame's partition, aligned to `alignment` (a power of two).
+    Returns the mapped pointer and the offset into [`StagingRing::buffer`], or `None` if the partition is full. */
+    pub fn allocate(
+        &mut self,
+        size: vk::DeviceSize,
+        alignment: vk::DeviceSize,
+    ) -> Option<(*mut c_void, vk::DeviceSize)> {
+        let start = (self.head + alignment - 1) & !(alignment - 1);
+        if start + size > self.partition_size {
+            return None;
+        }
+        self.head = start + size;
+        let offset = self.frame_index as vk::DeviceSize * self.partition_size + start;
+        let ptr = self.buffer.mapped_ptr(offset as usize, size as usize);
+        Some((ptr, offset))
+    }
+
+    /// Copies `data` into a new sub-allocation, returning its offset into [`StagingRing::buffer`]
+    pub fn write<T>(&mut self, data: &[T]) -> Option<vk::DeviceSize> {
+        let size = std::mem::size_of_val(data) as vk::DeviceSize;
+        let alignment = std::mem::align_of::<T>().max(4) as vk::DeviceSize;
+        let (ptr, offset) = self.allocate(size, alignment)?;
+        unsafe { super::write_vec_to_buffer(ptr, data) };
+        Some(offset)
+    }
+
+    /** Stages `data` and records a copy of it into `dst_buffer` at `dst_offset`, followed by a barrier making the write
+    visible to vertex input, index and shader reads. Returns `false` without recording anything if the partition is full.
+    # Safety
+    `command_buffer` must be the current frame's command buffer, recording outside a render pass. */
+    pub unsafe fn cmd_copy_to_buffer<T>(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        data: &[T],
+        dst_buffer: vk::Buffer,
+        dst_offset: vk::DeviceSize,
+    ) -> bool {
+        let size = std::mem::size_of_val(data) as vk::DeviceSize;
+        let src_offset = match self.write(data) {
+            Some(offset) => offset,
+            None => return false,
+        };
+        let logical_device = &self.buffer.logical_device;
+        let copy_region = vk::BufferCopy::builder()
+            .src_offset(src_offset)
+            .dst_offset(dst_offset)
+            .size(size);
+        logical_device.cmd_copy_buffer(command_buffer, *self.buffer, dst_buffer, &[*copy_region]);
+        let barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(
+                vk::AccessFlags::VERTEX_ATTRIBUTE_READ
+                    | vk::AccessFlags::INDEX_READ
+                    | vk::AccessFlags::UNIFORM_READ
+                    | vk::AccessFlags::SHADER_READ,
+            )
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(dst_buffer)
+            .offset(dst_offset)
+            .size(size);
+        logical_device.cmd_pipeline_barrier(
+            command_buffer,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::VERTEX_INPUT
+                | vk::PipelineStageFlags::VERTEX_SHADER
+                | vk::PipelineStageFlags::FRAGMENT_SHADER
+                | vk::PipelineStageFlags::COMPUTE_SHADER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[*barrier],
+            &[],
+        );
+        true
+    }
+
+    /** Stages tightly packed `pixels` and records a copy of them into the first mip level and layer of a 2D color image.
+    The image is transitioned from `old_layout` to `TRANSFER_DST_OPTIMAL` and left in `SHADER_READ_ONLY_OPTIMAL`.
+    Returns `false` without recording anything if the partition is full.
+    # Safety
+    `command_buffer` must be the current frame's command buffer, recording outside a render pass. */
+    pub unsafe fn cmd_copy_to_image<T>(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        pixels: &[T],
+        image: vk::Image,
+        dimensions: (u32, u32),
+        old_layout: vk::ImageLayout,
+    ) -> bool {
+        let src_offset = match self.write(pixels) {
+            Some(offset) => offset,
+            None => return false,
+        };
+        let logical_device = &self.buffer.logical_device;
+        let aspect_mask = vk::ImageAspectFlags::COLOR;
+        cmd_image_barrier(
+            logical_device,
+            command_buffer,
+            image,
+            aspect_mask,
+            &ImageTransition {
+                old_layout,
+                new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                src_stage: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                src_access: vk::AccessFlags::empty(),
+                dst_stage: vk::PipelineStageFlags::TRANSFER,
+                dst_access: vk::AccessFlags::TRANSFER_WRITE,
+            },
+            false,
+        );
+        let region = vk::BufferImageCopy::builder()
+            .buffer_offset(src_offset)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(
+                *vk::ImageSubresourceLayers::builder()
+                    .aspect_mask(aspect_mask)
+                    .mip_level(0)
+                    .base_array_layer(0)
+                    .layer_count(1),
+            )
+            .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+            .image_extent(vk::Extent3D {
+                width: dimensions.0,
+                height: dimensions.1,
+                depth: 1,
+            });
+        logical_device.cmd_copy_buffer_to_image(
+            command_buffer,
+            *self.buffer,
+            image,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            &[*region],
+        );
+        cmd_image_barrier(
+            logical_device,
+            command_buffer,
+            image,
+            aspect_mask,
+            &ImageTransition {
+                old_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                new_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                src_stage: vk::PipelineStageFlags::TRANSFER,
+                src_access: vk::AccessFlags::TRANSFER_WRITE,
+                dst_stage: vk::PipelineStageFlags::FRAGMENT_SHADER,
+                dst_access: vk::AccessFlags::SHADER_READ,
+            },
+            false,
+        );
+        true
+    }
+}
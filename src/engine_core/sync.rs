@@ -0,0 +1,105 @@
+use ash::{vk, Device};
+
+/// Layout transition and the stages/accesses it synchronizes, for [`cmd_image_barrier`]
+#[derive(Clone, Copy)]
+pub struct ImageTransition {
+    pub old_layout: vk::ImageLayout,
+    pub new_layout: vk::ImageLayout,
+    pub src_stage: vk::PipelineStageFlags,
+    pub src_access: vk::AccessFlags,
+    pub dst_stage: vk::PipelineStageFlags,
+    pub dst_access: vk::AccessFlags,
+}
+
+/** Records a pipeline barrier transitioning the whole of `image`.
+Uses `vkCmdPipelineBarrier2` if `synchronization2` is enabled on the device, otherwise the Vulkan 1.0 barrier.
+# Safety
+`command_buffer` must be recording, and `image` must be a valid image with the given aspect. */
+pub unsafe fn cmd_image_barrier(
+    logical_device: &Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    aspect_mask: vk::ImageAspectFlags,
+    transition: &ImageTransition,
+    synchronization2: bool,
+) {
+    let subresource_range = *vk::ImageSubresourceRange::builder()
+        .aspect_mask(aspect_mask)
+        .base_mip_level(0)
+        .level_count(vk::REMAINING_MIP_LEVELS)
+        .base_array_layer(0)
+        .layer_count(vk::REMAINING_ARRAY_LAYERS);
+    if synchronization2 {
+        // The legacy stage and access bits have the same values in the 64-bit flags
+        let barriers = [*vk::ImageMemoryBarrier2::builder()
+            .old_layout(transition.old_layout)
+            .new_layout(transition.new_layout)
+            .src_stage_mask(vk::PipelineStageFlags2::from_raw(
+                transition.src_stage.as_raw() as u64,
+            ))
+            .src_access_mask(vk::AccessFlags2::from_raw(
+                transition.src_access.as_raw() as u64
+            ))
+            .dst_stage_mask(vk::PipelineStageFlags2::from_raw(
+                transition.dst_stage.as_raw() as u64,
+            ))
+            .dst_access_mask(vk::AccessFlags2::from_raw(
+                transition.dst_access.as_raw() as u64
+            ))
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)];
+        let dependency_info = vk::DependencyInfo::builder().image_memory_barriers(&barriers);
+        logical_device.cmd_pipeline_barrier2(command_buffer, &dependency_info);
+    } else {
+        let barrier = vk::ImageMemoryBarrier::builder()
+            .old_layout(transition.old_layout)
+            .new_layout(transition.new_layout)
+            .src_access_mask(transition.src_access)
+            .dst_access_mask(transition.dst_access)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range);
+        logical_device.cmd_pipeline_barrier(
+            command_buffer,
+            transition.src_stage,
+            transition.dst_stage,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[*barrier],
+        );
+    }
+}
+
+/// Creates a timeline semaphore starting at `initial_value`. Requires the Vulkan 1.2 `timeline_semaphore` feature
+pub fn create_timeline_semaphore(logical_device: &Device, initial_value: u64) -> vk::Semaphore {
+    let mut type_info = vk::SemaphoreTypeCreateInfo::builder()
+        .semaphore_type(vk::SemaphoreType::TIMELINE)
+        .initial_value(initial_value);
+    let semaphore_info = vk::SemaphoreCreateInfo::builder().push_next(&mut type_info);
+    unsafe { logical_device.create_semaphore(&semaphore_info, None) }
+        .expect("Could not create timeline semaphore!")
+}
+
+/// Blocks until the timeline semaphore reaches `value`, or until `timeout` nanoseconds have passed
+pub fn wait_timeline_semaphore(
+    logical_device: &Device,
+    semaphore: vk::Semaphore,
+    value: u64,
+    timeout: u64,
+) -> Result<(), vk::Result> {
+    let semaphores = [semaphore];
+    let values = [value];
+    let wait_info = vk::SemaphoreWaitInfo::builder()
+        .semaphores(&semaphores)
+        .values(&values);
+    unsafe { logical_device.wait_semaphores(&wait_info, timeout) }
+}
+
+/// Current counter value of a timeline semaphore
+pub fn timeline_semaphore_value(logical_device: &Device, semaphore: vk::Semaphore) -> u64 {
+    unsafe { logical_device.get_semaphore_counter_value(semaphore) }.unwrap()
+}
@@ -1,17 +1,40 @@
 use std::ffi::c_void;
-use std::rc::Rc;
+use std::sync::Arc;
+
+use super::{write_vec_to_buffer, LogicalDevice};
 
 use ash::{vk, Device, Instance};
 
 pub struct ManagedImage {
-    pub logical_device: Rc<Device>,
+    pub logical_device: Arc<LogicalDevice>,
     pub image: vk::Image,
     pub image_view: vk::ImageView,
     pub image_memory: Option<vk::DeviceMemory>,
-    pub memory_ptr: Option<*mut c_void>,
+    // Pointer to the mapped memory and the number of bytes behind it, see ManagedImage::write
+    memory_ptr: Option<(*mut c_void, usize)>,
 }
+// The mapped pointer only refers to memory owned by the image, and Vulkan only requires external
+// synchronization for mapping, unmapping and destroying, which all take `&mut self` or ownership
+unsafe impl Send for ManagedImage {}
+unsafe impl Sync for ManagedImage {}
 impl ManagedImage {
-    /// Maps whole of the allocated image memory, returns pointer to the data.
+    /// Takes ownership of `image`, its `image_view` and `image_memory` bound to it, if any. The memory starts out unmapped
+    pub fn new(
+        logical_device: &Arc<LogicalDevice>,
+        image: vk::Image,
+        image_view: vk::ImageView,
+        image_memory: Option<vk::DeviceMemory>,
+    ) -> ManagedImage {
+        ManagedImage {
+            logical_device: Arc::clone(logical_device),
+            image,
+            image_view,
+            image_memory,
+            memory_ptr: None,
+        }
+    }
+
+    /// Maps whole of the allocated image memory, to be written with [`ManagedImage::write`].
     /// Invalid if memory is not visible to the host device (unsure what happens if not).
     /// Panics if there's no memory to map
     pub fn map_image_memory(&mut self) {
@@ -19,7 +42,12 @@ impl ManagedImage {
             if self.memory_ptr.is_some() {
                 panic!("Attempt to re-map image memory!")
             }
-            self.memory_ptr = Some(map_image_memory(&self.logical_device, memory))
+            let size = unsafe {
+                self.logical_device
+                    .get_image_memory_requirements(self.image)
+            }
+            .size as usize;
+            self.memory_ptr = Some((map_image_memory(&self.logical_device, memory), size))
         } else {
             panic!("Attempt to map unallocated/unbound image memory!");
         }
@@ -27,12 +55,33 @@ impl ManagedImage {
     /// Unmaps image memory (unsure what happens if it isn't mapped. no-op?)
     /// Panics if there's no memory to unmap (does it matter?)
     pub fn unmap_image_memory(&mut self) {
-        if self.memory_ptr.is_some() {
+        if self.memory_ptr.take().is_some() {
             unsafe { self.logical_device.unmap_memory(self.image_memory.unwrap()) };
         } else {
             panic!("Attempt to unmap unmapped image memory!");
         }
     }
+
+    pub fn is_mapped(&self) -> bool {
+        self.memory_ptr.is_some()
+    }
+
+    /// Copies `data` into the mapped memory, starting `offset` bytes in. Only meaningful for linearly tiled images.
+    /// Panics if the memory is not mapped or `data` does not fit
+    pub fn write<T>(&mut self, offset: usize, data: &[T]) {
+        let (ptr, mapped_size) = self.memory_ptr.expect("Image memory is not mapped!");
+        let size = std::mem::size_of_val(data);
+        assert!(
+            offset
+                .checked_add(size)
+                .is_some_and(|end| end <= mapped_size),
+            "Writing {} bytes at offset {} overruns the {} mapped bytes of the image!",
+            size,
+            offset,
+            mapped_size
+        );
+        unsafe { write_vec_to_buffer((ptr as *mut u8).add(offset) as *mut c_void, data) };
+    }
 }
 
 impl Drop for ManagedImage {
@@ -124,9 +173,7 @@ pub fn create_image(
         .sharing_mode(vk::SharingMode::EXCLUSIVE)
         .samples(vk::SampleCountFlags::TYPE_1);
 
-    let image = unsafe { logical_device.create_image(&img_create_info, None) }.unwrap();
-
-    image
+    unsafe { logical_device.create_image(&img_create_info, None) }.unwrap()
 }
 
 pub fn create_image_view(
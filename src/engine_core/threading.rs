@@ -0,0 +1,131 @@
+use super::{LogicalDevice, QueueFamilyIndices};
+use ash::vk;
+use std::sync::Arc;
+
+/** Handles to the instance and device that can be cloned and sent to other threads, for loading resources
+and recording commands off the render thread. Get one from [`BaseApp::device_context`](crate::BaseApp::device_context).
+Queues are left out on purpose: submissions go through the render thread, see [`BaseApp::submit_command_buffers`](crate::BaseApp::submit_command_buffers).
+The instance is reached through [`LogicalDevice::instance`]. Keeps the device alive, even past the [`BaseApp`](crate::BaseApp) it came from. */
+#[derive(Clone)]
+pub struct DeviceContext {
+    pub physical_device: vk::PhysicalDevice,
+    pub logical_device: Arc<LogicalDevice>,
+    pub queue_family_indices: QueueFamilyIndices,
+}
+
+impl DeviceContext {
+    /// Creates a command pool for the graphics queue family, to be owned by the calling thread
+    pub fn create_command_pool(&self) -> ThreadCommandPool {
+        ThreadCommandPool::new(
+            &self.logical_device,
+            self.queue_family_indices.graphics_queue,
+        )
+    }
+}
+
+/** A command pool owned by a single thread. Vulkan requires command pools, and every command buffer allocated from them,
+to be externally synchronized, so each thread recording commands needs its own pool.
+The pool can be moved to a worker thread, which records the buffers and sends their handles back for submission.
+```ignore
+let context = app.device_context();
+let worker = std::thread::spawn(move || {
+    let mut pool = context.create_command_pool();
+    let cmd = pool.allocate_primary(1)[0];
+    // Record cmd...
+    (pool, cmd)
+});
+let (pool, cmd) = worker.join().unwrap();
+let frame = unsafe { app.submit_command_buffers(&[cmd]) };
+// Reset or drop the pool once app.completed_frame_number() >= frame
+```
+Keeps the device alive, so it may be dropped after the [`BaseApp`](crate::BaseApp) it was created from. */
+pub struct ThreadCommandPool {
+    logical_device: Arc<LogicalDevice>,
+    command_pool: vk::CommandPool,
+    queue_family_index: u32,
+}
+
+impl ThreadCommandPool {
+    /// Creates a pool for `queue_family_index`. Command buffers are meant to be recorded once and freed by resetting the whole pool
+    pub fn new(logical_device: &Arc<LogicalDevice>, queue_family_index: u32) -> ThreadCommandPool {
+        let command_pool_info = vk::CommandPoolCreateInfo::builder()
+            .queue_family_index(queue_family_index)
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT);
+        let command_pool = unsafe { logical_device.create_command_pool(&command_pool_info, None) }
+            .expect("Could not create thread command pool!");
+
+        ThreadCommandPool {
+            logical_device: Arc::clone(logical_device),
+            command_pool,
+            queue_family_index,
+        }
+    }
+
+    pub fn handle(&self) -> vk::CommandPool {
+        self.command_pool
+    }
+
+    /// Queue family that command buffers from this pool may be submitted to
+    pub fn queue_family_index(&self) -> u32 {
+        self.queue_family_index
+    }
+
+    pub fn allocate(
+        &mut self,
+        level: vk::CommandBufferLevel,
+        amount: u32,
+    ) -> Vec<vk::CommandBuffer> {
+        let allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_pool(self.command_pool)
+            .level(level)
+            .command_buffer_count(amount);
+        unsafe { self.logical_device.allocate_command_buffers(&allocate_info) }
+            .expect("Could not allocate command buffers!")
+    }
+
+    /// Allocates command buffers that are submitted to a queue
+    pub fn allocate_primary(&mut self, amount: u32) -> Vec<vk::CommandBuffer> {
+        self.allocate(vk::CommandBufferLevel::PRIMARY, amount)
+    }
+
+    /// Allocates command buffers that are executed from a primary command buffer
+    pub fn allocate_secondary(&mut self, amount: u32) -> Vec<vk::CommandBuffer> {
+        self.allocate(vk::CommandBufferLevel::SECONDARY, amount)
+    }
+
+    /** Returns every command buffer of the pool to the initial state, to be recorded again.
+    # Safety
+    None of the pool's command buffers may be pending execution. */
+    pub unsafe fn reset(&mut self) {
+        self.logical_device
+            .reset_command_pool(self.command_pool, vk::CommandPoolResetFlags::empty())
+            .expect("Could not reset thread command pool!");
+    }
+
+    /** Frees command buffers allocated from this pool.
+    # Safety
+    None of `command_buffers` may be pending execution. */
+    pub unsafe fn free(&mut self, command_buffers: &[vk::CommandBuffer]) {
+        self.logical_device
+            .free_command_buffers(self.command_pool, command_buffers);
+    }
+}
+
+impl Drop for ThreadCommandPool {
+    fn drop(&mut self) {
+        unsafe {
+            self.logical_device
+                .destroy_command_pool(self.command_pool, None)
+        };
+    }
+}
+
+// Resources and device handles are shared with loading and recording threads
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<DeviceContext>();
+    assert_send_sync::<super::ManagedBuffer>();
+    assert_send_sync::<super::ManagedImage>();
+    const fn assert_send<T: Send>() {}
+    assert_send::<ThreadCommandPool>();
+};
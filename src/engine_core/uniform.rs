@@ -0,0 +1,167 @@
+use super::{LogicalDevice, ManagedBuffer, MAX_FRAMES_IN_FLIGHT};
+use ash::{vk, Instance};
+use glam::{
+    IVec2, IVec3, IVec4, Mat2, Mat3A, Mat4, Quat, UVec2, UVec3, UVec4, Vec2, Vec3, Vec3A, Vec4,
+};
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/** Types whose memory layout matches the std140 layout GLSL uses for uniform blocks.
+Implement with `#[derive(Std140)]`, which checks the layout at compile time:
+```
+use vk_engine::engine_core::Std140;
+#[repr(C)]
+#[derive(Clone, Copy, Std140)]
+struct Light {
+    position: glam::Vec3,
+    intensity: f32, // Packed into the end of the vec3, as in GLSL
+    color: glam::Vec4,
+}
+```
+Swapping `position` and `intensity` fails to compile, as GLSL would place `position` at offset 16:
+```compile_fail
+use vk_engine::engine_core::Std140;
+#[repr(C)]
+#[derive(Clone, Copy, Std140)]
+struct Light {
+    intensity: f32,
+    position: glam::Vec3,
+    color: glam::Vec4,
+}
+```
+`Mat3` and arrays of elements that aren't a multiple of 16 bytes have no matching Rust layout, use `Mat3A` or `[Vec4; N]` instead.
+# Safety
+`STD140_ALIGNMENT` must be the std140 base alignment of the type, and its size must match the std140 size. */
+pub unsafe trait Std140: Sized {
+    const STD140_ALIGNMENT: usize;
+}
+
+/** Types whose memory layout matches the std430 layout GLSL uses for storage buffers, see [`Std140`].
+Fields must also be where GLSL puts them when Rust aligns a type differently. `Mat2` is 8-byte aligned in std430,
+but may be 16-byte aligned in Rust, so this fails to compile instead of reading the matrix from the wrong offset:
+```compile_fail
+use vk_engine::engine_core::Std430;
+#[repr(C)]
+#[derive(Clone, Copy, Std430)]
+struct Transform2D {
+    scale: f32,
+    rotation: glam::Mat2,
+}
+```
+# Safety
+`STD430_ALIGNMENT` must be the std430 base alignment of the type, and its size must match the std430 size. */
+pub unsafe trait Std430: Sized {
+    const STD430_ALIGNMENT: usize;
+}
+
+/// Largest of `alignments` and `minimum`, for use by the derives in const context
+#[doc(hidden)]
+pub const fn max_alignment(alignments: &[usize], minimum: usize) -> usize {
+    let mut max = minimum;
+    let mut i = 0;
+    while i < alignments.len() {
+        if alignments[i] > max {
+            max = alignments[i];
+        }
+        i += 1;
+    }
+    max
+}
+
+/// `offset` rounded up to a multiple of `alignment`, for use by the derives in const context
+#[doc(hidden)]
+pub const fn align_offset(offset: usize, alignment: usize) -> usize {
+    offset.div_ceil(alignment) * alignment
+}
+
+macro_rules! impl_layout {
+    ($trait_name:ident, $alignment:ident, $($ty:ty => $align:expr),* $(,)?) => {
+        $(unsafe impl $trait_name for $ty {
+            const $alignment: usize = $align;
+        })*
+    };
+}
+
+impl_layout!(Std140, STD140_ALIGNMENT,
+    f32 => 4, i32 => 4, u32 => 4,
+    Vec2 => 8, IVec2 => 8, UVec2 => 8,
+    Vec3 => 16, IVec3 => 16, UVec3 => 16, Vec3A => 16,
+    Vec4 => 16, IVec4 => 16, UVec4 => 16, Quat => 16,
+    Mat3A => 16, Mat4 => 16,
+);
+
+// Mat2 only matches std430, where its columns aren't padded to 16 bytes
+impl_layout!(Std430, STD430_ALIGNMENT,
+    f32 => 4, i32 => 4, u32 => 4,
+    Vec2 => 8, IVec2 => 8, UVec2 => 8,
+    Vec3 => 16, IVec3 => 16, UVec3 => 16, Vec3A => 16,
+    Vec4 => 16, IVec4 => 16, UVec4 => 16, Quat => 16,
+    Mat2 => 8, Mat3A => 16, Mat4 => 16,
+);
+
+// Array elements are padded to 16 bytes in std140, which Rust arrays can't express for smaller elements
+unsafe impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const STD140_ALIGNMENT: usize = {
+        let alignment = max_alignment(&[T::STD140_ALIGNMENT], 16);
+        assert!(
+            std::mem::size_of::<T>().is_multiple_of(alignment),
+            "std140: array elements must be a multiple of 16 bytes, use e.g. [Vec4; N] instead"
+        );
+        alignment
+    };
+}
+
+unsafe impl<T: Std430, const N: usize> Std430 for [T; N] {
+    const STD430_ALIGNMENT: usize = {
+        assert!(
+            std::mem::size_of::<T>().is_multiple_of(T::STD430_ALIGNMENT),
+            "std430: array elements must be a multiple of their alignment, use e.g. [Vec4; N] instead of [Vec3; N]"
+        );
+        T::STD430_ALIGNMENT
+    };
+}
+
+/** A uniform buffer holding a `T` per frame in flight, persistently mapped.
+Writing a frame's value never touches data the GPU may still be reading.
+Must not be dropped while frames using it are in flight. */
+pub struct UniformBuffer<T> {
+    buffers: Vec<ManagedBuffer>,
+    _uniform_type: PhantomData<T>,
+}
+
+impl<T: Std140> UniformBuffer<T> {
+    pub fn new(
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+    ) -> UniformBuffer<T> {
+        UniformBuffer {
+            buffers: super::create_uniform_buffers(
+                instance,
+                physical_device,
+                logical_device,
+                std::mem::size_of::<T>() as vk::DeviceSize,
+                MAX_FRAMES_IN_FLIGHT,
+            ),
+            _uniform_type: PhantomData,
+        }
+    }
+
+    /// Buffer holding the value of `frame`
+    pub fn buffer(&self, frame: usize) -> vk::Buffer {
+        *self.buffers[frame]
+    }
+
+    /// For writing the buffer of `frame` to a `UNIFORM_BUFFER` descriptor
+    pub fn descriptor_buffer_info(&self, frame: usize) -> vk::DescriptorBufferInfo {
+        *vk::DescriptorBufferInfo::builder()
+            .buffer(*self.buffers[frame])
+            .offset(0)
+            .range(std::mem::size_of::<T>() as vk::DeviceSize)
+    }
+
+    /// Replaces the value of `frame`. Only call once the frame's in-flight fence has been waited on
+    pub fn write(&mut self, frame: usize, data: &T) {
+        self.buffers[frame].write_struct(0, data);
+    }
+}
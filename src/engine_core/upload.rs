@@ -0,0 +1,524 @@
+use super::{allocate_command_buffers, create_staging_buffer};
+use super::{LogicalDevice, ManagedBuffer, QueueFamilyIndices};
+use ash::{vk, Device, Instance};
+use std::sync::Arc;
+
+/** Stages of the graphics queue that use uploaded data. Frames only wait for uploads at these stages.
+Besides drawing, uploaded buffers may be read or written by compute shaders, or overwritten by later copies */
+const CONSUMING_STAGES: vk::PipelineStageFlags = vk::PipelineStageFlags::from_raw(
+    vk::PipelineStageFlags::DRAW_INDIRECT.as_raw()
+        | vk::PipelineStageFlags::VERTEX_INPUT.as_raw()
+        | vk::PipelineStageFlags::VERTEX_SHADER.as_raw()
+        | vk::PipelineStageFlags::FRAGMENT_SHADER.as_raw()
+        | vk::PipelineStageFlags::COMPUTE_SHADER.as_raw()
+        | vk::PipelineStageFlags::TRANSFER.as_raw(),
+);
+/// Accesses of [`CONSUMING_STAGES`] ordered after the upload, writes included so that overwriting uploaded data is ordered too
+const CONSUMING_ACCESS: vk::AccessFlags = vk::AccessFlags::from_raw(
+    vk::AccessFlags::MEMORY_READ.as_raw() | vk::AccessFlags::MEMORY_WRITE.as_raw(),
+);
+
+/// Handle to a batch of uploads submitted with [`UploadManager::submit`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadBatch {
+    id: u64,
+    /// Value the manager's timeline semaphore reaches once the copies of the batch have finished, if timeline semaphores are enabled.
+    /// With a dedicated transfer queue, the graphics queue still has to acquire the data, see [`UploadManager::cmd_acquire_finished`]
+    pub timeline_value: Option<u64>,
+}
+
+struct ImageUpload {
+    image: vk::Image,
+    aspect_mask: vk::ImageAspectFlags,
+}
+
+// Destination resources of a batch
+#[derive(Default)]
+struct Destinations {
+    buffers: Vec<(vk::Buffer, vk::DeviceSize, vk::DeviceSize)>,
+    images: Vec<ImageUpload>,
+}
+
+// Uploads recorded but not yet submitted
+struct Recording {
+    command_buffer: vk::CommandBuffer,
+    staging_buffers: Vec<ManagedBuffer>,
+    destinations: Destinations,
+}
+
+struct InFlight {
+    id: u64,
+    fence: vk::Fence,
+    command_buffer: vk::CommandBuffer,
+    _staging_buffers: Vec<ManagedBuffer>,
+    // Resources the graphics queue acquires once the transfer has finished, with a dedicated transfer queue
+    acquire: Option<Destinations>,
+}
+
+/** Batches uploads of buffer and image data to device local memory into a single submission.
+Uploads go through the dedicated transfer queue when the device has one, and through the graphics queue otherwise.
+Nothing submitted to the graphics queue waits for an upload, so rendering continues while a batch is in progress,
+and the uploaded resources may be used once [`UploadManager::is_complete`] returns `true` for their batch.
+
+With a dedicated transfer queue, ownership of the destination resources is released by the transfer queue,
+and acquired by the graphics queue with [`UploadManager::cmd_acquire_finished`] in the first frame recorded after the transfer finished,
+which [`BaseApp::record_command_buffer`](crate::BaseApp::record_command_buffer) does.
+Destination resources must use exclusive sharing mode, which is the case for all resources created by the engine.
+
+Staging memory is kept until the copies of a batch have finished, and is released by [`UploadManager::collect_finished`].
+# Example:
+```ignore
+app.uploads.upload_buffer(&vertices, *vertex_buffer, 0);
+app.uploads.upload_image(&pixels, texture.image, (width, height));
+let batch = app.uploads.submit();
+// Rendering continues while the upload is in progress
+if app.uploads.is_complete(batch) {
+    println!("Level loaded");
+}
+``` */
+pub struct UploadManager {
+    logical_device: Arc<LogicalDevice>,
+    instance: Instance,
+    physical_device: vk::PhysicalDevice,
+    transfer_queue: vk::Queue,
+    transfer_family: u32,
+    graphics_family: u32,
+    transfer_pool: vk::CommandPool,
+    timeline_semaphore: vk::Semaphore,
+    recording: Option<Recording>,
+    in_flight: Vec<InFlight>,
+    // Batches whose transfer has finished, waiting to be acquired by the graphics queue
+    pending_acquires: Vec<(u64, Destinations)>,
+    next_id: u64,
+}
+
+impl UploadManager {
+    /// `transfer_queue` should be the queue of `queue_family_indices.transfer_queue`, or `None` to upload through the graphics queue.
+    /// With `timeline_semaphores`, batches also signal a timeline semaphore (requires the Vulkan 1.2 feature)
+    pub fn new(
+        instance: &Instance,
+        physical_device: &vk::PhysicalDevice,
+        logical_device: &Arc<LogicalDevice>,
+        queue_family_indices: QueueFamilyIndices,
+        graphics_queue: vk::Queue,
+        transfer_queue: Option<vk::Queue>,
+        timeline_semaphores: bool,
+    ) -> UploadManager {
+        let graphics_family = queue_family_indices.graphics_queue;
+        let (transfer_queue, transfer_family) =
+            match (transfer_queue, queue_family_indices.transfer_queue) {
+                (Some(queue), Some(family)) => (queue, family),
+                _ => (graphics_queue, graphics_family),
+            };
+
+        let command_pool_info = vk::CommandPoolCreateInfo::builder()
+            .queue_family_index(transfer_family)
+            .flags(vk::CommandPoolCreateFlags::TRANSIENT);
+        let transfer_pool = unsafe { logical_device.create_command_pool(&command_pool_info, None) }
+            .expect("Could not create command pool!");
+        let timeline_semaphore = if timeline_semaphores {
+            super::create_timeline_semaphore(logical_device, 0)
+        } else {
+            vk::Semaphore::null()
+        };
+
+        UploadManager {
+            logical_device: Arc::clone(logical_device),
+            instance: instance.clone(),
+            physical_device: *physical_device,
+            transfer_queue,
+            transfer_family,
+            graphics_family,
+            transfer_pool,
+            timeline_semaphore,
+            recording: None,
+            in_flight: Vec::new(),
+            pending_acquires: Vec::new(),
+            next_id: 1,
+        }
+    }
+
+    /// Whether uploads go through a dedicated transfer queue
+    pub fn uses_transfer_queue(&self) -> bool {
+        self.transfer_family != self.graphics_family
+    }
+
+    /// Timeline semaphore signaled with [`UploadBatch::timeline_value`] as the copies of batches finish, if enabled
+    pub fn timeline_semaphore(&self) -> Option<vk::Semaphore> {
+        if self.timeline_semaphore == vk::Semaphore::null() {
+            None
+        } else {
+            Some(self.timeline_semaphore)
+        }
+    }
+
+    fn recording(&mut self) -> &mut Recording {
+        if self.recording.is_none() {
+            let command_buffer =
+                allocate_command_buffers(&self.logical_device, self.transfer_pool, 1)[0];
+            let begin_info = vk::CommandBufferBeginInfo::builder()
+                .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+            unsafe {
+                self.logical_device
+                    .begin_command_buffer(command_buffer, &begin_info)
+            }
+            .unwrap();
+            self.recording = Some(Recording {
+                command_buffer,
+                staging_buffers: Vec::new(),
+                destinations: Destinations::default(),
+            });
+        }
+        self.recording.as_mut().unwrap()
+    }
+
+    fn staging_buffer_with<T>(&self, data: &[T]) -> ManagedBuffer {
+        let mut staging_buffer = create_staging_buffer(
+            &self.instance,
+            &self.physical_device,
+            &self.logical_device,
+            std::mem::size_of_val(data) as vk::DeviceSize,
+        );
+        staging_buffer.map_buffer_memory();
+        staging_buffer.write(0, data);
+        staging_buffer
+    }
+
+    /// Records a copy of `data` into `dst_buffer` at `dst_offset`. The data is copied to staging memory immediately
+    pub fn upload_buffer<T>(
+        &mut self,
+        data: &[T],
+        dst_buffer: vk::Buffer,
+        dst_offset: vk::DeviceSize,
+    ) {
+        let size = std::mem::size_of_val(data) as vk::DeviceSize;
+        if size == 0 {
+            return;
+        }
+        let staging_buffer = self.staging_buffer_with(data);
+        let logical_device = Arc::clone(&self.logical_device);
+        let recording = self.recording();
+        let copy_region = vk::BufferCopy::builder()
+            .src_offset(0)
+            .dst_offset(dst_offset)
+            .size(size);
+        unsafe {
+            logical_device.cmd_copy_buffer(
+                recording.command_buffer,
+                *staging_buffer,
+                dst_buffer,
+                &[*copy_region],
+            )
+        };
+        recording.staging_buffers.push(staging_buffer);
+        recording
+            .destinations
+            .buffers
+            .push((dst_buffer, dst_offset, size));
+    }
+
+    /** Records a copy of tightly packed `pixels` into the first mip level and layer of a 2D color image of the given dimensions.
+    The image is expected in `UNDEFINED` layout, and is in `SHADER_READ_ONLY_OPTIMAL` layout once the batch completes. */
+    pub fn upload_image<T>(&mut self, pixels: &[T], image: vk::Image, dimensions: (u32, u32)) {
+        let staging_buffer = self.staging_buffer_with(pixels);
+        let logical_device = Arc::clone(&self.logical_device);
+        let recording = self.recording();
+        let aspect_mask = vk::ImageAspectFlags::COLOR;
+        unsafe {
+            super::cmd_image_barrier(
+                &logical_device,
+                recording.command_buffer,
+                image,
+                aspect_mask,
+                &super::ImageTransition {
+                    old_layout: vk::ImageLayout::UNDEFINED,
+                    new_layout: vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    src_stage: vk::PipelineStageFlags::TOP_OF_PIPE,
+                    src_access: vk::AccessFlags::empty(),
+                    dst_stage: vk::PipelineStageFlags::TRANSFER,
+                    dst_access: vk::AccessFlags::TRANSFER_WRITE,
+                },
+                false,
+            );
+            let region = vk::BufferImageCopy::builder()
+                .buffer_offset(0)
+                .buffer_row_length(0)
+                .buffer_image_height(0)
+                .image_subresource(
+                    *vk::ImageSubresourceLayers::builder()
+                        .aspect_mask(aspect_mask)
+                        .mip_level(0)
+                        .base_array_layer(0)
+                        .layer_count(1),
+                )
+                .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+                .image_extent(vk::Extent3D {
+                    width: dimensions.0,
+                    height: dimensions.1,
+                    depth: 1,
+                });
+            logical_device.cmd_copy_buffer_to_image(
+                recording.command_buffer,
+                *staging_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[*region],
+            );
+        }
+        recording.staging_buffers.push(staging_buffer);
+        recording
+            .destinations
+            .images
+            .push(ImageUpload { image, aspect_mask });
+    }
+
+    /// Whether there are recorded uploads that have not been submitted yet
+    pub fn has_pending_uploads(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /** Submits every upload recorded since the last submission as one batch. Does not block.
+    If nothing was recorded, the returned batch is already complete. */
+    pub fn submit(&mut self) -> UploadBatch {
+        let recording = match self.recording.take() {
+            Some(recording) => recording,
+            None => {
+                return UploadBatch {
+                    id: 0,
+                    timeline_value: None,
+                }
+            }
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        let timeline_value = self.timeline_semaphore().map(|_| id);
+
+        let cross_family = self.uses_transfer_queue();
+        unsafe {
+            if cross_family {
+                // Release half of the ownership transfers, whose destination scope is ignored
+                cmd_destination_barriers(
+                    &self.logical_device,
+                    recording.command_buffer,
+                    &recording.destinations,
+                    (self.transfer_family, self.graphics_family),
+                    (
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    ),
+                    (
+                        vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                        vk::AccessFlags::empty(),
+                    ),
+                );
+            } else {
+                // Makes the writes visible to the stages of later frames that read them
+                cmd_destination_barriers(
+                    &self.logical_device,
+                    recording.command_buffer,
+                    &recording.destinations,
+                    (vk::QUEUE_FAMILY_IGNORED, vk::QUEUE_FAMILY_IGNORED),
+                    (
+                        vk::PipelineStageFlags::TRANSFER,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                    ),
+                    (CONSUMING_STAGES, CONSUMING_ACCESS),
+                );
+            }
+            self.logical_device
+                .end_command_buffer(recording.command_buffer)
+                .unwrap();
+        }
+
+        let fence = unsafe {
+            self.logical_device
+                .create_fence(&vk::FenceCreateInfo::builder(), None)
+        }
+        .unwrap();
+        let command_buffers = [recording.command_buffer];
+        let timeline_semaphores = [self.timeline_semaphore];
+        let timeline_values = [id];
+        let mut timeline_info =
+            vk::TimelineSemaphoreSubmitInfo::builder().signal_semaphore_values(&timeline_values);
+        let mut submit_info = vk::SubmitInfo::builder().command_buffers(&command_buffers);
+        if timeline_value.is_some() {
+            submit_info = submit_info
+                .signal_semaphores(&timeline_semaphores)
+                .push_next(&mut timeline_info);
+        }
+        unsafe {
+            self.logical_device
+                .queue_submit(self.transfer_queue, &[*submit_info], fence)
+        }
+        .expect("Upload submission failed!");
+
+        self.in_flight.push(InFlight {
+            id,
+            fence,
+            command_buffer: recording.command_buffer,
+            _staging_buffers: recording.staging_buffers,
+            acquire: cross_family.then_some(recording.destinations),
+        });
+        UploadBatch { id, timeline_value }
+    }
+
+    /// Fence signaled once the copies of `batch` have finished, or `None` if the batch has already been collected
+    pub fn fence(&self, batch: UploadBatch) -> Option<vk::Fence> {
+        self.in_flight
+            .iter()
+            .find(|in_flight| in_flight.id == batch.id)
+            .map(|in_flight| in_flight.fence)
+    }
+
+    /// Releases the staging memory and command buffers of every batch whose copies have finished,
+    /// queueing their resources for [`UploadManager::cmd_acquire_finished`]. Does not block
+    pub fn collect_finished(&mut self) {
+        let logical_device = Arc::clone(&self.logical_device);
+        let transfer_pool = self.transfer_pool;
+        let mut finished = Vec::new();
+        self.in_flight.retain_mut(|in_flight| {
+            let done = unsafe { logical_device.get_fence_status(in_flight.fence) }.unwrap();
+            if done {
+                unsafe {
+                    logical_device.destroy_fence(in_flight.fence, None);
+                    logical_device.free_command_buffers(transfer_pool, &[in_flight.command_buffer]);
+                }
+                if let Some(destinations) = in_flight.acquire.take() {
+                    finished.push((in_flight.id, destinations));
+                }
+            }
+            !done
+        });
+        self.pending_acquires.extend(finished);
+    }
+
+    /** Records the acquire half of the ownership transfers of every batch whose copies have finished,
+    making the uploaded data available to the commands recorded after it. Nothing to do without a dedicated transfer queue.
+    The graphics queue never waits for the transfer queue, as only finished batches are acquired.
+    # Safety
+    `command_buffer` must be recording, outside of a render pass, and be submitted to the graphics queue. */
+    pub unsafe fn cmd_acquire_finished(&mut self, command_buffer: vk::CommandBuffer) {
+        self.collect_finished();
+        for (_, destinations) in self.pending_acquires.drain(..) {
+            cmd_destination_barriers(
+                &self.logical_device,
+                command_buffer,
+                &destinations,
+                (self.transfer_family, self.graphics_family),
+                (
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::AccessFlags::empty(),
+                ),
+                (CONSUMING_STAGES, CONSUMING_ACCESS),
+            );
+        }
+    }
+
+    /** Whether `batch` may be used by commands recorded from now on, meaning its copies have finished
+    and, with a dedicated transfer queue, it has been acquired with [`UploadManager::cmd_acquire_finished`].
+    Also releases the resources of finished batches */
+    pub fn is_complete(&mut self, batch: UploadBatch) -> bool {
+        self.collect_finished();
+        self.fence(batch).is_none() && !self.pending_acquires.iter().any(|(id, _)| *id == batch.id)
+    }
+
+    /// Blocks until the copies of `batch` have finished. With a dedicated transfer queue,
+    /// the batch is complete once the next frame has been recorded, see [`UploadManager::cmd_acquire_finished`]
+    pub fn wait(&mut self, batch: UploadBatch) {
+        if let Some(fence) = self.fence(batch) {
+            unsafe {
+                self.logical_device
+                    .wait_for_fences(&[fence], true, u64::MAX)
+            }
+            .unwrap();
+        }
+        self.collect_finished();
+    }
+
+    /// Blocks until the copies of every submitted batch have finished
+    pub fn wait_all(&mut self) {
+        let fences: Vec<vk::Fence> = self
+            .in_flight
+            .iter()
+            .map(|in_flight| in_flight.fence)
+            .collect();
+        if !fences.is_empty() {
+            unsafe { self.logical_device.wait_for_fences(&fences, true, u64::MAX) }.unwrap();
+        }
+        self.collect_finished();
+    }
+}
+
+// Barriers on every destination resource of a batch, moving images to SHADER_READ_ONLY_OPTIMAL
+unsafe fn cmd_destination_barriers(
+    logical_device: &Device,
+    command_buffer: vk::CommandBuffer,
+    destinations: &Destinations,
+    (src_family, dst_family): (u32, u32),
+    (src_stage, src_access): (vk::PipelineStageFlags, vk::AccessFlags),
+    (dst_stage, dst_access): (vk::PipelineStageFlags, vk::AccessFlags),
+) {
+    let buffer_barriers: Vec<vk::BufferMemoryBarrier> = destinations
+        .buffers
+        .iter()
+        .map(|(buffer, offset, size)| {
+            *vk::BufferMemoryBarrier::builder()
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .src_queue_family_index(src_family)
+                .dst_queue_family_index(dst_family)
+                .buffer(*buffer)
+                .offset(*offset)
+                .size(*size)
+        })
+        .collect();
+    let image_barriers: Vec<vk::ImageMemoryBarrier> = destinations
+        .images
+        .iter()
+        .map(|upload| {
+            *vk::ImageMemoryBarrier::builder()
+                .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .new_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .src_access_mask(src_access)
+                .dst_access_mask(dst_access)
+                .src_queue_family_index(src_family)
+                .dst_queue_family_index(dst_family)
+                .image(upload.image)
+                .subresource_range(
+                    *vk::ImageSubresourceRange::builder()
+                        .aspect_mask(upload.aspect_mask)
+                        .base_mip_level(0)
+                        .level_count(1)
+                        .base_array_layer(0)
+                        .layer_count(1),
+                )
+        })
+        .collect();
+    logical_device.cmd_pipeline_barrier(
+        command_buffer,
+        src_stage,
+        dst_stage,
+        vk::DependencyFlags::empty(),
+        &[],
+        &buffer_barriers,
+        &image_barriers,
+    );
+}
+
+impl Drop for UploadManager {
+    fn drop(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            unsafe {
+                self.logical_device
+                    .free_command_buffers(self.transfer_pool, &[recording.command_buffer])
+            };
+        }
+        self.wait_all();
+        unsafe {
+            self.logical_device
+                .destroy_semaphore(self.timeline_semaphore, None);
+            self.logical_device
+                .destroy_command_pool(self.transfer_pool, None);
+        }
+    }
+}
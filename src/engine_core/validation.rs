@@ -0,0 +1,369 @@
+use super::{VALIDATION_ENABLED, VALIDATION_LAYERS};
+use ash::{vk, Entry};
+use std::ffi::CStr;
+use std::os::raw::c_void;
+use std::sync::Mutex;
+
+/// Target of the `log` records validation messages are emitted as
+pub const VALIDATION_LOG_TARGET: &str = "vk_engine::validation";
+
+/** Name of the environment variable that overrides the [`ValidationSettings`] given to the engine.
+A comma separated list of `off`, `on`, `gpu` for GPU-assisted validation and `sync` for synchronization validation,
+where `gpu` and `sync` also turn validation on. */
+pub const VALIDATION_ENV: &str = "VK_ENGINE_VALIDATION";
+
+/// Which validation the Khronos validation layer performs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidationSettings {
+    /// Load the validation layer and the debug messenger. Defaults to on in debug builds
+    pub enabled: bool,
+    /// Instrument shaders to catch out of bounds accesses and similar errors on the GPU. Slow
+    pub gpu_assisted: bool,
+    /// Check for missing barriers and other hazards between commands
+    pub synchronization: bool,
+}
+
+impl Default for ValidationSettings {
+    fn default() -> Self {
+        ValidationSettings {
+            enabled: VALIDATION_ENABLED,
+            gpu_assisted: false,
+            synchronization: false,
+        }
+    }
+}
+
+impl ValidationSettings {
+    pub fn disabled() -> ValidationSettings {
+        ValidationSettings {
+            enabled: false,
+            gpu_assisted: false,
+            synchronization: false,
+        }
+    }
+
+    /// Standard validation, without GPU-assisted or synchronization validation
+    pub fn enabled() -> ValidationSettings {
+        ValidationSettings {
+            enabled: true,
+            ..ValidationSettings::disabled()
+        }
+    }
+
+    /// Also turns validation on if `gpu_assisted`
+    pub fn gpu_assisted(mut self, gpu_assisted: bool) -> Self {
+        self.gpu_assisted = gpu_assisted;
+        self.enabled |= gpu_assisted;
+        self
+    }
+
+    /// Also turns validation on if `synchronization`
+    pub fn synchronization(mut self, synchronization: bool) -> Self {
+        self.synchronization = synchronization;
+        self.enabled |= synchronization;
+        self
+    }
+
+    /// Reads the settings from the `VK_ENGINE_VALIDATION` environment variable, if it is set. Unknown options are ignored with a warning
+    pub fn from_env() -> Option<ValidationSettings> {
+        let value = std::env::var(VALIDATION_ENV).ok()?;
+        if value.trim().is_empty() {
+            return None;
+        }
+        let mut settings = ValidationSettings::disabled();
+        for option in value.split(',').map(|option| option.trim().to_lowercase()) {
+            match option.as_str() {
+                "0" | "off" | "false" => settings = ValidationSettings::disabled(),
+                "1" | "on" | "true" => settings.enabled = true,
+                "gpu" => settings = settings.gpu_assisted(true),
+                "sync" => settings = settings.synchronization(true),
+                "" => (),
+                _ => warn(&format!(
+                    "Ignoring unknown {} option '{}'",
+                    VALIDATION_ENV, option
+                )),
+            }
+        }
+        Some(settings)
+    }
+
+    /** What can actually be enabled with the layers and extensions `entry` provides.
+    Validation is turned off with a warning if the validation layer is missing,
+    and GPU-assisted and synchronization validation if `VK_EXT_validation_features` is. */
+    pub fn supported(self, entry: &Entry) -> ValidationSettings {
+        if !self.enabled {
+            return ValidationSettings::disabled();
+        }
+        if !super::check_validation_layer_support(entry) {
+            warn("Validation was requested, but the validation layer is not available. Continuing without validation");
+            return ValidationSettings::disabled();
+        }
+        if (self.gpu_assisted || self.synchronization) && !check_validation_features_support(entry)
+        {
+            warn("VK_EXT_validation_features is not available, GPU-assisted and synchronization validation are disabled");
+            return ValidationSettings::enabled();
+        }
+        self
+    }
+
+    /// Features to enable through `VkValidationFeaturesEXT`
+    pub fn enabled_validation_features(&self) -> Vec<vk::ValidationFeatureEnableEXT> {
+        let mut features = Vec::new();
+        if self.gpu_assisted {
+            features.push(vk::ValidationFeatureEnableEXT::GPU_ASSISTED);
+            features.push(vk::ValidationFeatureEnableEXT::GPU_ASSISTED_RESERVE_BINDING_SLOT);
+        }
+        if self.synchronization {
+            features.push(vk::ValidationFeatureEnableEXT::SYNCHRONIZATION_VALIDATION);
+        }
+        features
+    }
+}
+
+/// Whether the validation layer provides `VK_EXT_validation_features`
+pub fn check_validation_features_support(entry: &Entry) -> bool {
+    VALIDATION_LAYERS.iter().any(|layer| {
+        let layer = unsafe { CStr::from_ptr(*layer) };
+        entry
+            .enumerate_instance_extension_properties(Some(layer))
+            .unwrap_or_default()
+            .iter()
+            .any(|extension| {
+                let name = unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) };
+                name == vk::ExtValidationFeaturesFn::name()
+            })
+    })
+}
+
+// Warnings about the engine's own setup, which must be seen even without a logger
+pub(crate) fn warn(message: &str) {
+    if log::max_level() == log::LevelFilter::Off {
+        eprintln!("{}", message);
+    } else {
+        log::warn!(target: VALIDATION_LOG_TARGET, "{}", message);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValidationSeverity {
+    Verbose,
+    Info,
+    Warning,
+    Error,
+}
+
+impl ValidationSeverity {
+    fn from_flags(flags: vk::DebugUtilsMessageSeverityFlagsEXT) -> ValidationSeverity {
+        if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::ERROR) {
+            ValidationSeverity::Error
+        } else if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::WARNING) {
+            ValidationSeverity::Warning
+        } else if flags.contains(vk::DebugUtilsMessageSeverityFlagsEXT::INFO) {
+            ValidationSeverity::Info
+        } else {
+            ValidationSeverity::Verbose
+        }
+    }
+
+    fn log_level(self) -> log::Level {
+        match self {
+            ValidationSeverity::Verbose => log::Level::Trace,
+            ValidationSeverity::Info => log::Level::Info,
+            ValidationSeverity::Warning => log::Level::Warn,
+            ValidationSeverity::Error => log::Level::Error,
+        }
+    }
+}
+
+/// A Vulkan object a [`ValidationMessage`] refers to
+#[derive(Clone, Debug)]
+pub struct ValidationObject {
+    pub object_type: vk::ObjectType,
+    pub handle: u64,
+    /// Name given with [`DebugNamer::set_name`](super::DebugNamer::set_name), if any
+    pub name: Option<String>,
+}
+
+/// A message from the validation layers or another `VK_EXT_debug_utils` message source
+#[derive(Clone, Debug)]
+pub struct ValidationMessage {
+    pub severity: ValidationSeverity,
+    /// General, validation and/or performance
+    pub message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    /// Identifies the kind of message, such as the Valid Usage ID for validation errors
+    pub message_id_name: Option<String>,
+    pub message_id_number: i32,
+    pub message: String,
+    pub objects: Vec<ValidationObject>,
+}
+
+impl ValidationMessage {
+    pub fn is_error(&self) -> bool {
+        self.severity == ValidationSeverity::Error
+    }
+
+    // Safety: `data` must be valid callback data given by the messenger
+    unsafe fn from_callback_data(
+        severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+        message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+        data: &vk::DebugUtilsMessengerCallbackDataEXT,
+    ) -> ValidationMessage {
+        let string = |ptr: *const std::os::raw::c_char| {
+            (!ptr.is_null()).then(|| CStr::from_ptr(ptr).to_string_lossy().into_owned())
+        };
+        let objects = if data.p_objects.is_null() {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(data.p_objects, data.object_count as usize)
+                .iter()
+                .map(|object| ValidationObject {
+                    object_type: object.object_type,
+                    handle: object.object_handle,
+                    name: string(object.p_object_name),
+                })
+                .collect()
+        };
+        ValidationMessage {
+            severity: ValidationSeverity::from_flags(severity),
+            message_type,
+            message_id_name: string(data.p_message_id_name),
+            message_id_number: data.message_id_number,
+            message: string(data.p_message).unwrap_or_default(),
+            objects,
+        }
+    }
+}
+
+type ValidationCallback = Box<dyn Fn(&ValidationMessage) + Send + Sync>;
+
+/** Receives the messages of a debug messenger created with it as user data, see [`init_debug_messenger_info`].
+Every message is logged through the `log` crate under [`VALIDATION_LOG_TARGET`], at the level matching its severity.
+If no logger is installed, warnings and errors are printed to stderr instead.
+Messages may arrive from any thread the driver calls from. */
+#[derive(Default)]
+pub struct ValidationHandler {
+    callback: Mutex<Option<ValidationCallback>>,
+    captured: Mutex<Option<Vec<ValidationMessage>>>,
+}
+
+impl ValidationHandler {
+    pub fn new() -> ValidationHandler {
+        ValidationHandler::default()
+    }
+
+    /// Calls `callback` with every message, in addition to logging it. Replaces any earlier callback
+    pub fn set_callback(&self, callback: impl Fn(&ValidationMessage) + Send + Sync + 'static) {
+        *self.callback.lock().unwrap() = Some(Box::new(callback));
+    }
+
+    pub fn clear_callback(&self) {
+        *self.callback.lock().unwrap() = None;
+    }
+
+    /// Starts collecting messages, discarding any collected so far
+    pub fn start_capture(&self) {
+        *self.captured.lock().unwrap() = Some(Vec::new());
+    }
+
+    /// Returns the messages collected since the capture started or was last taken from, and keeps capturing
+    pub fn take_captured(&self) -> Vec<ValidationMessage> {
+        self.captured
+            .lock()
+            .unwrap()
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Stops collecting messages and returns those not yet taken
+    pub fn stop_capture(&self) -> Vec<ValidationMessage> {
+        self.captured.lock().unwrap().take().unwrap_or_default()
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.captured.lock().unwrap().is_some()
+    }
+
+    fn handle(&self, message: ValidationMessage) {
+        log_message(&message);
+        if let Some(callback) = self.callback.lock().unwrap().as_ref() {
+            callback(&message);
+        }
+        if let Some(captured) = self.captured.lock().unwrap().as_mut() {
+            captured.push(message);
+        }
+    }
+}
+
+fn log_message(message: &ValidationMessage) {
+    let level = message.severity.log_level();
+    if log::max_level() == log::LevelFilter::Off {
+        if message.severity >= ValidationSeverity::Warning {
+            eprintln!("{}", message.message);
+        }
+        return;
+    }
+    let objects: Vec<String> = message
+        .objects
+        .iter()
+        .map(|object| match &object.name {
+            Some(name) => format!(
+                "{:?} 0x{:x} \"{}\"",
+                object.object_type, object.handle, name
+            ),
+            None => format!("{:?} 0x{:x}", object.object_type, object.handle),
+        })
+        .collect();
+    log::log!(
+        target: VALIDATION_LOG_TARGET,
+        level,
+        "[{}] {}{}",
+        message.message_id_name.as_deref().unwrap_or("-"),
+        message.message,
+        if objects.is_empty() {
+            String::new()
+        } else {
+            format!(" (objects: {})", objects.join(", "))
+        }
+    );
+}
+
+/** Messenger create info receiving all message severities and types.
+Set its user data to a pointer to a [`ValidationHandler`] that outlives the messenger to receive messages there,
+otherwise messages are only logged. */
+pub fn init_debug_messenger_info() -> vk::DebugUtilsMessengerCreateInfoEXTBuilder<'static> {
+    vk::DebugUtilsMessengerCreateInfoEXT::builder()
+        .message_severity(
+            vk::DebugUtilsMessageSeverityFlagsEXT::VERBOSE
+                | vk::DebugUtilsMessageSeverityFlagsEXT::INFO
+                | vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
+                | vk::DebugUtilsMessageSeverityFlagsEXT::ERROR,
+        )
+        .message_type(
+            vk::DebugUtilsMessageTypeFlagsEXT::GENERAL
+                | vk::DebugUtilsMessageTypeFlagsEXT::VALIDATION
+                | vk::DebugUtilsMessageTypeFlagsEXT::PERFORMANCE,
+        )
+        .pfn_user_callback(Some(debug_callback))
+}
+
+unsafe extern "system" fn debug_callback(
+    message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
+    message_type: vk::DebugUtilsMessageTypeFlagsEXT,
+    p_callback_data: *const vk::DebugUtilsMessengerCallbackDataEXT,
+    p_user_data: *mut c_void,
+) -> vk::Bool32 {
+    if p_callback_data.is_null() {
+        return vk::FALSE;
+    }
+    let message =
+        ValidationMessage::from_callback_data(message_severity, message_type, &*p_callback_data);
+    // Unwinding out of an extern "system" function aborts, so a panicking user callback only loses the message
+    let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        match (p_user_data as *const ValidationHandler).as_ref() {
+            Some(handler) => handler.handle(message),
+            None => log_message(&message),
+        }
+    }));
+    vk::FALSE
+}
@@ -0,0 +1,402 @@
+/*!
+Keyboard and mouse state gathered from winit events, to be queried once per frame.
+# Example:
+```ignore
+let mut input = Input::new();
+input.bind_action("jump", Binding::Key(VirtualKeyCode::Space));
+
+event_loop.run(move |event, _, control_flow| {
+    input.handle_event(&event);
+    if let Event::MainEventsCleared = event {
+        if input.action_pressed("jump") {
+            // ...
+        }
+        // Render the frame
+        input.end_frame();
+    }
+});
+```
+*/
+
+use glam::Vec2;
+use std::collections::{HashMap, HashSet};
+use winit::event::{
+    DeviceEvent, ElementState, Event, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
+
+/// Pixels of scrolling counted as one line, for touchpads and other devices scrolling by pixels
+const PIXELS_PER_SCROLL_LINE: f32 = 40.0;
+
+/// A key or mouse button an action can be bound to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(VirtualKeyCode),
+    MouseButton(MouseButton),
+}
+
+/** Tracks which keys and mouse buttons are held, and which were pressed or released during the current frame,
+along with the cursor, mouse motion, scrolling and modifier keys.
+Feed every event to [`Input::handle_event`] and call [`Input::end_frame`] once the frame has been processed. */
+#[derive(Default)]
+pub struct Input {
+    keys_held: HashSet<VirtualKeyCode>,
+    keys_pressed: HashSet<VirtualKeyCode>,
+    keys_released: HashSet<VirtualKeyCode>,
+    buttons_held: HashSet<MouseButton>,
+    buttons_pressed: HashSet<MouseButton>,
+    buttons_released: HashSet<MouseButton>,
+    cursor_position: Option<Vec2>,
+    cursor_delta: Vec2,
+    mouse_motion: Vec2,
+    scroll: Vec2,
+    modifiers: ModifiersState,
+    actions: HashMap<String, Vec<Binding>>,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        Input::default()
+    }
+
+    /// Updates the state from a window or device event, other events are ignored
+    pub fn handle_event<T>(&mut self, event: &Event<T>) {
+        match event {
+            Event::WindowEvent { event, .. } => self.handle_window_event(event),
+            Event::DeviceEvent { event, .. } => self.handle_device_event(event),
+            _ => (),
+        }
+    }
+
+    pub fn handle_window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                if let Some(key) = input.virtual_keycode {
+                    match input.state {
+                        // Key repeats are not new presses
+                        ElementState::Pressed => {
+                            if self.keys_held.insert(key) {
+                                self.keys_pressed.insert(key);
+                            }
+                        }
+                        ElementState::Released => {
+                            if self.keys_held.remove(&key) {
+                                self.keys_released.insert(key);
+                            }
+                        }
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    if self.buttons_held.insert(*button) {
+                        self.buttons_pressed.insert(*button);
+                    }
+                }
+                ElementState::Released => {
+                    if self.buttons_held.remove(button) {
+                        self.buttons_released.insert(*button);
+                    }
+                }
+            },
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = Vec2::new(position.x as f32, position.y as f32);
+                if let Some(previous) = self.cursor_position {
+                    self.cursor_delta += position - previous;
+                }
+                self.cursor_position = Some(position);
+            }
+            WindowEvent::CursorLeft { .. } => self.cursor_position = None,
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.scroll += match delta {
+                    MouseScrollDelta::LineDelta(x, y) => Vec2::new(*x, *y),
+                    MouseScrollDelta::PixelDelta(position) => {
+                        Vec2::new(position.x as f32, position.y as f32) / PIXELS_PER_SCROLL_LINE
+                    }
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = *modifiers,
+            // Keys and buttons released outside the window would otherwise stay held
+            WindowEvent::Focused(false) => {
+                self.keys_released.extend(self.keys_held.drain());
+                self.buttons_released.extend(self.buttons_held.drain());
+                self.modifiers = ModifiersState::empty();
+            }
+            _ => (),
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            self.mouse_motion += Vec2::new(delta.0 as f32, delta.1 as f32);
+        }
+    }
+
+    /// Clears what happened during the frame, call once per frame after all queries
+    pub fn end_frame(&mut self) {
+        self.keys_pressed.clear();
+        self.keys_released.clear();
+        self.buttons_pressed.clear();
+        self.buttons_released.clear();
+        self.cursor_delta = Vec2::ZERO;
+        self.mouse_motion = Vec2::ZERO;
+        self.scroll = Vec2::ZERO;
+    }
+
+    pub fn key_held(&self, key: VirtualKeyCode) -> bool {
+        self.keys_held.contains(&key)
+    }
+
+    pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    pub fn key_released(&self, key: VirtualKeyCode) -> bool {
+        self.keys_released.contains(&key)
+    }
+
+    pub fn button_held(&self, button: MouseButton) -> bool {
+        self.buttons_held.contains(&button)
+    }
+
+    pub fn button_pressed(&self, button: MouseButton) -> bool {
+        self.buttons_pressed.contains(&button)
+    }
+
+    pub fn button_released(&self, button: MouseButton) -> bool {
+        self.buttons_released.contains(&button)
+    }
+
+    /// 1 if `positive` is held, -1 if `negative` is held and 0 if both or neither are
+    pub fn key_axis(&self, positive: VirtualKeyCode, negative: VirtualKeyCode) -> f32 {
+        self.key_held(positive) as i32 as f32 - self.key_held(negative) as i32 as f32
+    }
+
+    /// Cursor position in physical pixels from the top left of the window, `None` while the cursor is outside it
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor_position
+    }
+
+    /// How far the cursor moved inside the window this frame, in physical pixels
+    pub fn cursor_delta(&self) -> Vec2 {
+        self.cursor_delta
+    }
+
+    /** Raw mouse motion this frame, unaffected by pointer acceleration and the window edges.
+    Prefer this over [`Input::cursor_delta`] for camera controls. */
+    pub fn mouse_motion(&self) -> Vec2 {
+        self.mouse_motion
+    }
+
+    /// Lines scrolled this frame, positive `y` away from the user
+    pub fn scroll(&self) -> Vec2 {
+        self.scroll
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Adds a binding triggering `action`, an action may have any number of bindings
+    pub fn bind_action(&mut self, action: &str, binding: Binding) {
+        let bindings = self.actions.entry(action.to_owned()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes all bindings of `action`
+    pub fn unbind_action(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    pub fn action_bindings(&self, action: &str) -> &[Binding] {
+        self.actions.get(action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether any binding of `action` is held
+    pub fn action_held(&self, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| match *binding {
+                Binding::Key(key) => self.key_held(key),
+                Binding::MouseButton(button) => self.button_held(button),
+            })
+    }
+
+    /// Whether any binding of `action` was pressed this frame
+    pub fn action_pressed(&self, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| match *binding {
+                Binding::Key(key) => self.key_pressed(key),
+                Binding::MouseButton(button) => self.button_pressed(button),
+            })
+    }
+
+    /// Whether any binding of `action` was released this frame
+    pub fn action_released(&self, action: &str) -> bool {
+        self.action_bindings(action)
+            .iter()
+            .any(|binding| match *binding {
+                Binding::Key(key) => self.key_released(key),
+                Binding::MouseButton(button) => self.button_released(button),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::dpi::PhysicalPosition;
+    use winit::event::{DeviceId, KeyboardInput, TouchPhase};
+
+    fn device_id() -> DeviceId {
+        unsafe { DeviceId::dummy() }
+    }
+
+    #[allow(deprecated)]
+    fn key(input: &mut Input, key: VirtualKeyCode, state: ElementState) {
+        input.handle_window_event(&WindowEvent::KeyboardInput {
+            device_id: device_id(),
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        });
+    }
+
+    #[allow(deprecated)]
+    fn button(input: &mut Input, button: MouseButton, state: ElementState) {
+        input.handle_window_event(&WindowEvent::MouseInput {
+            device_id: device_id(),
+            state,
+            button,
+            modifiers: ModifiersState::empty(),
+        });
+    }
+
+    #[test]
+    fn key_presses_last_one_frame_and_holds_until_released() {
+        let mut input = Input::new();
+        key(&mut input, VirtualKeyCode::W, ElementState::Pressed);
+        assert!(input.key_pressed(VirtualKeyCode::W));
+        assert!(input.key_held(VirtualKeyCode::W));
+        assert!(!input.key_released(VirtualKeyCode::W));
+
+        input.end_frame();
+        assert!(!input.key_pressed(VirtualKeyCode::W));
+        assert!(input.key_held(VirtualKeyCode::W));
+
+        // A repeat of a held key is not a new press
+        key(&mut input, VirtualKeyCode::W, ElementState::Pressed);
+        assert!(!input.key_pressed(VirtualKeyCode::W));
+
+        key(&mut input, VirtualKeyCode::W, ElementState::Released);
+        assert!(input.key_released(VirtualKeyCode::W));
+        assert!(!input.key_held(VirtualKeyCode::W));
+
+        input.end_frame();
+        assert!(!input.key_released(VirtualKeyCode::W));
+    }
+
+    #[test]
+    fn press_and_release_within_a_frame_are_both_seen() {
+        let mut input = Input::new();
+        button(&mut input, MouseButton::Left, ElementState::Pressed);
+        button(&mut input, MouseButton::Left, ElementState::Released);
+        assert!(input.button_pressed(MouseButton::Left));
+        assert!(input.button_released(MouseButton::Left));
+        assert!(!input.button_held(MouseButton::Left));
+
+        // Releasing something that was never held is ignored
+        key(&mut input, VirtualKeyCode::Q, ElementState::Released);
+        assert!(!input.key_released(VirtualKeyCode::Q));
+    }
+
+    #[test]
+    fn losing_focus_releases_everything_held() {
+        let mut input = Input::new();
+        key(&mut input, VirtualKeyCode::A, ElementState::Pressed);
+        button(&mut input, MouseButton::Right, ElementState::Pressed);
+        input.handle_window_event(&WindowEvent::ModifiersChanged(ModifiersState::SHIFT));
+        input.end_frame();
+
+        input.handle_window_event(&WindowEvent::Focused(false));
+        assert!(!input.key_held(VirtualKeyCode::A));
+        assert!(input.key_released(VirtualKeyCode::A));
+        assert!(!input.button_held(MouseButton::Right));
+        assert!(input.button_released(MouseButton::Right));
+        assert_eq!(input.modifiers(), ModifiersState::empty());
+        assert_eq!(input.key_axis(VirtualKeyCode::D, VirtualKeyCode::A), 0.0);
+    }
+
+    #[test]
+    fn actions_follow_any_of_their_bindings() {
+        let mut input = Input::new();
+        input.bind_action("jump", Binding::Key(VirtualKeyCode::Space));
+        input.bind_action("jump", Binding::MouseButton(MouseButton::Left));
+        input.bind_action("jump", Binding::Key(VirtualKeyCode::Space));
+        assert_eq!(input.action_bindings("jump").len(), 2);
+
+        button(&mut input, MouseButton::Left, ElementState::Pressed);
+        assert!(input.action_pressed("jump"));
+        assert!(input.action_held("jump"));
+        assert!(!input.action_pressed("crouch"));
+
+        input.end_frame();
+        key(&mut input, VirtualKeyCode::Space, ElementState::Pressed);
+        button(&mut input, MouseButton::Left, ElementState::Released);
+        assert!(input.action_pressed("jump"));
+        assert!(input.action_released("jump"));
+        assert!(input.action_held("jump"));
+
+        input.unbind_action("jump");
+        assert!(input.action_bindings("jump").is_empty());
+        assert!(!input.action_held("jump"));
+    }
+
+    #[test]
+    fn motion_and_scroll_accumulate_until_the_frame_ends() {
+        let mut input = Input::new();
+        let moved = |x, y| WindowEvent::CursorMoved {
+            device_id: device_id(),
+            position: PhysicalPosition::new(x, y),
+            #[allow(deprecated)]
+            modifiers: ModifiersState::empty(),
+        };
+        input.handle_window_event(&moved(10.0, 20.0));
+        assert_eq!(input.cursor_delta(), Vec2::ZERO);
+        input.handle_window_event(&moved(15.0, 18.0));
+        input.handle_window_event(&moved(16.0, 28.0));
+        assert_eq!(input.cursor_position(), Some(Vec2::new(16.0, 28.0)));
+        assert_eq!(input.cursor_delta(), Vec2::new(6.0, 8.0));
+
+        input.handle_event::<()>(&Event::DeviceEvent {
+            device_id: device_id(),
+            event: DeviceEvent::MouseMotion { delta: (3.0, -1.0) },
+        });
+        assert_eq!(input.mouse_motion(), Vec2::new(3.0, -1.0));
+
+        #[allow(deprecated)]
+        input.handle_window_event(&WindowEvent::MouseWheel {
+            device_id: device_id(),
+            delta: MouseScrollDelta::PixelDelta(PhysicalPosition::new(
+                0.0,
+                PIXELS_PER_SCROLL_LINE as f64,
+            )),
+            phase: TouchPhase::Moved,
+            modifiers: ModifiersState::empty(),
+        });
+        assert_eq!(input.scroll(), Vec2::new(0.0, 1.0));
+
+        input.end_frame();
+        assert_eq!(input.cursor_delta(), Vec2::ZERO);
+        assert_eq!(input.mouse_motion(), Vec2::ZERO);
+        assert_eq!(input.scroll(), Vec2::ZERO);
+        assert_eq!(input.cursor_position(), Some(Vec2::new(16.0, 28.0)));
+    }
+}
@@ -6,9 +6,14 @@ There is yet no entirely consistent rule for which functions are safe/unsafe.
 
 ### Crate features
 * **shader_compilation** -
-    Provides functions for runtime compilation of shaders using [shaderc](https://crates.io/crates/shaderc)
+  Provides functions for runtime compilation of shaders using [shaderc](https://crates.io/crates/shaderc)
+* **gltf** -
+  Provides [`assets::gltf`] for loading glTF 2.0 scenes using [gltf](https://crates.io/crates/gltf)
 */
 
+// Lets the derives in vk_engine_derive refer to this crate as `::vk_engine` from inside it too
+extern crate self as vk_engine;
+
 use winit::event_loop::EventLoop;
 use winit::window::{Window, WindowBuilder};
 
@@ -24,9 +29,23 @@ pub mod application;
 /// Managing shaders
 pub mod shaders;
 
+/// Loading models and other assets from files
+pub mod assets;
+
+/// Keyboard and mouse state tracking
+pub mod input;
+
+/// Cameras and controllers for moving them with mouse and keyboard
+pub mod camera;
+
+#[doc(hidden)]
+pub mod runner;
+
 #[doc(inline)]
-pub use application::BaseApp;
-pub use engine_core::VertexInputDescriptors;
+pub use application::{AppSettings, BaseApp};
+pub use engine_core::{DeviceFeatures, DeviceRequirements, DeviceSelector, VertexInputDescriptors};
+#[doc(inline)]
+pub use runner::{run, App, Flow, FrameContext, PipelineDescription};
 
 /// Quick initialization of a window
 pub fn init_window(app_name: &str, width: u32, height: u32) -> (Window, EventLoop<()>) {
@@ -43,9 +62,10 @@ pub fn init_window(app_name: &str, width: u32, height: u32) -> (Window, EventLoo
 /**
 For use inside [`BaseApp::record_command_buffer`]. Will cover most common use cases for drawing:
 1. Sets the render area to the full swapchain extent and sets the (first) clear color to black
-2. Begins a render pass and binds the graphics pipeline to the graphics stage
-3. Runs `commands` closure
-4. Ends render pass
+2. Begins a render pass (or dynamic rendering, see [`BaseApp::begin_rendering`]) and binds the graphics pipeline to the graphics stage
+3. Binds the frame's descriptor set, and the bindless textures if present
+4. Runs `commands` closure
+5. Ends render pass
 # Safety
 Behaviour is undefined if the arguments are invalid.
 */
@@ -55,30 +75,12 @@ pub unsafe fn drawing_commands<F>(
     swapchain_image_index: u32,
     commands: F,
     push_constants: &[f32; 1],
-    index_type: vk::IndexType
+    index_type: vk::IndexType,
 ) where
     F: FnOnce(&mut BaseApp),
 {
-    //Start render pass
-    let render_area = vk::Rect2D::builder()
-        .offset(vk::Offset2D { x: 0, y: 0 })
-        .extent(app.swapchain_extent);
-    let mut clear_values = [vk::ClearValue::default(); 2];
-    clear_values[0].color.float32 = [0.0, 0.0, 0.0, 1.0];
-    clear_values[1].depth_stencil = vk::ClearDepthStencilValue {
-        depth: 1.0,
-        stencil: 0,
-    };
-    let renderpass_begin_info = vk::RenderPassBeginInfo::builder()
-        .render_pass(app.render_pass)
-        .framebuffer(app.framebuffers[swapchain_image_index as usize])
-        .render_area(*render_area)
-        .clear_values(&clear_values);
-    app.logical_device.cmd_begin_render_pass(
-        app.command_buffers[buffer_index],
-        &renderpass_begin_info,
-        vk::SubpassContents::INLINE,
-    );
+    //Start render pass (or dynamic rendering)
+    app.begin_rendering(buffer_index, swapchain_image_index);
     app.logical_device.cmd_bind_pipeline(
         app.command_buffers[buffer_index],
         vk::PipelineBindPoint::GRAPHICS,
@@ -117,17 +119,28 @@ pub unsafe fn drawing_commands<F>(
         &[app.descriptor_sets[buffer_index]],
         &[],
     );
+    if let (Some(bindless_textures), Some(set)) =
+        (app.bindless_textures.as_ref(), app.bindless_set_index())
+    {
+        app.logical_device.cmd_bind_descriptor_sets(
+            app.command_buffers[buffer_index],
+            vk::PipelineBindPoint::GRAPHICS,
+            app.graphics_pipeline_layout,
+            set,
+            &[bindless_textures.set()],
+            &[],
+        );
+    }
 
     commands(app);
 
     //End the render pass
-    app.logical_device
-        .cmd_end_render_pass(app.command_buffers[buffer_index]);
+    app.end_rendering(buffer_index, swapchain_image_index);
 }
 
 // Struct for for MVP matrices, to be used in uniform buffers
 #[repr(C)]
-#[derive(Clone)]
+#[derive(Clone, engine_core::Std140)]
 pub struct MVP {
     pub model: glam::Mat4,
     pub view: glam::Mat4,
@@ -154,11 +167,25 @@ pub fn default_descriptor_set_layout_bindings() -> Vec<vk::DescriptorSetLayoutBi
     ]
 }
 
+/** Binding for a `STORAGE_BUFFER` descriptor, to add to e.g. [`default_descriptor_set_layout_bindings`].
+Point it at a buffer from [`engine_core::create_storage_buffer`] with [`BaseApp::bind_storage_buffer`].
+In GLSL, declared as e.g. `layout(std430, binding = 2) buffer Objects { mat4 models[]; };` */
+pub fn storage_buffer_layout_binding(
+    binding: u32,
+    stage_flags: vk::ShaderStageFlags,
+) -> vk::DescriptorSetLayoutBinding {
+    *vk::DescriptorSetLayoutBinding::builder()
+        .binding(binding)
+        .descriptor_type(vk::DescriptorType::STORAGE_BUFFER)
+        .descriptor_count(1)
+        .stage_flags(stage_flags)
+}
+
 pub fn load_image_as_rgba_samples(img_path: &str) -> (Vec<u8>, (u32, u32)) {
     let img = image::io::Reader::open(img_path)
-        .expect(&format!("Could not open '{}'", img_path))
+        .unwrap_or_else(|_| panic!("Could not open '{}'", img_path))
         .decode()
-        .expect(&format!("Could not decode '{}'", img_path));
+        .unwrap_or_else(|_| panic!("Could not decode '{}'", img_path));
     let img_size = (img.width(), img.height());
     let pixels = img.into_rgba8().into_flat_samples().samples;
 
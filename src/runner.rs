@@ -0,0 +1,224 @@
+/*!
+A managed frame loop, driving the winit event loop and a [`BaseApp`] on behalf of an [`App`].
+# Example:
+```ignore
+struct Triangle { shaders: Vec<Shader>, vertex_input_descriptors: VertexInputDescriptors }
+
+impl App for Triangle {
+    fn pipeline_description(&self) -> PipelineDescription<'_> {
+        PipelineDescription {
+            shaders: &self.shaders,
+            vertex_input_descriptors: &self.vertex_input_descriptors,
+            descriptor_set_bindings: &[],
+        }
+    }
+
+    fn render(&mut self, frame: &mut FrameContext) {
+        unsafe {
+            vk_engine::drawing_commands(frame.app, frame.frame_index, frame.image_index, |app| {
+                app.logical_device.cmd_draw_indexed(app.command_buffers[frame.frame_index], 3, 1, 0, 0, 0);
+            }, &[0.0], vk::IndexType::UINT16);
+        }
+    }
+}
+
+vk_engine::run(event_loop, vulkan_app, Triangle { shaders, vertex_input_descriptors });
+```
+*/
+
+use crate::engine_core::MAX_FRAMES_IN_FLIGHT;
+use crate::input::Input;
+use crate::shaders::Shader;
+use crate::{BaseApp, VertexInputDescriptors};
+use ash::vk;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+
+/// What the graphics pipeline is rebuilt from whenever the swapchain is recreated
+pub struct PipelineDescription<'a> {
+    pub shaders: &'a [Shader],
+    pub vertex_input_descriptors: &'a VertexInputDescriptors,
+    pub descriptor_set_bindings: &'a [vk::DescriptorSetLayoutBinding],
+}
+
+/// The frame being recorded by [`App::render`]
+pub struct FrameContext<'a> {
+    pub app: &'a mut BaseApp,
+    /// Index of the frame in flight, selecting per-frame resources such as command and uniform buffers
+    pub frame_index: usize,
+    /// Index of the acquired swapchain image to render to
+    pub image_index: u32,
+    /// Frames rendered since the loop started
+    pub frame_number: u64,
+}
+
+impl FrameContext<'_> {
+    /// The command buffer being recorded
+    pub fn command_buffer(&self) -> vk::CommandBuffer {
+        self.app.command_buffers[self.frame_index]
+    }
+}
+
+/// Whether the loop should keep running after [`App::update`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Exit,
+}
+
+/** An application driven by [`run`]. Only [`App::pipeline_description`] and [`App::render`] are required.
+Each frame, once the frame's previous submission has finished, [`App::update`] is called followed by [`App::render`]. */
+pub trait App: 'static {
+    fn pipeline_description(&self) -> PipelineDescription<'_>;
+
+    /// Called once before the first frame, for example to bind input actions
+    fn init(&mut self, _app: &mut BaseApp, _input: &mut Input) {}
+
+    /** Advances the application by `delta_time` seconds, the duration of the last frame from [`BaseApp::frame_stats`]. The frame's in-flight fence has been waited on,
+    so geometry may be changed with [`BaseApp::set_vertices`] and similar. */
+    fn update(&mut self, _app: &mut BaseApp, _input: &Input, _delta_time: f32) -> Flow {
+        Flow::Continue
+    }
+
+    /// Records the frame's drawing commands, called inside [`BaseApp::record_command_buffer`] timed as the "frame" GPU scope and labelled "frame" for debugging tools
+    fn render(&mut self, frame: &mut FrameContext);
+
+    /// Called after the swapchain has been recreated with a new extent
+    fn on_resize(&mut self, _app: &mut BaseApp, _extent: vk::Extent2D) {}
+
+    /// Called with every event before the loop handles it
+    fn on_event(&mut self, _app: &mut BaseApp, _event: &Event<()>) {}
+}
+
+/** Runs the event loop until the window is closed or [`App::update`] returns [`Flow::Exit`].
+Handles waiting for frames in flight, image acquisition and presentation, recreating the swapchain when it is out of date,
+suboptimal or the window is resized, and pauses rendering while the window is minimised.
+On exit, `app_impl` is dropped before `base_app`, so it may own resources created from it. */
+pub fn run<A: App>(event_loop: EventLoop<()>, mut base_app: BaseApp, mut app_impl: A) -> ! {
+    let mut input = Input::new();
+    app_impl.init(&mut base_app, &mut input);
+    let mut state = Some((app_impl, base_app));
+    let mut current_frame = 0;
+    let mut frame_number = 0;
+    let mut swapchain_outdated = false;
+    let mut minimised = false;
+    // Whether the fence of `current_frame` has been waited on and the app updated, but the frame not yet submitted.
+    // Waiting again would restart the frame's staging partition under copies queued by the update
+    let mut frame_prepared = false;
+
+    event_loop.run(move |event, _, control_flow| {
+        if let Event::LoopDestroyed = event {
+            // Resources owned by the app must go before the device they were created from
+            if let Some((app_impl, base_app)) = state.take() {
+                unsafe { base_app.logical_device.device_wait_idle() }.unwrap();
+                drop(app_impl);
+                drop(base_app);
+            }
+            return;
+        }
+        let Some((app_impl, base_app)) = state.as_mut() else {
+            return;
+        };
+        app_impl.on_event(base_app, &event);
+        input.handle_event(&event);
+
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+                // Some platforms do not report the swapchain as out of date on resize
+                WindowEvent::Resized(size) => {
+                    let was_minimised = minimised;
+                    minimised = size.width == 0 || size.height == 0;
+                    if was_minimised && !minimised {
+                        // Time spent minimised is neither simulated nor counted as a frame
+                        base_app.frame_stats_mut().reset();
+                        *control_flow = ControlFlow::Poll;
+                    }
+                    swapchain_outdated = true;
+                }
+                _ => (),
+            },
+            Event::MainEventsCleared => {
+                // A swapchain cannot have a zero extent, so nothing is rendered until the window is restored
+                if minimised {
+                    *control_flow = ControlFlow::Wait;
+                    return;
+                }
+                if swapchain_outdated {
+                    recreate_swapchain(app_impl, base_app);
+                    swapchain_outdated = false;
+                }
+
+                if !frame_prepared {
+                    base_app.wait_for_in_flight_fence(current_frame);
+
+                    let delta_time = base_app.frame_stats().delta().as_secs_f32();
+                    let flow = app_impl.update(base_app, &input, delta_time);
+                    input.end_frame();
+                    if flow == Flow::Exit {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    frame_prepared = true;
+                }
+
+                // A suboptimal image has still been acquired and its semaphore will be signaled, so the frame is rendered
+                // and the swapchain recreated afterwards
+                let image_index = match base_app.acquire_next_image(current_frame) {
+                    Ok((image_index, suboptimal)) => {
+                        swapchain_outdated |= suboptimal;
+                        image_index
+                    }
+                    // The frame stays prepared, and is rendered once the swapchain has been recreated
+                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                        swapchain_outdated = true;
+                        return;
+                    }
+                    Err(error) => panic!("Could not acquire image from swapchain: {}", error),
+                };
+
+                // Only reset once an image is acquired, an early return would otherwise leave the fence unsignaled forever
+                base_app.reset_in_flight_fence(current_frame);
+                frame_prepared = false;
+
+                unsafe {
+                    base_app.record_command_buffer(current_frame, |app| {
+                        app.cmd_begin_label(current_frame, "frame");
+                        let frame_scope = app.profile_scope(current_frame, "frame");
+                        app_impl.render(&mut FrameContext {
+                            app,
+                            frame_index: current_frame,
+                            image_index,
+                            frame_number,
+                        });
+                        drop(frame_scope);
+                        app.cmd_end_label(current_frame);
+                    })
+                };
+                base_app.submit_drawing_command_buffer(current_frame);
+
+                match base_app
+                    .present_image(image_index, base_app.sync.render_finished[current_frame])
+                {
+                    Ok(suboptimal) => swapchain_outdated |= suboptimal,
+                    Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => swapchain_outdated = true,
+                    Err(error) => panic!("Could not present image: {}", error),
+                };
+
+                current_frame = (current_frame + 1) % MAX_FRAMES_IN_FLIGHT;
+                frame_number += 1;
+            }
+            _ => (),
+        }
+    })
+}
+
+fn recreate_swapchain<A: App>(app_impl: &mut A, base_app: &mut BaseApp) {
+    let description = app_impl.pipeline_description();
+    base_app.recreate_swapchain(
+        description.shaders,
+        description.vertex_input_descriptors,
+        description.descriptor_set_bindings.to_vec(),
+    );
+    app_impl.on_resize(base_app, base_app.swapchain_extent);
+}
@@ -0,0 +1,161 @@
+//! Derive macros for `vk_engine`, re-exported from `vk_engine::engine_core`
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Member};
+
+/** Implements `Std140` for a `#[repr(C)]` struct whose fields all implement it.
+Fails to compile if a field's offset or the struct's size differs from the std140 layout GLSL gives the same struct,
+which is fixed by adding explicit padding fields or using aligned types such as `Vec3A`. */
+#[proc_macro_derive(Std140)]
+pub fn derive_std140(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_layout(input, Layout::Std140)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Same as `Std140`, for the std430 layout of storage buffers
+#[proc_macro_derive(Std430)]
+pub fn derive_std430(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive_layout(input, Layout::Std430)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Clone, Copy)]
+enum Layout {
+    Std140,
+    Std430,
+}
+
+impl Layout {
+    fn name(self) -> &'static str {
+        match self {
+            Layout::Std140 => "std140",
+            Layout::Std430 => "std430",
+        }
+    }
+
+    // Structs in std140 are aligned to at least a vec4
+    fn minimum_struct_alignment(self) -> usize {
+        match self {
+            Layout::Std140 => 16,
+            Layout::Std430 => 1,
+        }
+    }
+}
+
+fn derive_layout(input: DeriveInput, layout: Layout) -> syn::Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            format!("{} can not be derived for generic types", layout.name()),
+        ));
+    }
+    if !has_repr_c(&input) {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            format!(
+                "{} requires #[repr(C)], otherwise the field order is unspecified",
+                layout.name()
+            ),
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            Fields::Unnamed(fields) => &fields.unnamed,
+            Fields::Unit => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    format!("{} can not be derived for unit structs", layout.name()),
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                format!("{} can only be derived for structs", layout.name()),
+            ))
+        }
+    };
+
+    let name = &input.ident;
+    let (trait_name, alignment) = match layout {
+        Layout::Std140 => (format_ident!("Std140"), format_ident!("STD140_ALIGNMENT")),
+        Layout::Std430 => (format_ident!("Std430"), format_ident!("STD430_ALIGNMENT")),
+    };
+    let trait_path = quote!(::vk_engine::engine_core::#trait_name);
+    let minimum_alignment = layout.minimum_struct_alignment();
+
+    let field_types: Vec<_> = fields.iter().map(|field| &field.ty).collect();
+    // Lays the fields out as GLSL does, each at the end of the previous one rounded up to its alignment
+    let offset_checks = fields.iter().enumerate().map(|(i, field)| {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(i.into()),
+        };
+        let ty = &field.ty;
+        let message = format!(
+            "{}: field `{}` of `{}` is not at its {} offset, add padding before it or use a type with the {} alignment",
+            layout.name(),
+            quote!(#member),
+            name,
+            layout.name(),
+            layout.name()
+        );
+        quote! {
+            offset = ::vk_engine::engine_core::align_offset(offset, <#ty as #trait_path>::#alignment);
+            assert!(::core::mem::offset_of!(#name, #member) == offset, #message);
+            offset += ::core::mem::size_of::<#ty>();
+        }
+    });
+    let size_message = format!(
+        "{}: size of `{}` does not match its {} size, add padding at the end",
+        layout.name(),
+        name,
+        layout.name()
+    );
+
+    Ok(quote! {
+        unsafe impl #trait_path for #name {
+            const #alignment: usize = ::vk_engine::engine_core::max_alignment(
+                &[#(<#field_types as #trait_path>::#alignment),*],
+                #minimum_alignment,
+            );
+        }
+
+        const _: () = {
+            let mut offset: usize = 0;
+            #(#offset_checks)*
+            assert!(
+                ::core::mem::size_of::<#name>()
+                    == ::vk_engine::engine_core::align_offset(offset, <#name as #trait_path>::#alignment),
+                #size_message
+            );
+        };
+    })
+}
+
+fn has_repr_c(input: &DeriveInput) -> bool {
+    input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("repr"))
+        .any(|attr| {
+            let mut is_c = false;
+            let _ = attr.parse_nested_meta(|meta| {
+                is_c |= meta.path.is_ident("C");
+                // Skip the arguments of e.g. align(16)
+                if meta.input.peek(syn::token::Paren) {
+                    let _content;
+                    syn::parenthesized!(_content in meta.input);
+                }
+                Ok(())
+            });
+            is_c
+        })
+}